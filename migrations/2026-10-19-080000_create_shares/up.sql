@@ -0,0 +1,10 @@
+CREATE TABLE shares (
+    id UUID PRIMARY KEY,
+    board UUID NOT NULL,
+    token TEXT NOT NULL UNIQUE,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    expires_at TIMESTAMPTZ,
+    hidden_fields TEXT [] NOT NULL DEFAULT '{}',
+
+    CONSTRAINT fk_board FOREIGN KEY (board) REFERENCES boards (id)
+)
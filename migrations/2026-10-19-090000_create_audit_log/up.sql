@@ -0,0 +1,11 @@
+ALTER TABLE users ADD COLUMN admin BOOLEAN NOT NULL DEFAULT FALSE;
+
+-- No foreign keys, entries have to outlive the users they reference
+CREATE TABLE audit_log (
+    id UUID PRIMARY KEY,
+    actor UUID NOT NULL,
+    subject UUID NOT NULL,
+    method TEXT NOT NULL,
+    path TEXT NOT NULL,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
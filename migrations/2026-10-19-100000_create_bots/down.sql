@@ -0,0 +1,15 @@
+DROP TABLE board_members;
+
+DROP TABLE api_keys;
+
+DELETE FROM users WHERE bot;
+
+ALTER TABLE users
+    DROP CONSTRAINT bot_owned,
+    DROP CONSTRAINT bot_credentials,
+    DROP CONSTRAINT fk_bot_owner,
+    DROP COLUMN bot_owner,
+    DROP COLUMN bot,
+    DROP COLUMN name,
+    ALTER COLUMN password SET NOT NULL,
+    ALTER COLUMN mail SET NOT NULL;
@@ -0,0 +1,31 @@
+ALTER TABLE users
+    ALTER COLUMN mail DROP NOT NULL,
+    ALTER COLUMN password DROP NOT NULL,
+    ADD COLUMN name TEXT,
+    ADD COLUMN bot BOOLEAN NOT NULL DEFAULT FALSE,
+    ADD COLUMN bot_owner UUID,
+    ADD CONSTRAINT fk_bot_owner FOREIGN KEY (bot_owner) REFERENCES users (id) ON DELETE CASCADE,
+    -- Bots are the only accounts allowed to exist without login credentials
+    ADD CONSTRAINT bot_credentials CHECK (bot OR (mail IS NOT NULL AND password IS NOT NULL)),
+    ADD CONSTRAINT bot_owned CHECK (bot = (bot_owner IS NOT NULL));
+
+CREATE TABLE api_keys (
+    id UUID PRIMARY KEY,
+    account UUID NOT NULL,
+    name TEXT NOT NULL,
+    hash TEXT NOT NULL,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    last_used_at TIMESTAMPTZ,
+
+    CONSTRAINT fk_account FOREIGN KEY (account) REFERENCES users (id) ON DELETE CASCADE
+);
+
+CREATE TABLE board_members (
+    board UUID NOT NULL,
+    member UUID NOT NULL,
+    role TEXT NOT NULL CHECK (role IN ('viewer', 'editor', 'admin')),
+
+    PRIMARY KEY (board, member),
+    CONSTRAINT fk_board FOREIGN KEY (board) REFERENCES boards (id) ON DELETE CASCADE,
+    CONSTRAINT fk_member FOREIGN KEY (member) REFERENCES users (id) ON DELETE CASCADE
+);
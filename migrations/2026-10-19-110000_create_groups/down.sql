@@ -0,0 +1,9 @@
+DROP TABLE group_members;
+
+DROP TABLE groups;
+
+DELETE FROM users WHERE NOT bot AND password IS NULL;
+
+ALTER TABLE users
+    DROP CONSTRAINT bot_credentials,
+    ADD CONSTRAINT bot_credentials CHECK (bot OR (mail IS NOT NULL AND password IS NOT NULL));
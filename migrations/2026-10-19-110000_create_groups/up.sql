@@ -0,0 +1,22 @@
+-- Accounts provisioned by an external directory don't have a local password
+ALTER TABLE users
+    DROP CONSTRAINT bot_credentials,
+    ADD CONSTRAINT bot_credentials CHECK (bot OR mail IS NOT NULL);
+
+CREATE TABLE groups (
+    id UUID PRIMARY KEY,
+    name TEXT NOT NULL,
+    origin TEXT NOT NULL DEFAULT 'local',
+    external_id TEXT,
+
+    UNIQUE (origin, name)
+);
+
+CREATE TABLE group_members (
+    group_id UUID NOT NULL,
+    member UUID NOT NULL,
+
+    PRIMARY KEY (group_id, member),
+    CONSTRAINT fk_group FOREIGN KEY (group_id) REFERENCES groups (id) ON DELETE CASCADE,
+    CONSTRAINT fk_member FOREIGN KEY (member) REFERENCES users (id) ON DELETE CASCADE
+);
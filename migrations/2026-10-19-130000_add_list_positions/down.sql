@@ -0,0 +1,3 @@
+DROP INDEX lists_board_position;
+
+ALTER TABLE lists DROP COLUMN position;
@@ -0,0 +1,14 @@
+-- Ranks are compared byte by byte, see `rank.rs`
+ALTER TABLE lists ADD COLUMN position TEXT COLLATE "C";
+
+UPDATE lists
+SET position = ranked.position
+FROM (
+    SELECT id, lpad((row_number() OVER (PARTITION BY board ORDER BY id))::text, 8, '0') || 'V' AS position
+    FROM lists
+) AS ranked
+WHERE lists.id = ranked.id;
+
+ALTER TABLE lists ALTER COLUMN position SET NOT NULL;
+
+CREATE INDEX lists_board_position ON lists (board, position);
@@ -0,0 +1,3 @@
+DROP INDEX cards_list_position;
+
+ALTER TABLE cards DROP COLUMN position;
@@ -0,0 +1,14 @@
+-- Ranks are compared byte by byte, see `rank.rs`
+ALTER TABLE cards ADD COLUMN position TEXT COLLATE "C";
+
+UPDATE cards
+SET position = ranked.position
+FROM (
+    SELECT id, lpad((row_number() OVER (PARTITION BY list ORDER BY id))::text, 8, '0') || 'V' AS position
+    FROM cards
+) AS ranked
+WHERE cards.id = ranked.id;
+
+ALTER TABLE cards ALTER COLUMN position SET NOT NULL;
+
+CREATE INDEX cards_list_position ON cards (list, position);
@@ -0,0 +1,8 @@
+UPDATE cards
+SET description = CASE
+    WHEN description IS NULL THEN NULLIF(title, '')
+    ELSE title || E'\n' || description
+END;
+
+ALTER TABLE cards DROP COLUMN title;
+ALTER TABLE cards RENAME COLUMN description TO content;
@@ -0,0 +1,10 @@
+ALTER TABLE cards RENAME COLUMN content TO description;
+ALTER TABLE cards ADD COLUMN title TEXT NOT NULL DEFAULT '';
+
+-- The first line of the old content becomes the title, the rest the description
+UPDATE cards
+SET title = split_part(description, E'\n', 1),
+    description = NULLIF(substr(description, length(split_part(description, E'\n', 1)) + 2), '')
+WHERE description IS NOT NULL;
+
+ALTER TABLE cards ALTER COLUMN title DROP DEFAULT;
@@ -0,0 +1,7 @@
+DROP TABLE notifications;
+DROP TABLE reminders;
+
+ALTER TABLE cards DROP CONSTRAINT card_dates;
+ALTER TABLE cards DROP COLUMN completed;
+ALTER TABLE cards DROP COLUMN due_at;
+ALTER TABLE cards DROP COLUMN start_at;
@@ -0,0 +1,27 @@
+ALTER TABLE cards ADD COLUMN start_at TIMESTAMPTZ;
+ALTER TABLE cards ADD COLUMN due_at TIMESTAMPTZ;
+ALTER TABLE cards ADD COLUMN completed BOOLEAN NOT NULL DEFAULT FALSE;
+ALTER TABLE cards ADD CONSTRAINT card_dates CHECK (start_at <= due_at);
+
+CREATE INDEX cards_due_at ON cards (due_at) WHERE NOT completed;
+
+CREATE TABLE reminders (
+    card UUID NOT NULL REFERENCES cards (id) ON DELETE CASCADE,
+    account UUID NOT NULL REFERENCES users (id) ON DELETE CASCADE,
+    minutes_before INTEGER NOT NULL CHECK (minutes_before >= 0),
+    -- Cleared when the due date changes, so the reminder fires again
+    sent_at TIMESTAMPTZ,
+
+    PRIMARY KEY (card, account)
+);
+
+CREATE TABLE notifications (
+    id UUID PRIMARY KEY,
+    account UUID NOT NULL REFERENCES users (id) ON DELETE CASCADE,
+    card UUID NOT NULL REFERENCES cards (id) ON DELETE CASCADE,
+    message TEXT NOT NULL,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    read_at TIMESTAMPTZ
+);
+
+CREATE INDEX notifications_account ON notifications (account, created_at);
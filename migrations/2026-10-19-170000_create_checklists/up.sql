@@ -0,0 +1,21 @@
+CREATE TABLE checklists (
+    id UUID PRIMARY KEY,
+    card UUID NOT NULL REFERENCES cards (id) ON DELETE CASCADE,
+    name TEXT NOT NULL,
+    -- Ranks are compared byte by byte, see `rank.rs`
+    position TEXT COLLATE "C" NOT NULL
+);
+
+CREATE INDEX checklists_card_position ON checklists (card, position);
+
+CREATE TABLE checklist_items (
+    id UUID PRIMARY KEY,
+    checklist UUID NOT NULL REFERENCES checklists (id) ON DELETE CASCADE,
+    content TEXT NOT NULL,
+    checked BOOLEAN NOT NULL DEFAULT FALSE,
+    assignee UUID REFERENCES users (id) ON DELETE SET NULL,
+    due_at TIMESTAMPTZ,
+    position TEXT COLLATE "C" NOT NULL
+);
+
+CREATE INDEX checklist_items_checklist_position ON checklist_items (checklist, position);
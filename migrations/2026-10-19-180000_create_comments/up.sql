@@ -0,0 +1,11 @@
+CREATE TABLE comments (
+    id UUID PRIMARY KEY,
+    card UUID NOT NULL REFERENCES cards (id) ON DELETE CASCADE,
+    -- Comments stay in the discussion when their author is deleted
+    author UUID REFERENCES users (id) ON DELETE SET NULL,
+    content TEXT NOT NULL,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    edited_at TIMESTAMPTZ
+);
+
+CREATE INDEX comments_card_created_at ON comments (card, created_at);
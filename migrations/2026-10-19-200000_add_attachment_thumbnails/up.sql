@@ -0,0 +1,2 @@
+-- Edge lengths of the generated thumbnails
+ALTER TABLE attachments ADD COLUMN thumbnails INTEGER[] NOT NULL DEFAULT '{}';
@@ -0,0 +1,12 @@
+ALTER TABLE cards ADD COLUMN labels TEXT[];
+
+UPDATE cards
+SET labels = (
+    SELECT array_agg(labels.name ORDER BY labels.name)
+    FROM card_labels
+    JOIN labels ON labels.id = card_labels.label
+    WHERE card_labels.card = cards.id
+);
+
+DROP TABLE card_labels;
+DROP TABLE labels;
@@ -0,0 +1,35 @@
+CREATE TABLE labels (
+    id UUID PRIMARY KEY,
+    board UUID NOT NULL REFERENCES boards (id) ON DELETE CASCADE,
+    name TEXT NOT NULL,
+    -- Hex code like `#61bd4f`
+    color TEXT NOT NULL CHECK (color ~ '^#[0-9a-f]{6}$'),
+    description TEXT,
+
+    UNIQUE (board, name)
+);
+
+CREATE TABLE card_labels (
+    card UUID NOT NULL REFERENCES cards (id) ON DELETE CASCADE,
+    label UUID NOT NULL REFERENCES labels (id) ON DELETE CASCADE,
+
+    PRIMARY KEY (card, label)
+);
+
+CREATE INDEX card_labels_label ON card_labels (label);
+
+-- Every distinct label string of a board becomes a grey label. The ids are derived
+-- from the board and the name, so the cards can be linked up below.
+INSERT INTO labels (id, board, name, color)
+SELECT DISTINCT md5(lists.board || ':' || label)::uuid, lists.board, label, '#808080'
+FROM cards
+JOIN lists ON lists.id = cards.list
+CROSS JOIN unnest(cards.labels) AS label;
+
+INSERT INTO card_labels (card, label)
+SELECT DISTINCT cards.id, md5(lists.board || ':' || label)::uuid
+FROM cards
+JOIN lists ON lists.id = cards.list
+CROSS JOIN unnest(cards.labels) AS label;
+
+ALTER TABLE cards DROP COLUMN labels;
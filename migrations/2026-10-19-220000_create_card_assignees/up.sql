@@ -0,0 +1,8 @@
+CREATE TABLE card_assignees (
+    card UUID NOT NULL REFERENCES cards (id) ON DELETE CASCADE,
+    account UUID NOT NULL REFERENCES users (id) ON DELETE CASCADE,
+
+    PRIMARY KEY (card, account)
+);
+
+CREATE INDEX card_assignees_account ON card_assignees (account);
@@ -0,0 +1,2 @@
+ALTER TABLE cards DROP COLUMN estimate;
+ALTER TABLE cards DROP COLUMN priority;
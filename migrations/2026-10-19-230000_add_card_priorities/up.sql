@@ -0,0 +1,4 @@
+-- 0 = low, 1 = medium, 2 = high, 3 = urgent
+ALTER TABLE cards ADD COLUMN priority SMALLINT CHECK (priority BETWEEN 0 AND 3);
+-- Story points
+ALTER TABLE cards ADD COLUMN estimate DOUBLE PRECISION CHECK (estimate >= 0);
@@ -0,0 +1,2 @@
+DROP TABLE card_fields;
+DROP TABLE custom_fields;
@@ -0,0 +1,20 @@
+CREATE TABLE custom_fields (
+    id UUID PRIMARY KEY,
+    board UUID NOT NULL REFERENCES boards (id) ON DELETE CASCADE,
+    name TEXT NOT NULL,
+    kind TEXT NOT NULL,
+    -- Choices of select fields
+    options TEXT[] NOT NULL DEFAULT '{}',
+
+    UNIQUE (board, name)
+);
+
+CREATE TABLE card_fields (
+    card UUID NOT NULL REFERENCES cards (id) ON DELETE CASCADE,
+    field UUID NOT NULL REFERENCES custom_fields (id) ON DELETE CASCADE,
+    value JSONB NOT NULL,
+
+    PRIMARY KEY (card, field)
+);
+
+CREATE INDEX card_fields_field ON card_fields (field);
@@ -0,0 +1,3 @@
+ALTER TABLE lists ADD COLUMN wip_limit INTEGER CHECK (wip_limit > 0);
+-- Soft limits only flag the list instead of rejecting cards
+ALTER TABLE lists ADD COLUMN wip_soft BOOLEAN NOT NULL DEFAULT FALSE;
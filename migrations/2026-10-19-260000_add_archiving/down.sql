@@ -0,0 +1,3 @@
+ALTER TABLE cards DROP COLUMN archived_at;
+ALTER TABLE lists DROP COLUMN archived_at;
+ALTER TABLE boards DROP COLUMN archived_at;
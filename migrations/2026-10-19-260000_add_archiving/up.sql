@@ -0,0 +1,3 @@
+ALTER TABLE boards ADD COLUMN archived_at TIMESTAMPTZ;
+ALTER TABLE lists ADD COLUMN archived_at TIMESTAMPTZ;
+ALTER TABLE cards ADD COLUMN archived_at TIMESTAMPTZ;
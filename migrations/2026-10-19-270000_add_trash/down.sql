@@ -0,0 +1,3 @@
+ALTER TABLE cards DROP COLUMN deleted_at, DROP COLUMN deleted_by;
+ALTER TABLE lists DROP COLUMN deleted_at, DROP COLUMN deleted_by;
+ALTER TABLE boards DROP COLUMN deleted_at, DROP COLUMN deleted_by;
@@ -0,0 +1,9 @@
+ALTER TABLE boards
+    ADD COLUMN deleted_at TIMESTAMPTZ,
+    ADD COLUMN deleted_by UUID REFERENCES users (id) ON DELETE SET NULL;
+ALTER TABLE lists
+    ADD COLUMN deleted_at TIMESTAMPTZ,
+    ADD COLUMN deleted_by UUID REFERENCES users (id) ON DELETE SET NULL;
+ALTER TABLE cards
+    ADD COLUMN deleted_at TIMESTAMPTZ,
+    ADD COLUMN deleted_by UUID REFERENCES users (id) ON DELETE SET NULL;
@@ -0,0 +1,9 @@
+ALTER TABLE shares
+    DROP CONSTRAINT fk_board,
+    ADD CONSTRAINT fk_board FOREIGN KEY (board) REFERENCES boards (id);
+ALTER TABLE cards
+    DROP CONSTRAINT fk_list,
+    ADD CONSTRAINT fk_list FOREIGN KEY (list) REFERENCES lists (id);
+ALTER TABLE lists
+    DROP CONSTRAINT fk_board,
+    ADD CONSTRAINT fk_board FOREIGN KEY (board) REFERENCES boards (id);
@@ -0,0 +1,11 @@
+-- Deletes are cascaded explicitly so their results can be counted, these keep
+-- the hierarchy consistent if rows are removed by other means
+ALTER TABLE lists
+    DROP CONSTRAINT fk_board,
+    ADD CONSTRAINT fk_board FOREIGN KEY (board) REFERENCES boards (id) ON DELETE CASCADE;
+ALTER TABLE cards
+    DROP CONSTRAINT fk_list,
+    ADD CONSTRAINT fk_list FOREIGN KEY (list) REFERENCES lists (id) ON DELETE CASCADE;
+ALTER TABLE shares
+    DROP CONSTRAINT fk_board,
+    ADD CONSTRAINT fk_board FOREIGN KEY (board) REFERENCES boards (id) ON DELETE CASCADE;
@@ -0,0 +1,12 @@
+CREATE TABLE templates (
+    id UUID PRIMARY KEY,
+    owner UUID NOT NULL REFERENCES users (id) ON DELETE CASCADE,
+    name TEXT NOT NULL,
+    description TEXT,
+    public BOOLEAN NOT NULL DEFAULT FALSE,
+    -- Snapshot of the lists, labels, custom fields and optionally cards of a board
+    content JSONB NOT NULL,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX templates_owner ON templates (owner);
@@ -0,0 +1,200 @@
+use actix_web::web::Data;
+use ldap3::{ldap_escape, LdapConn, LdapConnSettings, LdapError, Scope, SearchEntry};
+use parse_duration::parse;
+use std::{env, time::Duration};
+
+use super::AuthBackend;
+use crate::{
+    errors::ServiceError,
+    models::{Group, User},
+    DbPool,
+};
+
+/// Origin of groups synchronized from the directory
+const ORIGIN: &str = "ldap";
+
+/// Authenticates users by binding to an LDAP directory.
+///
+/// Users are provisioned on their first login and their group memberships
+/// are synchronized on every login. Only accounts the backend provisioned
+/// itself are linked to directory entries, so an entry can't take over a
+/// local account that happens to have the same mail address.
+pub struct LdapBackend {
+    url: String,
+    base_dn: String,
+    bind_dn: Option<String>,
+    bind_password: Option<String>,
+    /// Filter used to find the user entry, `{username}` is replaced with the login
+    user_filter: String,
+    /// Filter used to find groups of a user, `{dn}` is replaced with the user's DN
+    group_filter: String,
+    mail_attribute: String,
+    name_attribute: String,
+    /// Limit for connecting and for every single operation
+    timeout: Duration,
+}
+
+/// Entry of a user who authenticated against the directory
+#[derive(Debug)]
+pub struct DirectoryUser {
+    pub dn: String,
+    pub mail: Option<String>,
+    pub name: Option<String>,
+    /// Common names of the groups of the user
+    pub groups: Vec<String>,
+}
+
+impl LdapBackend {
+    pub fn from_env() -> Self {
+        let var_or = |name: &str, default: &str| env::var(name).unwrap_or_else(|_| default.into());
+
+        Self {
+            url: env::var("LDAP_URL").expect("LDAP_URL must be set"),
+            base_dn: env::var("LDAP_BASE_DN").expect("LDAP_BASE_DN must be set"),
+            bind_dn: env::var("LDAP_BIND_DN").ok(),
+            bind_password: env::var("LDAP_BIND_PASSWORD").ok(),
+            user_filter: var_or("LDAP_USER_FILTER", "(uid={username})"),
+            group_filter: var_or("LDAP_GROUP_FILTER", "(member={dn})"),
+            mail_attribute: var_or("LDAP_MAIL_ATTRIBUTE", "mail"),
+            name_attribute: var_or("LDAP_NAME_ATTRIBUTE", "cn"),
+            timeout: parse(&var_or("LDAP_TIMEOUT", "5s"))
+                .expect("LDAP_TIMEOUT must be a valid duration"),
+        }
+    }
+
+    fn connect(&self) -> Result<LdapConn, LdapError> {
+        let settings = LdapConnSettings::new().set_conn_timeout(self.timeout);
+
+        LdapConn::with_settings(settings, &self.url)
+    }
+
+    /// Binds with the service account, if one is configured
+    fn bind_service(&self, conn: &mut LdapConn) -> Result<(), LdapError> {
+        if let (Some(dn), Some(password)) = (&self.bind_dn, &self.bind_password) {
+            conn.with_timeout(self.timeout)
+                .simple_bind(dn, password)?
+                .success()?;
+        }
+
+        Ok(())
+    }
+
+    fn find_user(
+        &self,
+        conn: &mut LdapConn,
+        username: &str,
+    ) -> Result<Option<SearchEntry>, LdapError> {
+        let filter = self
+            .user_filter
+            .replace("{username}", &ldap_escape(username));
+        let attrs = vec![self.mail_attribute.as_str(), self.name_attribute.as_str()];
+
+        let (entries, _) = conn
+            .with_timeout(self.timeout)
+            .search(&self.base_dn, Scope::Subtree, &filter, attrs)?
+            .success()?;
+
+        // Ambiguous logins are treated the same as unknown ones
+        if entries.len() != 1 {
+            return Ok(None);
+        }
+
+        Ok(entries.into_iter().next().map(SearchEntry::construct))
+    }
+
+    fn find_groups(&self, conn: &mut LdapConn, dn: &str) -> Result<Vec<String>, LdapError> {
+        let filter = self.group_filter.replace("{dn}", &ldap_escape(dn));
+
+        let (entries, _) = conn
+            .with_timeout(self.timeout)
+            .search(&self.base_dn, Scope::Subtree, &filter, vec!["cn"])?
+            .success()?;
+
+        Ok(entries
+            .into_iter()
+            .map(SearchEntry::construct)
+            .filter_map(|e| e.attrs.get("cn").and_then(|cn| cn.first().cloned()))
+            .collect())
+    }
+
+    /// Checks the credentials against the directory and returns the entry of the user.
+    /// Blocks until the directory answers or the timeout is reached.
+    pub fn lookup(&self, username: &str, password: &str) -> Result<DirectoryUser, ServiceError> {
+        // Binding with an empty password is an unauthenticated bind, which always succeeds
+        if password.is_empty() {
+            return Err(ServiceError::InvalidCredentials);
+        }
+
+        let mut conn = self
+            .connect()
+            .map_err(|_| ServiceError::InternalServerError)?;
+        self.bind_service(&mut conn)
+            .map_err(|_| ServiceError::InternalServerError)?;
+
+        let entry = self
+            .find_user(&mut conn, username)
+            .map_err(|_| ServiceError::InternalServerError)?
+            .ok_or(ServiceError::InvalidCredentials)?;
+
+        conn.with_timeout(self.timeout)
+            .simple_bind(&entry.dn, password)
+            .and_then(|r| r.success())
+            .map_err(|_| ServiceError::InvalidCredentials)?;
+
+        // Searching for groups may not be permitted for regular users
+        self.bind_service(&mut conn)
+            .map_err(|_| ServiceError::InternalServerError)?;
+        let groups = self
+            .find_groups(&mut conn, &entry.dn)
+            .map_err(|_| ServiceError::InternalServerError)?;
+        let _ = conn.unbind();
+
+        let first = |attr: &str| entry.attrs.get(attr).and_then(|v| v.first().cloned());
+
+        Ok(DirectoryUser {
+            mail: first(&self.mail_attribute),
+            name: first(&self.name_attribute),
+            dn: entry.dn,
+            groups,
+        })
+    }
+
+    /// Returns the account linked to the entry, creating it on the first login
+    fn provision(&self, pool: &Data<DbPool>, entry: &DirectoryUser) -> Result<User, ServiceError> {
+        if let Some(user) = User::find_by_external_id(pool, &entry.dn)? {
+            return user.ensure_active();
+        }
+
+        let mail = entry
+            .mail
+            .clone()
+            .ok_or(ServiceError::InternalServerError)?;
+
+        // Accounts that weren't created from the directory are never linked to it
+        if User::find_by_mail(pool, &mail)?.is_some() {
+            return Err(ServiceError::UnlinkedAccount);
+        }
+
+        let mut user = User::new_external(mail, entry.name.clone());
+        user.external_id = Some(entry.dn.clone());
+        user.save(pool)?;
+
+        Ok(user)
+    }
+}
+
+impl AuthBackend for LdapBackend {
+    fn authenticate(
+        &self,
+        pool: &Data<DbPool>,
+        username: &str,
+        password: &str,
+    ) -> Result<User, ServiceError> {
+        let entry = self.lookup(username, password)?;
+
+        let user = self.provision(pool, &entry)?;
+        Group::sync_memberships(pool, &user, ORIGIN, &entry.groups)?;
+
+        Ok(user)
+    }
+}
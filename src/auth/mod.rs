@@ -0,0 +1,56 @@
+use actix_web::web::Data;
+use argon2::{Argon2, PasswordHash, PasswordVerifier};
+use diesel::{prelude::*, result::Error as DieselError};
+
+use crate::{errors::ServiceError, get_conn, models::User, schema::users, DbPool};
+
+mod ldap;
+
+pub use ldap::{DirectoryUser, LdapBackend};
+
+/// Verifies user credentials during login
+pub trait AuthBackend: Send + Sync {
+    /// Returns the user the credentials belong to
+    fn authenticate(
+        &self,
+        pool: &Data<DbPool>,
+        username: &str,
+        password: &str,
+    ) -> Result<User, ServiceError>;
+}
+
+/// Checks passwords against the Argon2 hashes stored in the users table
+pub struct DatabaseBackend;
+
+impl AuthBackend for DatabaseBackend {
+    fn authenticate(
+        &self,
+        pool: &Data<DbPool>,
+        username: &str,
+        password: &str,
+    ) -> Result<User, ServiceError> {
+        let conn = get_conn(pool)?;
+
+        let user = users::table
+            .filter(users::mail.eq(username))
+            .filter(users::password.is_not_null())
+            .filter(users::active.eq(true))
+            .first::<User>(&conn)
+            .map_err(|e| match e {
+                DieselError::NotFound => ServiceError::InvalidCredentials,
+                _ => ServiceError::InternalServerError,
+            })?;
+
+        let password_hash = user
+            .password
+            .as_deref()
+            .ok_or(ServiceError::InvalidCredentials)?;
+        let password_hash = PasswordHash::new(password_hash).unwrap();
+
+        Argon2::default()
+            .verify_password(password.as_bytes(), &password_hash)
+            .map_err(|_| ServiceError::InvalidCredentials)?;
+
+        Ok(user)
+    }
+}
@@ -1,12 +1,20 @@
-use std::{env, io};
+use std::{env, io, sync::Arc};
 
-use actix_web::{middleware::Logger, App, HttpServer};
-use backend::{routes::config, JWTConfig};
+use actix_web::{dev::Service, middleware::Logger, web::Data, App, HttpServer};
+use backend::{
+    auth::{AuthBackend, DatabaseBackend, LdapBackend},
+    jobs,
+    models::AuditEntry,
+    routes::config,
+    storage::{BlobStore, LocalStore, S3Store},
+    AttachmentConfig, JWTConfig, ScimConfig, TrashConfig,
+};
 use diesel::{
     prelude::*,
     r2d2::{ConnectionManager, Pool},
 };
 use dotenv::dotenv;
+use parse_duration::parse;
 
 #[actix_web::main]
 async fn main() -> io::Result<()> {
@@ -27,13 +35,65 @@ async fn main() -> io::Result<()> {
 
     let jwt_secret = env::var("JWT_SECRET").expect("JWT_SECRET must be set");
     let jwt_expiry = env::var("JWT_EXPIRY").expect("JWT_EXPIRY must be set");
-    let jwt_config = JWTConfig::new(jwt_secret, jwt_expiry);
+    let impersonation_expiry =
+        env::var("IMPERSONATION_EXPIRY").expect("IMPERSONATION_EXPIRY must be set");
+    let jwt_config = JWTConfig::new(jwt_secret.clone(), jwt_expiry, impersonation_expiry);
+
+    let scim_config = ScimConfig::new(env::var("SCIM_TOKEN").ok());
+
+    let auth_backend: Arc<dyn AuthBackend> = match env::var("AUTH_BACKEND").as_deref() {
+        Ok("database") | Err(_) => Arc::new(DatabaseBackend),
+        Ok("ldap") => Arc::new(LdapBackend::from_env()),
+        Ok(_) => panic!("AUTH_BACKEND must be either `database` or `ldap`"),
+    };
+
+    let attachment_config = AttachmentConfig::new(
+        env::var("ATTACHMENT_SECRET").unwrap_or_else(|_| AttachmentConfig::derive_key(&jwt_secret)),
+        env::var("ATTACHMENT_MAX_SIZE").unwrap_or_else(|_| "10485760".to_string()),
+        env::var("ATTACHMENT_TYPES")
+            .unwrap_or_else(|_| "image/*,application/pdf,text/plain".to_string()),
+        env::var("ATTACHMENT_URL_EXPIRY").unwrap_or_else(|_| "5m".to_string()),
+    );
+
+    let blob_store: Arc<dyn BlobStore> = match env::var("STORAGE_BACKEND").as_deref() {
+        Ok("local") | Err(_) => Arc::new(LocalStore::new(
+            env::var("STORAGE_PATH").unwrap_or_else(|_| "attachments".to_string()),
+        )),
+        Ok("s3") => Arc::new(S3Store::from_env()),
+        Ok(_) => panic!("STORAGE_BACKEND must be either `local` or `s3`"),
+    };
+
+    let reminder_interval = env::var("REMINDER_INTERVAL").unwrap_or_else(|_| "1m".to_string());
+    let reminder_interval =
+        parse(&reminder_interval).expect("REMINDER_INTERVAL must be a valid duration");
+    jobs::spawn_reminders(pool.clone(), reminder_interval);
+
+    let trash_config =
+        TrashConfig::new(env::var("TRASH_RETENTION").unwrap_or_else(|_| "30d".to_string()));
+    let purge_interval = env::var("TRASH_PURGE_INTERVAL").unwrap_or_else(|_| "1h".to_string());
+    let purge_interval =
+        parse(&purge_interval).expect("TRASH_PURGE_INTERVAL must be a valid duration");
+    jobs::spawn_trash_purge(
+        pool.clone(),
+        Data::from(blob_store.clone()),
+        trash_config.clone(),
+        purge_interval,
+    );
 
     HttpServer::new(move || {
         App::new()
+            .wrap_fn(|req, srv| {
+                let res = AuditEntry::check_request(&req).map(|_| srv.call(req));
+                async move { res?.await }
+            })
             .wrap(Logger::default())
             .data(pool.clone())
             .data(jwt_config.clone())
+            .data(scim_config.clone())
+            .data(attachment_config.clone())
+            .data(trash_config.clone())
+            .app_data(Data::from(auth_backend.clone()))
+            .app_data(Data::from(blob_store.clone()))
             .configure(config)
     })
     .bind(bind_url)?
@@ -1,5 +1,6 @@
 use actix_web::{http::StatusCode, HttpResponse, ResponseError};
 use derive_more::Display;
+use diesel::result::Error as DieselError;
 
 #[derive(Debug, Display)]
 pub enum ServiceError {
@@ -23,6 +24,57 @@ pub enum ServiceError {
 
     #[display(fmt = "Invalid user credentials")]
     InvalidCredentials,
+
+    #[display(fmt = "Account is deactivated")]
+    InactiveAccount,
+
+    #[display(fmt = "Account with this email exists and isn't managed by the directory")]
+    UnlinkedAccount,
+
+    #[display(fmt = "Action is not allowed while impersonating a user")]
+    ImpersonationForbidden,
+
+    #[display(fmt = "Invalid position, the neighbouring item doesn't exist")]
+    InvalidPosition,
+
+    #[display(fmt = "Field cannot be hidden: {}", _0)]
+    UnhideableField(String),
+
+    #[display(fmt = "Start date must not be after the due date")]
+    InvalidDates,
+
+    #[display(fmt = "File exceeds the maximum size of {} bytes", _0)]
+    FileTooLarge(usize),
+
+    #[display(fmt = "File type is not allowed: {}", _0)]
+    UnsupportedFileType(String),
+
+    #[display(fmt = "Estimate must be a non-negative number")]
+    InvalidEstimate,
+
+    #[display(fmt = "Label with this name already exists on the board")]
+    LabelExists,
+
+    #[display(fmt = "Invalid color, expected a hex code like #61bd4f: {}", _0)]
+    InvalidColor(String),
+
+    #[display(fmt = "Field with this name already exists on the board")]
+    FieldExists,
+
+    #[display(fmt = "Only select fields have options, which must be unique")]
+    InvalidFieldOptions,
+
+    #[display(fmt = "Invalid value for field: {}", _0)]
+    InvalidFieldValue(String),
+
+    #[display(fmt = "Work-in-progress limit must be at least 1")]
+    InvalidWipLimit,
+
+    #[display(fmt = "List has reached its work-in-progress limit of {} cards", _0)]
+    WipLimitReached(i32),
+
+    #[display(fmt = "Only archived items can be deleted")]
+    NotArchived,
 }
 
 impl ServiceError {
@@ -37,16 +89,41 @@ impl ServiceError {
     }
 }
 
+/// Allows using `?` on queries inside of transactions
+impl From<DieselError> for ServiceError {
+    fn from(_: DieselError) -> Self {
+        ServiceError::InternalServerError
+    }
+}
+
 impl ResponseError for ServiceError {
     fn status_code(&self) -> StatusCode {
         match self {
             ServiceError::InternalServerError => StatusCode::INTERNAL_SERVER_ERROR,
             ServiceError::UserExists => StatusCode::FORBIDDEN,
-            ServiceError::EmptyUpdate => StatusCode::BAD_REQUEST,
+            ServiceError::EmptyUpdate
+            | ServiceError::InvalidPosition
+            | ServiceError::UnhideableField(_)
+            | ServiceError::InvalidDates
+            | ServiceError::InvalidEstimate
+            | ServiceError::InvalidColor(_)
+            | ServiceError::InvalidFieldOptions
+            | ServiceError::InvalidFieldValue(_)
+            | ServiceError::InvalidWipLimit => StatusCode::BAD_REQUEST,
             ServiceError::MissingToken
             | ServiceError::InvalidToken
             | ServiceError::ExpiredToken
             | ServiceError::InvalidCredentials => StatusCode::UNAUTHORIZED,
+            ServiceError::InactiveAccount | ServiceError::ImpersonationForbidden => {
+                StatusCode::FORBIDDEN
+            }
+            ServiceError::FileTooLarge(_) => StatusCode::PAYLOAD_TOO_LARGE,
+            ServiceError::UnsupportedFileType(_) => StatusCode::UNSUPPORTED_MEDIA_TYPE,
+            ServiceError::UnlinkedAccount
+            | ServiceError::LabelExists
+            | ServiceError::FieldExists
+            | ServiceError::WipLimitReached(_)
+            | ServiceError::NotArchived => StatusCode::CONFLICT,
         }
     }
 
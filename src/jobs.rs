@@ -0,0 +1,54 @@
+//! Periodic background work running next to the HTTP server
+
+use std::{thread, time::Duration};
+
+use actix_web::{
+    rt::{self, time::delay_for},
+    web::{self, Data},
+};
+use log::error;
+
+use crate::{
+    models::{Reminder, TrashItem},
+    storage::{remove_blobs, BlobStore},
+    DbPool, TrashConfig,
+};
+
+/// Fires due card reminders every `interval` on a separate thread, so the blocking
+/// database calls don't stall the server
+pub fn spawn_reminders(pool: DbPool, interval: Duration) {
+    let pool = Data::new(pool);
+
+    thread::spawn(move || loop {
+        if let Err(e) = Reminder::fire_due(&pool) {
+            error!("Could not send reminders: {}", e);
+        }
+
+        thread::sleep(interval);
+    });
+}
+
+/// Purges items whose trash retention ended every `interval`. Runs on the actix runtime
+/// instead of a thread, since blobs of purged attachments are removed asynchronously.
+pub fn spawn_trash_purge(
+    pool: DbPool,
+    store: Data<dyn BlobStore>,
+    config: TrashConfig,
+    interval: Duration,
+) {
+    let pool = Data::new(pool);
+
+    rt::spawn(async move {
+        loop {
+            let cutoff = config.cutoff();
+            let pool = pool.clone();
+
+            match web::block(move || TrashItem::purge(&pool, cutoff)).await {
+                Ok(removed) => remove_blobs(&store, &removed.blobs).await,
+                Err(e) => error!("Could not purge the trash: {}", e),
+            }
+
+            delay_for(interval).await;
+        }
+    });
+}
@@ -1,20 +1,29 @@
+pub mod auth;
 pub mod errors;
+pub mod jobs;
+pub mod markdown;
 pub mod models;
+pub mod rank;
 pub mod routes;
 pub mod schema;
+pub mod storage;
+pub mod thumbnails;
 
 #[macro_use]
 extern crate diesel;
 
-use actix_web::{web::Data, HttpRequest};
-use chrono::{self, Duration};
+use actix_web::{dev::ServiceRequest, http::HeaderMap, web::Data, HttpRequest};
+use chrono::{self, DateTime, Duration, Utc};
 use diesel::{
     r2d2::{ConnectionManager, Pool, PooledConnection},
     PgConnection,
 };
+use hmac::{Hmac, Mac};
 use jsonwebtoken::{decode, errors::ErrorKind, DecodingKey, Validation};
 use parse_duration::parse;
 use serde::{Deserialize, Serialize};
+use sha2::Sha256;
+use uuid::Uuid;
 
 use errors::ServiceError;
 
@@ -29,14 +38,163 @@ pub fn get_conn(pool: &Data<DbPool>) -> Result<DbConn, ServiceError> {
 pub struct JWTConfig {
     key: String,
     expiry: Duration,
+    impersonation_expiry: Duration,
 }
 
 impl JWTConfig {
-    pub fn new(key: String, expiry: String) -> Self {
+    pub fn new(key: String, expiry: String, impersonation_expiry: String) -> Self {
         let expiry = parse(expiry.as_str()).expect("JWT_EXPIRY must be a valid duration");
         let expiry = Duration::from_std(expiry).unwrap();
 
-        Self { key, expiry }
+        let impersonation_expiry = parse(impersonation_expiry.as_str())
+            .expect("IMPERSONATION_EXPIRY must be a valid duration");
+        let impersonation_expiry = Duration::from_std(impersonation_expiry).unwrap();
+
+        Self {
+            key,
+            expiry,
+            impersonation_expiry,
+        }
+    }
+}
+
+/// Bearer token identity providers use to access the SCIM endpoints.
+/// SCIM provisioning is disabled when no token is set.
+#[derive(Debug, Clone)]
+pub struct ScimConfig {
+    token: Option<String>,
+}
+
+impl ScimConfig {
+    pub fn new(token: Option<String>) -> Self {
+        Self { token }
+    }
+}
+
+/// Limits for uploaded attachments and the key used to sign their download URLs
+#[derive(Debug, Clone)]
+pub struct AttachmentConfig {
+    key: String,
+    max_size: usize,
+    allowed_types: Vec<String>,
+    url_expiry: Duration,
+}
+
+impl AttachmentConfig {
+    /// `allowed_types` is a comma separated list of MIME types, `image/*` matches all images
+    pub fn new(key: String, max_size: String, allowed_types: String, url_expiry: String) -> Self {
+        let max_size = max_size
+            .parse()
+            .expect("ATTACHMENT_MAX_SIZE must be a number of bytes");
+
+        let allowed_types = allowed_types
+            .split(',')
+            .map(|t| t.trim().to_lowercase())
+            .filter(|t| !t.is_empty())
+            .collect();
+
+        let url_expiry =
+            parse(url_expiry.as_str()).expect("ATTACHMENT_URL_EXPIRY must be a valid duration");
+        let url_expiry = Duration::from_std(url_expiry).unwrap();
+
+        Self {
+            key,
+            max_size,
+            allowed_types,
+            url_expiry,
+        }
+    }
+
+    /// Derives the key used to sign download URLs from another secret, so the secret
+    /// itself is never used for more than one purpose
+    pub fn derive_key(secret: &str) -> String {
+        let mut mac = Hmac::<Sha256>::new_from_slice(secret.as_bytes())
+            .expect("HMAC accepts keys of any size");
+        mac.update(b"attachment download URLs");
+
+        hex::encode(mac.finalize().into_bytes())
+    }
+
+    pub fn allows_type(&self, content_type: &str) -> bool {
+        let content_type = content_type.to_lowercase();
+
+        self.allowed_types
+            .iter()
+            .any(|allowed| match allowed.strip_suffix("/*") {
+                Some(prefix) => content_type.split('/').next() == Some(prefix),
+                None => *allowed == content_type,
+            })
+    }
+
+    fn signature(&self, id: &Uuid, size: Option<u32>, expires: i64) -> String {
+        let mut mac = Hmac::<Sha256>::new_from_slice(self.key.as_bytes())
+            .expect("HMAC accepts keys of any size");
+        match size {
+            Some(size) => mac.update(format!("{}:{}:{}", id, size, expires).as_bytes()),
+            None => mac.update(format!("{}:{}", id, expires).as_bytes()),
+        }
+
+        hex::encode(mac.finalize().into_bytes())
+    }
+
+    /// Returns a download URL for the attachment, or one of its thumbnails if `size` is
+    /// set, that is valid for a short time
+    pub fn signed_url(&self, id: &Uuid, size: Option<u32>) -> String {
+        let expires = (Utc::now() + self.url_expiry).timestamp();
+        let signature = self.signature(id, size, expires);
+
+        match size {
+            Some(size) => format!(
+                "/attachments/{}?size={}&expires={}&signature={}",
+                id, size, expires, signature
+            ),
+            None => format!(
+                "/attachments/{}?expires={}&signature={}",
+                id, expires, signature
+            ),
+        }
+    }
+
+    /// Checks a signature from [`AttachmentConfig::signed_url`]
+    pub fn verify(&self, id: &Uuid, size: Option<u32>, expires: i64, signature: &str) -> bool {
+        let expected = self.signature(id, size, expires);
+
+        expires >= Utc::now().timestamp() && constant_time_eq(&expected, signature)
+    }
+}
+
+/// Compares secrets without leaking the position of the first difference through timing
+pub(crate) fn constant_time_eq(a: &str, b: &str) -> bool {
+    a.len() == b.len()
+        && a.bytes()
+            .zip(b.bytes())
+            .fold(0, |acc, (x, y)| acc | (x ^ y))
+            == 0
+}
+
+/// How long deleted boards, lists and cards stay in the trash before they're purged
+#[derive(Debug, Clone)]
+pub struct TrashConfig {
+    retention: Duration,
+}
+
+impl TrashConfig {
+    pub fn new(retention: String) -> Self {
+        let retention =
+            parse(retention.as_str()).expect("TRASH_RETENTION must be a valid duration");
+        let retention = Duration::from_std(retention).unwrap();
+
+        Self { retention }
+    }
+
+    /// Returns when an item deleted at `deleted_at` will be purged
+    pub fn purge_at(&self, deleted_at: DateTime<Utc>) -> DateTime<Utc> {
+        deleted_at + self.retention
+    }
+
+    /// Items deleted before the returned time are due for purging
+    pub fn cutoff(&self) -> DateTime<Utc> {
+        Utc::now() - self.retention
     }
 }
 
@@ -45,27 +203,38 @@ pub struct Claims {
     sub: String,
     iat: usize,
     exp: usize,
+    /// Id of the admin acting as `sub` during impersonation
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    act: Option<String>,
 }
 
 impl Claims {
     pub fn new(sub: String, expiry: Duration) -> Self {
-        let now = chrono::Utc::now();
+        let now = Utc::now();
         let exp = now + expiry;
 
         Self {
             sub,
             iat: now.timestamp() as usize,
             exp: exp.timestamp() as usize,
+            act: None,
         }
     }
-}
 
-impl TryFrom<&HttpRequest> for Claims {
-    type Error = ServiceError;
+    pub fn impersonate(sub: String, actor: String, expiry: Duration) -> Self {
+        Self {
+            act: Some(actor),
+            ..Self::new(sub, expiry)
+        }
+    }
 
-    fn try_from(req: &HttpRequest) -> Result<Self, Self::Error> {
-        let header = req
-            .headers()
+    pub fn is_impersonated(&self) -> bool {
+        self.act.is_some()
+    }
+
+    /// Decodes the bearer token in the `authorization` header
+    fn from_headers(headers: &HeaderMap, jwt_config: &JWTConfig) -> Result<Self, ServiceError> {
+        let header = headers
             .get("authorization")
             .ok_or(ServiceError::MissingToken)?
             .to_str()
@@ -73,7 +242,6 @@ impl TryFrom<&HttpRequest> for Claims {
         let token = header.replace("Bearer ", "");
         // If the token wasn't prefixed with `Bearer ` it will error during validation
 
-        let jwt_config = req.app_data::<Data<JWTConfig>>().unwrap();
         let validation = Validation {
             leeway: 60,
             ..Default::default()
@@ -85,11 +253,28 @@ impl TryFrom<&HttpRequest> for Claims {
             &validation,
         )
         .map_err(|e| match e.kind() {
-            ErrorKind::InvalidToken | ErrorKind::InvalidSignature => ServiceError::InvalidToken,
             ErrorKind::ExpiredSignature => ServiceError::ExpiredToken,
-            _ => panic!(),
+            _ => ServiceError::InvalidToken,
         });
 
         Ok(token_data?.claims)
     }
 }
+
+impl TryFrom<&HttpRequest> for Claims {
+    type Error = ServiceError;
+
+    fn try_from(req: &HttpRequest) -> Result<Self, Self::Error> {
+        let jwt_config = req.app_data::<Data<JWTConfig>>().unwrap();
+        Claims::from_headers(req.headers(), jwt_config)
+    }
+}
+
+impl TryFrom<&ServiceRequest> for Claims {
+    type Error = ServiceError;
+
+    fn try_from(req: &ServiceRequest) -> Result<Self, Self::Error> {
+        let jwt_config = req.app_data::<Data<JWTConfig>>().unwrap();
+        Claims::from_headers(req.headers(), jwt_config)
+    }
+}
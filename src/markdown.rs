@@ -0,0 +1,21 @@
+//! Server-side Markdown rendering, so all clients display card descriptions identically
+
+use pulldown_cmark::{html, Options, Parser};
+
+/// Renders Markdown to HTML, stripping anything that could run scripts or break out
+/// of the surrounding page
+pub fn render(source: &str) -> String {
+    let mut options = Options::empty();
+    options.insert(Options::ENABLE_STRIKETHROUGH);
+    options.insert(Options::ENABLE_TABLES);
+    options.insert(Options::ENABLE_TASKLISTS);
+
+    let mut unsafe_html = String::new();
+    html::push_html(&mut unsafe_html, Parser::new_ext(source, options));
+
+    ammonia::Builder::default()
+        .add_tags(&["input"])
+        .add_tag_attributes("input", &["type", "checked", "disabled"])
+        .clean(&unsafe_html)
+        .to_string()
+}
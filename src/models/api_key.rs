@@ -0,0 +1,103 @@
+use chrono::{DateTime, Duration, Utc};
+use rand_core::{OsRng, RngCore};
+use sha2::{Digest, Sha256};
+use std::str::FromStr;
+
+use super::prelude::*;
+use crate::{constant_time_eq, models::User, schema::api_keys};
+
+#[derive(Debug, Identifiable, Queryable, Insertable, Associations, Serialize)]
+#[belongs_to(User, foreign_key = "account")]
+#[table_name = "api_keys"]
+pub struct ApiKey {
+    pub id: Uuid,
+    pub account: Uuid,
+    pub name: String,
+    #[serde(skip_serializing)]
+    pub hash: String,
+    pub created_at: DateTime<Utc>,
+    /// Only updated once every few minutes while the key is in use
+    pub last_used_at: Option<DateTime<Utc>>,
+}
+
+impl ApiKey {
+    /// Creates a new key for `account`, returning it together with the plaintext secret.
+    /// The secret is only stored as a hash, so it can't be retrieved later.
+    pub fn generate(account: &User, name: String) -> (Self, String) {
+        let mut secret = [0u8; 32];
+        OsRng.fill_bytes(&mut secret);
+        let secret: String = secret.iter().map(|b| format!("{:02x}", b)).collect();
+
+        let key = ApiKey {
+            id: Uuid::new_v4(),
+            account: account.id,
+            name,
+            hash: ApiKey::hash(&secret),
+            created_at: Utc::now(),
+            last_used_at: None,
+        };
+        let token = format!("{}.{}", key.id.to_simple(), secret);
+
+        (key, token)
+    }
+
+    /// Secrets are 256 random bits, so unlike passwords they don't need a slow, salted hash
+    fn hash(secret: &str) -> String {
+        hex::encode(Sha256::digest(secret.as_bytes()))
+    }
+
+    /// Resolves the account a `<key id>.<secret>` token belongs to
+    pub fn authenticate(pool: &Data<DbPool>, token: &str) -> Result<User, ServiceError> {
+        let (id, secret) = token.split_once('.').ok_or(ServiceError::InvalidToken)?;
+        let id = Uuid::from_str(id).map_err(|_| ServiceError::InvalidToken)?;
+
+        let key = ApiKey::find(pool, id)?.ok_or(ServiceError::InvalidToken)?;
+
+        if !constant_time_eq(&ApiKey::hash(secret), &key.hash) {
+            return Err(ServiceError::InvalidToken);
+        }
+
+        // Recording every single use would cost a write per request
+        if !matches!(key.last_used_at, Some(at) if Utc::now() - at < Duration::minutes(5)) {
+            key.touch(pool)?;
+        }
+
+        User::find(pool, key.account)?.ok_or(ServiceError::InvalidToken)
+    }
+
+    pub fn save(&self, pool: &Data<DbPool>) -> Result<usize, ServiceError> {
+        let conn = get_conn(pool)?;
+
+        diesel::insert_into(api_keys::table)
+            .values(self)
+            .execute(&conn)
+            .map_err(|_| ServiceError::InternalServerError)
+    }
+
+    pub fn find(pool: &Data<DbPool>, id: Uuid) -> Result<Option<Self>, ServiceError> {
+        let conn = get_conn(pool)?;
+
+        api_keys::table
+            .find(id)
+            .first::<Self>(&conn)
+            .optional()
+            .map_err(|_| ServiceError::InternalServerError)
+    }
+
+    pub fn delete(&self, pool: &Data<DbPool>) -> Result<usize, ServiceError> {
+        let conn = get_conn(pool)?;
+
+        diesel::delete(self)
+            .execute(&conn)
+            .map_err(|_| ServiceError::InternalServerError)
+    }
+
+    fn touch(&self, pool: &Data<DbPool>) -> Result<usize, ServiceError> {
+        let conn = get_conn(pool)?;
+
+        diesel::update(self)
+            .set(api_keys::last_used_at.eq(Utc::now()))
+            .execute(&conn)
+            .map_err(|_| ServiceError::InternalServerError)
+    }
+}
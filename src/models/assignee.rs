@@ -0,0 +1,105 @@
+use diesel::{result::Error as DieselError, PgConnection};
+
+use super::prelude::*;
+use crate::{
+    models::{Card, User},
+    schema::{board_members, boards, card_assignees, cards, lists, users},
+};
+
+/// Assignment of a user to a card
+#[derive(Debug, Identifiable, Queryable, Insertable, Associations)]
+#[belongs_to(Card, foreign_key = "card")]
+#[primary_key(card, account)]
+#[table_name = "card_assignees"]
+pub struct Assignment {
+    pub card: Uuid,
+    pub account: Uuid,
+}
+
+/// Public details of an assignee embedded in cards
+#[derive(Debug, Queryable, Serialize)]
+pub struct Assignee {
+    pub id: Uuid,
+    pub name: Option<String>,
+    pub bot: bool,
+}
+
+impl Assignment {
+    pub fn new(card: &Card, account: &User) -> Self {
+        Assignment {
+            card: card.id,
+            account: account.id,
+        }
+    }
+
+    /// Assigning a user twice is not an error
+    pub fn save(&self, pool: &Data<DbPool>) -> Result<usize, ServiceError> {
+        let conn = get_conn(pool)?;
+
+        diesel::insert_into(card_assignees::table)
+            .values(self)
+            .on_conflict_do_nothing()
+            .execute(&conn)
+            .map_err(|_| ServiceError::InternalServerError)
+    }
+
+    /// Returns the assignees of the given cards as `(card, assignee)` pairs
+    pub(crate) fn for_cards(
+        conn: &PgConnection,
+        cards: &[Uuid],
+    ) -> Result<Vec<(Uuid, Assignee)>, DieselError> {
+        card_assignees::table
+            .inner_join(users::table)
+            .filter(card_assignees::card.eq_any(cards))
+            .order((users::name, users::id))
+            .select((card_assignees::card, (users::id, users::name, users::bot)))
+            .load::<(Uuid, Assignee)>(conn)
+    }
+
+    /// Assigns the assignees of card `from` to card `to` as well
+    pub(crate) fn copy_all(conn: &PgConnection, from: Uuid, to: Uuid) -> Result<(), DieselError> {
+        let copies = card_assignees::table
+            .filter(card_assignees::card.eq(from))
+            .select(card_assignees::account)
+            .load::<Uuid>(conn)?
+            .into_iter()
+            .map(|account| Assignment { card: to, account })
+            .collect::<Vec<_>>();
+
+        diesel::insert_into(card_assignees::table)
+            .values(&copies)
+            .execute(conn)?;
+
+        Ok(())
+    }
+
+    /// Unassigns users that lost access to `board` from its cards, e.g. after cards were
+    /// moved there or a member left
+    pub(crate) fn prune(conn: &PgConnection, board: Uuid) -> Result<usize, DieselError> {
+        let cards = cards::table
+            .inner_join(lists::table)
+            .filter(lists::board.eq(board))
+            .select(cards::id);
+        let members = board_members::table
+            .filter(board_members::board.eq(board))
+            .select(board_members::member);
+        let owner = boards::table.find(board).select(boards::owner);
+
+        diesel::delete(
+            card_assignees::table
+                .filter(card_assignees::card.eq_any(cards))
+                .filter(card_assignees::account.ne_all(members))
+                .filter(card_assignees::account.ne_all(owner)),
+        )
+        .execute(conn)
+    }
+
+    /// Returns 0 if the user wasn't assigned
+    pub fn delete(pool: &Data<DbPool>, card: Uuid, account: Uuid) -> Result<usize, ServiceError> {
+        let conn = get_conn(pool)?;
+
+        diesel::delete(card_assignees::table.find((card, account)))
+            .execute(&conn)
+            .map_err(|_| ServiceError::InternalServerError)
+    }
+}
@@ -0,0 +1,178 @@
+use chrono::{DateTime, Utc};
+use diesel::{result::Error as DieselError, PgConnection};
+use std::collections::BTreeMap;
+
+use super::prelude::*;
+use crate::{
+    models::{Card, User},
+    schema::attachments,
+    AttachmentConfig,
+};
+
+/// Metadata of a file attached to a card, the contents live in a blob store
+#[derive(Debug, Identifiable, Queryable, Insertable, Associations, Serialize)]
+#[belongs_to(Card, foreign_key = "card")]
+#[table_name = "attachments"]
+pub struct Attachment {
+    pub id: Uuid,
+    pub card: Uuid,
+    /// `None` once the uploader has been deleted
+    pub uploader: Option<Uuid>,
+    pub name: String,
+    pub content_type: String,
+    pub size: i64,
+    #[serde(skip_serializing)]
+    pub storage_key: String,
+    pub created_at: DateTime<Utc>,
+    /// Sizes of the generated thumbnails, empty until they are ready
+    #[serde(skip_serializing)]
+    pub thumbnails: Vec<i32>,
+}
+
+/// Attachment as returned by the API, with short-lived URLs of the file and its
+/// thumbnails by size
+#[derive(Debug, Serialize)]
+pub struct AttachmentResponse {
+    #[serde(flatten)]
+    pub attachment: Attachment,
+    pub url: String,
+    pub thumbnails: BTreeMap<u32, String>,
+}
+
+impl Attachment {
+    pub fn new(
+        card: &Card,
+        uploader: &User,
+        name: String,
+        content_type: String,
+        size: usize,
+    ) -> Self {
+        let id = Uuid::new_v4();
+
+        Attachment {
+            id,
+            card: card.id,
+            uploader: Some(uploader.id),
+            name,
+            content_type,
+            size: size as i64,
+            storage_key: id.to_string(),
+            created_at: Utc::now(),
+            thumbnails: Vec::new(),
+        }
+    }
+
+    pub fn thumbnail_key(storage_key: &str, size: u32) -> String {
+        format!("{}-{}", storage_key, size)
+    }
+
+    /// Returns the keys of the file and all its thumbnails
+    pub fn blob_keys(&self) -> Vec<String> {
+        let mut keys = vec![self.storage_key.clone()];
+        keys.extend(
+            self.thumbnails
+                .iter()
+                .map(|&size| Self::thumbnail_key(&self.storage_key, size as u32)),
+        );
+
+        keys
+    }
+
+    pub fn respond(self, config: &AttachmentConfig) -> AttachmentResponse {
+        let thumbnails = self
+            .thumbnails
+            .iter()
+            .map(|&size| (size as u32, config.signed_url(&self.id, Some(size as u32))))
+            .collect();
+
+        AttachmentResponse {
+            url: config.signed_url(&self.id, None),
+            thumbnails,
+            attachment: self,
+        }
+    }
+
+    pub fn save(&self, pool: &Data<DbPool>) -> Result<usize, ServiceError> {
+        let conn = get_conn(pool)?;
+
+        diesel::insert_into(attachments::table)
+            .values(self)
+            .execute(&conn)
+            .map_err(|_| ServiceError::InternalServerError)
+    }
+
+    pub fn find(pool: &Data<DbPool>, id: Uuid) -> Result<Option<Self>, ServiceError> {
+        let conn = get_conn(pool)?;
+
+        attachments::table
+            .find(id)
+            .first::<Self>(&conn)
+            .optional()
+            .map_err(|_| ServiceError::InternalServerError)
+    }
+
+    /// Returns the attachments of `card`, oldest first
+    pub fn for_card(pool: &Data<DbPool>, card: &Card) -> Result<Vec<Self>, ServiceError> {
+        let conn = get_conn(pool)?;
+
+        Attachment::belonging_to(card)
+            .order(attachments::created_at)
+            .load::<Self>(&conn)
+            .map_err(|_| ServiceError::InternalServerError)
+    }
+
+    /// Records the generated thumbnails, returns 0 if the attachment is gone
+    pub fn set_thumbnails(
+        pool: &Data<DbPool>,
+        id: Uuid,
+        sizes: Vec<i32>,
+    ) -> Result<usize, ServiceError> {
+        let conn = get_conn(pool)?;
+
+        diesel::update(attachments::table.find(id))
+            .set(attachments::thumbnails.eq(sizes))
+            .execute(&conn)
+            .map_err(|_| ServiceError::InternalServerError)
+    }
+
+    /// Attaches copies of the attachments of card `from` to card `to`. Returns the
+    /// originals with their copies, whose blobs are left for the caller to copy.
+    pub(crate) fn copy_all(
+        conn: &PgConnection,
+        from: Uuid,
+        to: Uuid,
+    ) -> Result<Vec<(Self, Self)>, DieselError> {
+        let originals = attachments::table
+            .filter(attachments::card.eq(from))
+            .load::<Self>(conn)?;
+
+        let mut copies = Vec::new();
+        for original in originals {
+            let id = Uuid::new_v4();
+            let copy = diesel::insert_into(attachments::table)
+                .values(&Attachment {
+                    id,
+                    card: to,
+                    uploader: original.uploader,
+                    name: original.name.clone(),
+                    content_type: original.content_type.clone(),
+                    size: original.size,
+                    storage_key: id.to_string(),
+                    created_at: original.created_at,
+                    thumbnails: original.thumbnails.clone(),
+                })
+                .get_result::<Self>(conn)?;
+            copies.push((original, copy));
+        }
+
+        Ok(copies)
+    }
+
+    pub fn delete(&self, pool: &Data<DbPool>) -> Result<usize, ServiceError> {
+        let conn = get_conn(pool)?;
+
+        diesel::delete(self)
+            .execute(&conn)
+            .map_err(|_| ServiceError::InternalServerError)
+    }
+}
@@ -0,0 +1,80 @@
+use actix_web::{dev::ServiceRequest, http::Method};
+use chrono::{DateTime, Utc};
+use std::str::FromStr;
+
+use super::prelude::*;
+use crate::{schema::audit_log, Claims};
+
+/// A request made by `actor` on behalf of `subject` while impersonating them
+#[derive(Debug, Identifiable, Queryable, Insertable, Serialize)]
+#[table_name = "audit_log"]
+pub struct AuditEntry {
+    pub id: Uuid,
+    pub actor: Uuid,
+    pub subject: Uuid,
+    pub method: String,
+    pub path: String,
+    pub created_at: DateTime<Utc>,
+}
+
+impl AuditEntry {
+    pub fn new(actor: Uuid, subject: Uuid, method: String, path: String) -> Self {
+        AuditEntry {
+            id: Uuid::new_v4(),
+            actor,
+            subject,
+            method,
+            path,
+            created_at: Utc::now(),
+        }
+    }
+
+    pub fn save(&self, pool: &Data<DbPool>) -> Result<usize, ServiceError> {
+        let conn = get_conn(pool)?;
+
+        diesel::insert_into(audit_log::table)
+            .values(self)
+            .execute(&conn)
+            .map_err(|_| ServiceError::InternalServerError)
+    }
+
+    /// Logs requests made with an impersonation token and rejects those that could change
+    /// data. Runs before routing, so requests that don't authenticate a user are logged too.
+    pub fn check_request(req: &ServiceRequest) -> Result<(), ServiceError> {
+        // Handlers that need a token reject missing or invalid ones themselves
+        let claims = match Claims::try_from(req) {
+            Ok(claims) => claims,
+            Err(_) => return Ok(()),
+        };
+        let actor = match &claims.act {
+            Some(actor) => Uuid::from_str(actor.as_str()).unwrap(),
+            None => return Ok(()),
+        };
+        let subject = Uuid::from_str(claims.sub.as_str()).unwrap();
+
+        let pool = req.app_data::<Data<DbPool>>().unwrap();
+        AuditEntry::new(
+            actor,
+            subject,
+            req.method().to_string(),
+            req.path().to_string(),
+        )
+        .save(pool)?;
+
+        // Impersonation is meant for looking around, not for acting as the user
+        match *req.method() {
+            Method::GET | Method::HEAD | Method::OPTIONS => Ok(()),
+            _ => Err(ServiceError::ImpersonationForbidden),
+        }
+    }
+
+    /// Returns the newest entries first
+    pub fn all(pool: &Data<DbPool>) -> Result<Vec<Self>, ServiceError> {
+        let conn = get_conn(pool)?;
+
+        audit_log::table
+            .order(audit_log::created_at.desc())
+            .load::<Self>(&conn)
+            .map_err(|_| ServiceError::InternalServerError)
+    }
+}
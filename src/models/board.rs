@@ -1,5 +1,16 @@
+use chrono::{DateTime, Utc};
+use diesel::{dsl::sql, sql_types::Double};
+use std::collections::HashMap;
+
 use super::prelude::*;
-use crate::{models::User, schema::boards};
+use crate::{
+    models::{
+        Assignment, Attachment, ChecklistItem, CloneOptions, Cloning, CustomField, Estimate, Label,
+        List, Member, Removed, Role, User,
+    },
+    rank::{self, Parent},
+    schema::{boards, cards, custom_fields, labels, lists},
+};
 
 #[derive(Debug, Identifiable, Queryable, Insertable, Associations, Serialize, Deserialize)]
 #[belongs_to(User, foreign_key = "owner")]
@@ -11,6 +22,20 @@ pub struct Board {
     pub owner: Uuid,
     pub name: String,
     pub description: Option<String>,
+    #[serde(skip_deserializing)]
+    pub archived_at: Option<DateTime<Utc>>,
+    #[serde(skip)]
+    pub deleted_at: Option<DateTime<Utc>>,
+    #[serde(skip)]
+    pub deleted_by: Option<Uuid>,
+}
+
+/// Board as returned by the API, with the estimates of its cards rolled up
+#[derive(Debug, Serialize)]
+pub struct BoardResponse {
+    #[serde(flatten)]
+    pub board: Board,
+    pub estimate: Estimate,
 }
 
 #[derive(Debug, AsChangeset, Deserialize)]
@@ -22,6 +47,8 @@ pub struct BoardUpdate {
     pub description: Option<String>,
 }
 
+rank::parent!(Board, boards);
+
 impl Board {
     pub fn new(owner: &User, name: String, desc: Option<String>) -> Self {
         Board {
@@ -29,6 +56,9 @@ impl Board {
             owner: owner.id,
             name,
             description: desc,
+            archived_at: None,
+            deleted_at: None,
+            deleted_by: None,
         }
     }
 
@@ -41,16 +71,62 @@ impl Board {
             .map_err(|_| ServiceError::InternalServerError)
     }
 
+    /// Items in the trash aren't found, see [`Board::find_trashed`]
     pub fn find(pool: &Data<DbPool>, id: Uuid) -> Result<Option<Self>, ServiceError> {
         let conn = get_conn(pool)?;
 
         boards::table
             .find(id)
+            .filter(boards::deleted_at.is_null())
             .first::<Self>(&conn)
             .optional()
             .map_err(|_| ServiceError::InternalServerError)
     }
 
+    /// Wraps boards for a response with the total estimates of their cards, leaving out
+    /// archived cards and lists
+    pub fn render(
+        pool: &Data<DbPool>,
+        boards: Vec<Self>,
+    ) -> Result<Vec<BoardResponse>, ServiceError> {
+        let conn = get_conn(pool)?;
+
+        let ids = boards.iter().map(|board| board.id).collect::<Vec<_>>();
+        let estimates = cards::table
+            .inner_join(lists::table)
+            .filter(lists::board.eq_any(&ids))
+            .filter(lists::archived_at.is_null())
+            .filter(cards::archived_at.is_null())
+            .group_by(lists::board)
+            .select((
+                lists::board,
+                sql::<Double>("COALESCE(SUM(cards.estimate), 0)"),
+                sql::<Double>(
+                    "COALESCE(SUM(cards.estimate) FILTER (WHERE NOT cards.completed), 0)",
+                ),
+            ))
+            .load::<(Uuid, f64, f64)>(&conn)
+            .map_err(|_| ServiceError::InternalServerError)?
+            .into_iter()
+            .map(|(board, total, remaining)| (board, Estimate { total, remaining }))
+            .collect::<HashMap<_, _>>();
+
+        Ok(boards
+            .into_iter()
+            .map(|board| BoardResponse {
+                estimate: estimates.get(&board.id).copied().unwrap_or_default(),
+                board,
+            })
+            .collect())
+    }
+
+    /// Wraps a single board for a response, see [`Board::render`]
+    pub fn render_one(self, pool: &Data<DbPool>) -> Result<BoardResponse, ServiceError> {
+        let mut responses = Board::render(pool, vec![self])?;
+
+        Ok(responses.remove(0))
+    }
+
     pub fn update(&self, pool: &Data<DbPool>, data: BoardUpdate) -> Result<Self, ServiceError> {
         let conn = get_conn(pool)?;
 
@@ -60,13 +136,165 @@ impl Board {
             .map_err(|_| ServiceError::InternalServerError)
     }
 
-    pub fn delete(&self, pool: &Data<DbPool>) -> Result<usize, ServiceError> {
+    /// Returns the role `user` has on this board. Owners are always admins.
+    pub fn role(&self, pool: &Data<DbPool>, user: &User) -> Result<Option<Role>, ServiceError> {
+        if self.owner == user.id {
+            return Ok(Some(Role::Admin));
+        }
+
+        Ok(Member::find(pool, self.id, user.id)?.map(|m| m.role))
+    }
+
+    /// Returns true if `user` has at least the given role on this board
+    pub fn allows(
+        &self,
+        pool: &Data<DbPool>,
+        user: &User,
+        role: Role,
+    ) -> Result<bool, ServiceError> {
+        Ok(matches!(self.role(pool, user)?, Some(r) if r >= role))
+    }
+
+    /// Clones the board as a new board of `owner`, see [`CloneOptions`]. Custom fields are
+    /// always cloned, members and share links never. Assignees without access to the clone
+    /// are dropped. Returns the clone along with the cloned attachments, whose blobs still
+    /// have to be copied.
+    pub fn clone_for(
+        &self,
+        pool: &Data<DbPool>,
+        owner: &User,
+        name: String,
+        options: CloneOptions,
+    ) -> Result<(Self, Vec<(Attachment, Attachment)>), ServiceError> {
+        let conn = get_conn(pool)?;
+
+        conn.transaction::<_, ServiceError, _>(|| {
+            let copy = Board::new(owner, name, self.description.clone());
+            diesel::insert_into(boards::table)
+                .values(&copy)
+                .execute(&conn)?;
+
+            let mut cloning = Cloning::new(copy.id, options);
+
+            if options.labels {
+                let labels = Label::belonging_to(self)
+                    .load::<Label>(&conn)?
+                    .into_iter()
+                    .map(|label| Label {
+                        id: Uuid::new_v4(),
+                        board: copy.id,
+                        ..label
+                    })
+                    .collect::<Vec<_>>();
+                diesel::insert_into(labels::table)
+                    .values(&labels)
+                    .execute(&conn)?;
+            }
+
+            for field in CustomField::belonging_to(self).load::<CustomField>(&conn)? {
+                let id = Uuid::new_v4();
+                cloning.fields.insert(field.id, id);
+                diesel::insert_into(custom_fields::table)
+                    .values(&CustomField {
+                        id,
+                        board: copy.id,
+                        ..field
+                    })
+                    .execute(&conn)?;
+            }
+
+            let lists = List::belonging_to(self)
+                .filter(lists::archived_at.is_null())
+                .filter(lists::deleted_at.is_null())
+                .load::<List>(&conn)?;
+            for list in lists {
+                list.clone_to(&conn, copy.id, list.position.clone(), &mut cloning)?;
+            }
+            Assignment::prune(&conn, copy.id)?;
+            ChecklistItem::prune(&conn, copy.id)?;
+
+            Ok((copy, cloning.attachments))
+        })
+    }
+
+    /// Hides the board from listings until it is restored
+    pub fn archive(&self, pool: &Data<DbPool>) -> Result<Self, ServiceError> {
+        let conn = get_conn(pool)?;
+
+        diesel::update(self)
+            .set(boards::archived_at.eq(Some(Utc::now())))
+            .get_result::<Self>(&conn)
+            .map_err(|_| ServiceError::InternalServerError)
+    }
+
+    pub fn restore(&self, pool: &Data<DbPool>) -> Result<Self, ServiceError> {
         let conn = get_conn(pool)?;
 
-        diesel::delete(self)
+        diesel::update(self)
+            .set(boards::archived_at.eq(None::<DateTime<Utc>>))
+            .get_result::<Self>(&conn)
+            .map_err(|_| ServiceError::InternalServerError)
+    }
+
+    pub fn is_archived(&self) -> bool {
+        self.archived_at.is_some()
+    }
+
+    pub fn find_trashed(pool: &Data<DbPool>, id: Uuid) -> Result<Option<Self>, ServiceError> {
+        let conn = get_conn(pool)?;
+
+        boards::table
+            .find(id)
+            .filter(boards::deleted_at.is_not_null())
+            .first::<Self>(&conn)
+            .optional()
+            .map_err(|_| ServiceError::InternalServerError)
+    }
+
+    /// Moves an archived board to the trash of `user`, its contents go along with it
+    pub fn trash(&self, pool: &Data<DbPool>, user: &User) -> Result<usize, ServiceError> {
+        if !self.is_archived() {
+            return Err(ServiceError::NotArchived);
+        }
+
+        let conn = get_conn(pool)?;
+
+        diesel::update(self)
+            .set((
+                boards::deleted_at.eq(Some(Utc::now())),
+                boards::deleted_by.eq(Some(user.id)),
+            ))
             .execute(&conn)
             .map_err(|_| ServiceError::InternalServerError)
     }
+
+    /// Takes the board out of the trash, it stays archived
+    pub fn recover(&self, pool: &Data<DbPool>) -> Result<Self, ServiceError> {
+        let conn = get_conn(pool)?;
+
+        diesel::update(self)
+            .set((
+                boards::deleted_at.eq(None::<DateTime<Utc>>),
+                boards::deleted_by.eq(None::<Uuid>),
+            ))
+            .get_result::<Self>(&conn)
+            .map_err(|_| ServiceError::InternalServerError)
+    }
+
+    /// Permanently deletes the board with all its lists and cards, only archived boards
+    /// can be deleted
+    pub fn delete(&self, pool: &Data<DbPool>) -> Result<Removed, ServiceError> {
+        if !self.is_archived() {
+            return Err(ServiceError::NotArchived);
+        }
+
+        let conn = get_conn(pool)?;
+
+        conn.transaction::<_, ServiceError, _>(|| {
+            Board::lock(&conn, self.id)?;
+            Ok(Removed::boards(&conn, &[self.id])?)
+        })
+    }
 }
 
 impl BoardUpdate {
@@ -1,5 +1,71 @@
+use chrono::{DateTime, Utc};
+use diesel::{
+    deserialize::{self, FromSql},
+    dsl::sql,
+    pg::Pg,
+    result::Error as DieselError,
+    serialize::{self, Output, ToSql},
+    sql_types::{BigInt, SmallInt},
+    PgConnection,
+};
+use serde_json::Value;
+use std::{
+    collections::{BTreeMap, HashMap},
+    io::Write,
+};
+
 use super::prelude::*;
-use crate::{models::list::List, schema::cards};
+use crate::{
+    markdown,
+    models::{
+        list::List, Assignee, Assignment, Attachment, AttachmentResponse, Checklist, ChecklistItem,
+        CloneOptions, Cloning, Comment, FieldValue, Label, Removed, User,
+    },
+    rank::{self, Parent, Placement, Ranked},
+    schema::{attachments, cards, checklist_items, checklists, lists, reminders},
+    AttachmentConfig,
+};
+
+/// Priority of a card, ordered from the least to the most urgent
+#[derive(
+    Debug,
+    Clone,
+    Copy,
+    PartialEq,
+    Eq,
+    PartialOrd,
+    Ord,
+    AsExpression,
+    FromSqlRow,
+    Serialize,
+    Deserialize,
+)]
+#[sql_type = "SmallInt"]
+#[serde(rename_all = "lowercase")]
+pub enum Priority {
+    Low,
+    Medium,
+    High,
+    Urgent,
+}
+
+impl ToSql<SmallInt, Pg> for Priority {
+    fn to_sql<W: Write>(&self, out: &mut Output<W, Pg>) -> serialize::Result {
+        ToSql::<SmallInt, Pg>::to_sql(&(*self as i16), out)
+    }
+}
+
+impl FromSql<SmallInt, Pg> for Priority {
+    fn from_sql(bytes: Option<&[u8]>) -> deserialize::Result<Self> {
+        match <i16 as FromSql<SmallInt, Pg>>::from_sql(bytes)? {
+            0 => Ok(Priority::Low),
+            1 => Ok(Priority::Medium),
+            2 => Ok(Priority::High),
+            3 => Ok(Priority::Urgent),
+            _ => Err("Unrecognized priority".into()),
+        }
+    }
+}
 
 #[derive(Debug, Identifiable, Queryable, Insertable, Associations, Serialize, Deserialize)]
 #[belongs_to(List, foreign_key = "list")]
@@ -9,8 +75,53 @@ pub struct Card {
     pub id: Uuid,
     #[serde(skip_deserializing)]
     pub list: Uuid,
-    pub content: Option<String>,
-    pub labels: Option<Vec<String>>,
+    pub title: String,
+    /// Markdown
+    pub description: Option<String>,
+    #[serde(skip_deserializing)]
+    pub position: String,
+    pub start_at: Option<DateTime<Utc>>,
+    pub due_at: Option<DateTime<Utc>>,
+    #[serde(default)]
+    pub completed: bool,
+    pub priority: Option<Priority>,
+    /// Story points
+    pub estimate: Option<f64>,
+    #[serde(skip_deserializing)]
+    pub archived_at: Option<DateTime<Utc>>,
+    #[serde(skip)]
+    pub deleted_at: Option<DateTime<Utc>>,
+    #[serde(skip)]
+    pub deleted_by: Option<Uuid>,
+}
+
+/// Card as returned by the API, optionally with its description rendered to HTML
+#[derive(Debug, Serialize)]
+pub struct CardResponse {
+    #[serde(flatten)]
+    pub card: Card,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub description_html: Option<String>,
+    pub labels: Vec<Label>,
+    pub assignees: Vec<Assignee>,
+    /// Values of custom fields by field id
+    pub fields: BTreeMap<Uuid, Value>,
+    pub checklists: Progress,
+    pub attachments: Vec<AttachmentResponse>,
+}
+
+/// Number of checked and total checklist items of a card
+#[derive(Debug, Default, Clone, Copy, Serialize)]
+pub struct Progress {
+    pub checked: i64,
+    pub total: i64,
+}
+
+/// Sum of the card estimates of a list or board, `remaining` leaves out completed cards
+#[derive(Debug, Default, Clone, Copy, Serialize)]
+pub struct Estimate {
+    pub total: f64,
+    pub remaining: f64,
 }
 
 #[derive(Debug, AsChangeset, Deserialize)]
@@ -18,60 +129,442 @@ pub struct Card {
 pub struct CardUpdate {
     #[serde(skip_deserializing)]
     pub id: Uuid,
-    pub content: Option<String>,
-    pub labels: Option<Vec<String>>,
+    pub title: Option<String>,
+    pub description: Option<String>,
+    /// `null` removes the date
+    #[serde(default, deserialize_with = "super::nullable")]
+    pub start_at: Option<Option<DateTime<Utc>>>,
+    #[serde(default, deserialize_with = "super::nullable")]
+    pub due_at: Option<Option<DateTime<Utc>>>,
+    pub completed: Option<bool>,
+    /// `null` removes the priority
+    #[serde(default, deserialize_with = "super::nullable")]
+    pub priority: Option<Option<Priority>>,
+    #[serde(default, deserialize_with = "super::nullable")]
+    pub estimate: Option<Option<f64>>,
+}
+
+/// Fails if the card would start after it is due
+fn check_dates(
+    start_at: Option<DateTime<Utc>>,
+    due_at: Option<DateTime<Utc>>,
+) -> Result<(), ServiceError> {
+    match (start_at, due_at) {
+        (Some(start_at), Some(due_at)) if start_at > due_at => Err(ServiceError::InvalidDates),
+        _ => Ok(()),
+    }
 }
 
+/// Estimates are non-negative story points
+fn check_estimate(estimate: Option<f64>) -> Result<(), ServiceError> {
+    match estimate {
+        Some(estimate) if !estimate.is_finite() || estimate < 0.0 => {
+            Err(ServiceError::InvalidEstimate)
+        }
+        _ => Ok(()),
+    }
+}
+
+rank::parent!(Card, cards);
+rank::ranked!(Card, cards, list);
+
 impl Card {
-    pub fn new(list: &List, content: Option<String>, labels: Option<Vec<String>>) -> Self {
+    pub fn new(list: &List, title: String, description: Option<String>) -> Self {
         Card {
             id: Uuid::new_v4(),
             list: list.id,
-            content,
-            labels,
+            title,
+            description,
+            position: String::new(),
+            start_at: None,
+            due_at: None,
+            completed: false,
+            priority: None,
+            estimate: None,
+            archived_at: None,
+            deleted_at: None,
+            deleted_by: None,
         }
     }
 
-    pub fn save(&self, pool: &Data<DbPool>) -> Result<usize, ServiceError> {
+    /// Inserts the card with its custom field values at the end of its list, unless that
+    /// exceeds the work-in-progress limit of the list
+    pub fn save(
+        &mut self,
+        pool: &Data<DbPool>,
+        fields: Vec<(Uuid, Option<Value>)>,
+    ) -> Result<(), ServiceError> {
+        check_dates(self.start_at, self.due_at)?;
+        check_estimate(self.estimate)?;
+
         let conn = get_conn(pool)?;
 
-        diesel::insert_into(cards::table)
-            .values(self)
-            .execute(&conn)
-            .map_err(|_| ServiceError::InternalServerError)
+        conn.transaction::<_, ServiceError, _>(|| {
+            List::lock(&conn, self.list)?;
+            List::check_limit(&conn, self.list, self.id)?;
+
+            self.position = Card::position_in(&conn, self.list, self.id, None)?;
+
+            diesel::insert_into(cards::table)
+                .values(&*self)
+                .execute(&conn)?;
+            FieldValue::set_all(&conn, self.id, fields)?;
+
+            Ok(())
+        })
     }
 
+    /// Items in the trash aren't found, see [`Card::find_trashed`]
     pub fn find(pool: &Data<DbPool>, id: Uuid) -> Result<Option<Self>, ServiceError> {
         let conn = get_conn(pool)?;
 
         cards::table
             .find(id)
+            .filter(cards::deleted_at.is_null())
             .first::<Self>(&conn)
             .optional()
             .map_err(|_| ServiceError::InternalServerError)
     }
 
-    pub fn update(&self, pool: &Data<DbPool>, data: CardUpdate) -> Result<Self, ServiceError> {
+    /// Updates the card and sets the given custom field values, `data` may be empty.
+    /// Changing the due date rearms the reminders of the card.
+    pub fn update(
+        &self,
+        pool: &Data<DbPool>,
+        data: CardUpdate,
+        fields: Vec<(Uuid, Option<Value>)>,
+    ) -> Result<Self, ServiceError> {
+        check_dates(
+            data.start_at.unwrap_or(self.start_at),
+            data.due_at.unwrap_or(self.due_at),
+        )?;
+        check_estimate(data.estimate.flatten())?;
+
+        let conn = get_conn(pool)?;
+
+        conn.transaction::<_, ServiceError, _>(|| {
+            if matches!(data.due_at, Some(due_at) if due_at != self.due_at) {
+                diesel::update(reminders::table.filter(reminders::card.eq(self.id)))
+                    .set(reminders::sent_at.eq(None::<DateTime<Utc>>))
+                    .execute(&conn)?;
+            }
+
+            let card = if data.is_empty() {
+                cards::table.find(self.id).get_result::<Self>(&conn)?
+            } else {
+                diesel::update(self).set(&data).get_result::<Self>(&conn)?
+            };
+            FieldValue::set_all(&conn, self.id, fields)?;
+
+            Ok(card)
+        })
+    }
+
+    /// Moves the card into `list`, which may belong to another board, next to one of its
+    /// cards or after all of them. Concurrent moves into a list are serialized, so they
+    /// can't end up with clashing positions or exceed its limit. Labels are carried over
+    /// to the new board, assignees without access to it and custom field values of the
+    /// old board are dropped.
+    pub fn move_to(
+        &self,
+        pool: &Data<DbPool>,
+        list: Uuid,
+        placement: Option<Placement>,
+    ) -> Result<Self, ServiceError> {
+        let conn = get_conn(pool)?;
+
+        conn.transaction::<_, ServiceError, _>(|| {
+            List::lock(&conn, list)?;
+            // Reordering a list that is over its limit is fine
+            if list != self.list {
+                List::check_limit(&conn, list, self.id)?;
+            }
+
+            let position = Card::position_in(&conn, list, self.id, placement)?;
+            let board = lists::table
+                .find(list)
+                .select(lists::board)
+                .first::<Uuid>(&conn)?;
+            Label::copy_links(&conn, self.id, self.id, board)?;
+
+            let card = diesel::update(self)
+                .set((cards::list.eq(list), cards::position.eq(position)))
+                .get_result::<Self>(&conn)?;
+            Assignment::prune(&conn, board)?;
+            ChecklistItem::prune(&conn, board)?;
+            FieldValue::prune(&conn, board)?;
+
+            Ok(card)
+        })
+    }
+
+    /// Wraps cards for a response with their labels, assignees, custom field values,
+    /// checklist progress and attachments, rendering descriptions if `html` is set.
+    /// Attachments come with download URLs, so they're only listed if `attachments` is set.
+    pub fn render(
+        pool: &Data<DbPool>,
+        config: &AttachmentConfig,
+        cards: Vec<Self>,
+        html: bool,
+        attachments: bool,
+    ) -> Result<Vec<CardResponse>, ServiceError> {
+        let conn = get_conn(pool)?;
+
+        let ids = cards.iter().map(|card| card.id).collect::<Vec<_>>();
+        let progress = checklist_items::table
+            .inner_join(checklists::table)
+            .filter(checklists::card.eq_any(&ids))
+            .group_by(checklists::card)
+            .select((
+                checklists::card,
+                sql::<BigInt>("COUNT(*) FILTER (WHERE checklist_items.checked)"),
+                sql::<BigInt>("COUNT(*)"),
+            ))
+            .load::<(Uuid, i64, i64)>(&conn)
+            .map_err(|_| ServiceError::InternalServerError)?
+            .into_iter()
+            .map(|(card, checked, total)| (card, Progress { checked, total }))
+            .collect::<HashMap<_, _>>();
+
+        let mut labels = HashMap::<_, Vec<_>>::new();
+        for (card, label) in Label::for_cards(&conn, &ids)? {
+            labels.entry(card).or_default().push(label);
+        }
+        let mut assignees = HashMap::<_, Vec<_>>::new();
+        for (card, assignee) in Assignment::for_cards(&conn, &ids)? {
+            assignees.entry(card).or_default().push(assignee);
+        }
+        let mut fields = HashMap::<_, BTreeMap<_, _>>::new();
+        for value in FieldValue::for_cards(&conn, &ids)? {
+            fields
+                .entry(value.card)
+                .or_default()
+                .insert(value.field, value.value);
+        }
+
+        let attachments = if attachments {
+            Attachment::belonging_to(&cards)
+                .order(attachments::created_at)
+                .load::<Attachment>(&conn)
+                .map_err(|_| ServiceError::InternalServerError)?
+                .grouped_by(&cards)
+        } else {
+            cards.iter().map(|_| Vec::new()).collect()
+        };
+
+        Ok(cards
+            .into_iter()
+            .zip(attachments)
+            .map(|(card, attachments)| {
+                let description_html = match (&card.description, html) {
+                    (Some(description), true) => Some(markdown::render(description)),
+                    _ => None,
+                };
+
+                CardResponse {
+                    labels: labels.remove(&card.id).unwrap_or_default(),
+                    assignees: assignees.remove(&card.id).unwrap_or_default(),
+                    fields: fields.remove(&card.id).unwrap_or_default(),
+                    checklists: progress.get(&card.id).copied().unwrap_or_default(),
+                    card,
+                    description_html,
+                    attachments: attachments
+                        .into_iter()
+                        .map(|attachment| attachment.respond(config))
+                        .collect(),
+                }
+            })
+            .collect())
+    }
+
+    /// Wraps a single card for a response, see [`Card::render`]
+    pub fn render_one(
+        self,
+        pool: &Data<DbPool>,
+        config: &AttachmentConfig,
+        html: bool,
+        attachments: bool,
+    ) -> Result<CardResponse, ServiceError> {
+        let mut responses = Card::render(pool, config, vec![self], html, attachments)?;
+
+        Ok(responses.remove(0))
+    }
+
+    /// Clones the card right after itself, see [`CloneOptions`]. Returns the clone along
+    /// with the cloned attachments, whose blobs still have to be copied.
+    pub fn duplicate(
+        &self,
+        pool: &Data<DbPool>,
+        options: CloneOptions,
+    ) -> Result<(Self, Vec<(Attachment, Attachment)>), ServiceError> {
+        let conn = get_conn(pool)?;
+
+        conn.transaction::<_, ServiceError, _>(|| {
+            List::lock(&conn, self.list)?;
+            // The clone doesn't exist yet, so no card is excluded
+            List::check_limit(&conn, self.list, Uuid::nil())?;
+
+            let board = lists::table
+                .find(self.list)
+                .select(lists::board)
+                .first::<Uuid>(&conn)?;
+            let position = Card::position_in(
+                &conn,
+                self.list,
+                Uuid::nil(),
+                Some(Placement::After(self.id)),
+            )?;
+
+            let mut cloning = Cloning::new(board, options);
+            let copy = self.clone_to(&conn, self.list, position, &mut cloning)?;
+
+            Ok((copy, cloning.attachments))
+        })
+    }
+
+    /// Inserts an unarchived copy of the card into `list` with the contents selected by
+    /// the options of `cloning`
+    pub(crate) fn clone_to(
+        &self,
+        conn: &PgConnection,
+        list: Uuid,
+        position: String,
+        cloning: &mut Cloning,
+    ) -> Result<Self, DieselError> {
+        let copy = diesel::insert_into(cards::table)
+            .values(&Card {
+                id: Uuid::new_v4(),
+                list,
+                title: self.title.clone(),
+                description: self.description.clone(),
+                position,
+                start_at: self.start_at,
+                due_at: self.due_at,
+                completed: self.completed,
+                priority: self.priority,
+                estimate: self.estimate,
+                archived_at: None,
+                deleted_at: None,
+                deleted_by: None,
+            })
+            .get_result::<Self>(conn)?;
+
+        Assignment::copy_all(conn, self.id, copy.id)?;
+        if cloning.fields.is_empty() {
+            FieldValue::copy_all(conn, self.id, copy.id, cloning.board)?;
+        } else {
+            FieldValue::copy_mapped(conn, self.id, copy.id, &cloning.fields)?;
+        }
+
+        let options = cloning.options;
+        if options.labels {
+            Label::copy_links(conn, self.id, copy.id, cloning.board)?;
+        }
+        if options.checklists {
+            Checklist::copy_all(conn, self.id, copy.id)?;
+        }
+        if options.comments {
+            Comment::copy_all(conn, self.id, copy.id)?;
+        }
+        if options.attachments {
+            let attachments = Attachment::copy_all(conn, self.id, copy.id)?;
+            cloning.attachments.extend(attachments);
+        }
+
+        Ok(copy)
+    }
+
+    /// Hides the card from listings until it is restored
+    pub fn archive(&self, pool: &Data<DbPool>) -> Result<Self, ServiceError> {
         let conn = get_conn(pool)?;
 
         diesel::update(self)
-            .set(&data)
+            .set(cards::archived_at.eq(Some(Utc::now())))
             .get_result::<Self>(&conn)
             .map_err(|_| ServiceError::InternalServerError)
     }
 
-    pub fn delete(&self, pool: &Data<DbPool>) -> Result<usize, ServiceError> {
+    /// Unarchives the card, as long as its list has room for it
+    pub fn restore(&self, pool: &Data<DbPool>) -> Result<Self, ServiceError> {
+        let conn = get_conn(pool)?;
+
+        conn.transaction::<_, ServiceError, _>(|| {
+            List::lock(&conn, self.list)?;
+            List::check_limit(&conn, self.list, self.id)?;
+
+            Ok(diesel::update(self)
+                .set(cards::archived_at.eq(None::<DateTime<Utc>>))
+                .get_result::<Self>(&conn)?)
+        })
+    }
+
+    pub fn is_archived(&self) -> bool {
+        self.archived_at.is_some()
+    }
+
+    pub fn find_trashed(pool: &Data<DbPool>, id: Uuid) -> Result<Option<Self>, ServiceError> {
+        let conn = get_conn(pool)?;
+
+        cards::table
+            .find(id)
+            .filter(cards::deleted_at.is_not_null())
+            .first::<Self>(&conn)
+            .optional()
+            .map_err(|_| ServiceError::InternalServerError)
+    }
+
+    /// Moves an archived card to the trash of `user`, its contents go along with it
+    pub fn trash(&self, pool: &Data<DbPool>, user: &User) -> Result<usize, ServiceError> {
+        if !self.is_archived() {
+            return Err(ServiceError::NotArchived);
+        }
+
         let conn = get_conn(pool)?;
 
-        diesel::delete(self)
+        diesel::update(self)
+            .set((
+                cards::deleted_at.eq(Some(Utc::now())),
+                cards::deleted_by.eq(Some(user.id)),
+            ))
             .execute(&conn)
             .map_err(|_| ServiceError::InternalServerError)
     }
+
+    /// Takes the card out of the trash, it stays archived
+    pub fn recover(&self, pool: &Data<DbPool>) -> Result<Self, ServiceError> {
+        let conn = get_conn(pool)?;
+
+        diesel::update(self)
+            .set((
+                cards::deleted_at.eq(None::<DateTime<Utc>>),
+                cards::deleted_by.eq(None::<Uuid>),
+            ))
+            .get_result::<Self>(&conn)
+            .map_err(|_| ServiceError::InternalServerError)
+    }
+
+    /// Permanently deletes the card with its checklists, comments and attachments, only
+    /// archived cards can be deleted
+    pub fn delete(&self, pool: &Data<DbPool>) -> Result<Removed, ServiceError> {
+        if !self.is_archived() {
+            return Err(ServiceError::NotArchived);
+        }
+
+        let conn = get_conn(pool)?;
+
+        conn.transaction::<_, ServiceError, _>(|| Ok(Removed::cards(&conn, &[self.id])?))
+    }
 }
 
 impl CardUpdate {
     /// Returns true if all update fields are None
     pub fn is_empty(&self) -> bool {
-        self.content == None && self.labels == None
+        self.title == None
+            && self.description == None
+            && self.start_at == None
+            && self.due_at == None
+            && self.completed == None
+            && self.priority.is_none()
+            && self.estimate.is_none()
     }
 }
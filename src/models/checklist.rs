@@ -0,0 +1,309 @@
+use chrono::{DateTime, Utc};
+use diesel::{result::Error as DieselError, PgConnection};
+
+use super::prelude::*;
+use crate::{
+    models::Card,
+    rank::{self, Parent, Placement, Ranked},
+    schema::{board_members, boards, cards, checklist_items, checklists, lists},
+};
+
+#[derive(Debug, Identifiable, Queryable, Insertable, Associations, Serialize, Deserialize)]
+#[belongs_to(Card, foreign_key = "card")]
+#[table_name = "checklists"]
+pub struct Checklist {
+    #[serde(skip_deserializing)]
+    pub id: Uuid,
+    #[serde(skip_deserializing)]
+    pub card: Uuid,
+    pub name: String,
+    #[serde(skip_deserializing)]
+    pub position: String,
+}
+
+#[derive(Debug, Identifiable, Queryable, Insertable, Associations, Serialize, Deserialize)]
+#[belongs_to(Checklist, foreign_key = "checklist")]
+#[table_name = "checklist_items"]
+pub struct ChecklistItem {
+    #[serde(skip_deserializing)]
+    pub id: Uuid,
+    #[serde(skip_deserializing)]
+    pub checklist: Uuid,
+    pub content: String,
+    #[serde(default)]
+    pub checked: bool,
+    pub assignee: Option<Uuid>,
+    pub due_at: Option<DateTime<Utc>>,
+    #[serde(skip_deserializing)]
+    pub position: String,
+}
+
+#[derive(Debug, AsChangeset, Deserialize)]
+#[table_name = "checklist_items"]
+pub struct ChecklistItemUpdate {
+    #[serde(skip_deserializing)]
+    pub id: Uuid,
+    pub content: Option<String>,
+    pub checked: Option<bool>,
+    /// `null` removes the assignee
+    #[serde(default, deserialize_with = "super::nullable")]
+    pub assignee: Option<Option<Uuid>>,
+    #[serde(default, deserialize_with = "super::nullable")]
+    pub due_at: Option<Option<DateTime<Utc>>>,
+}
+
+/// Checklist with its items, in order
+#[derive(Debug, Serialize)]
+pub struct ChecklistResponse {
+    #[serde(flatten)]
+    pub checklist: Checklist,
+    pub items: Vec<ChecklistItem>,
+}
+
+rank::parent!(Checklist, checklists);
+rank::ranked!(Checklist, checklists, card);
+
+impl Checklist {
+    pub fn new(card: &Card, name: String) -> Self {
+        Checklist {
+            id: Uuid::new_v4(),
+            card: card.id,
+            name,
+            position: String::new(),
+        }
+    }
+
+    /// Inserts the checklist after the last checklist of its card
+    pub fn save(&mut self, pool: &Data<DbPool>) -> Result<usize, ServiceError> {
+        let conn = get_conn(pool)?;
+
+        conn.transaction::<_, ServiceError, _>(|| {
+            Card::lock(&conn, self.card)?;
+
+            self.position = Checklist::position_in(&conn, self.card, self.id, None)?;
+
+            Ok(diesel::insert_into(checklists::table)
+                .values(&*self)
+                .execute(&conn)?)
+        })
+    }
+
+    pub fn find(pool: &Data<DbPool>, id: Uuid) -> Result<Option<Self>, ServiceError> {
+        let conn = get_conn(pool)?;
+
+        checklists::table
+            .find(id)
+            .first::<Self>(&conn)
+            .optional()
+            .map_err(|_| ServiceError::InternalServerError)
+    }
+
+    /// Returns all checklists of `card` with their items
+    pub fn for_card(
+        pool: &Data<DbPool>,
+        card: &Card,
+    ) -> Result<Vec<ChecklistResponse>, ServiceError> {
+        let conn = get_conn(pool)?;
+
+        let lists = Checklist::belonging_to(card)
+            .order((checklists::position, checklists::id))
+            .load::<Checklist>(&conn)
+            .map_err(|_| ServiceError::InternalServerError)?;
+        let items = ChecklistItem::belonging_to(&lists)
+            .order((checklist_items::position, checklist_items::id))
+            .load::<ChecklistItem>(&conn)
+            .map_err(|_| ServiceError::InternalServerError)?
+            .grouped_by(&lists);
+
+        Ok(lists
+            .into_iter()
+            .zip(items)
+            .map(|(checklist, items)| ChecklistResponse { checklist, items })
+            .collect())
+    }
+
+    pub fn rename(&self, pool: &Data<DbPool>, name: String) -> Result<Self, ServiceError> {
+        let conn = get_conn(pool)?;
+
+        diesel::update(self)
+            .set(checklists::name.eq(name))
+            .get_result::<Self>(&conn)
+            .map_err(|_| ServiceError::InternalServerError)
+    }
+
+    /// Moves the checklist next to another checklist of the same card
+    pub fn move_to(&self, pool: &Data<DbPool>, placement: Placement) -> Result<Self, ServiceError> {
+        let conn = get_conn(pool)?;
+
+        conn.transaction::<_, ServiceError, _>(|| {
+            Card::lock(&conn, self.card)?;
+
+            let position = Checklist::position_in(&conn, self.card, self.id, Some(placement))?;
+
+            Ok(diesel::update(self)
+                .set(checklists::position.eq(position))
+                .get_result::<Self>(&conn)?)
+        })
+    }
+
+    /// Copies all checklists of the card `from` with their items to the card `to`
+    pub(crate) fn copy_all(conn: &PgConnection, from: Uuid, to: Uuid) -> Result<(), DieselError> {
+        let lists = checklists::table
+            .filter(checklists::card.eq(from))
+            .load::<Checklist>(conn)?;
+        let items = ChecklistItem::belonging_to(&lists)
+            .load::<ChecklistItem>(conn)?
+            .grouped_by(&lists);
+
+        for (checklist, items) in lists.into_iter().zip(items) {
+            let copy = Checklist {
+                id: Uuid::new_v4(),
+                card: to,
+                ..checklist
+            };
+            diesel::insert_into(checklists::table)
+                .values(&copy)
+                .execute(conn)?;
+
+            let items = items
+                .into_iter()
+                .map(|item| ChecklistItem {
+                    id: Uuid::new_v4(),
+                    checklist: copy.id,
+                    ..item
+                })
+                .collect::<Vec<_>>();
+            diesel::insert_into(checklist_items::table)
+                .values(&items)
+                .execute(conn)?;
+        }
+
+        Ok(())
+    }
+
+    pub fn delete(&self, pool: &Data<DbPool>) -> Result<usize, ServiceError> {
+        let conn = get_conn(pool)?;
+
+        diesel::delete(self)
+            .execute(&conn)
+            .map_err(|_| ServiceError::InternalServerError)
+    }
+}
+
+rank::ranked!(ChecklistItem, checklist_items, checklist);
+
+impl ChecklistItem {
+    pub fn new(
+        checklist: &Checklist,
+        content: String,
+        assignee: Option<Uuid>,
+        due_at: Option<DateTime<Utc>>,
+    ) -> Self {
+        ChecklistItem {
+            id: Uuid::new_v4(),
+            checklist: checklist.id,
+            content,
+            checked: false,
+            assignee,
+            due_at,
+            position: String::new(),
+        }
+    }
+
+    /// Inserts the item at the end of its checklist
+    pub fn save(&mut self, pool: &Data<DbPool>) -> Result<usize, ServiceError> {
+        let conn = get_conn(pool)?;
+
+        conn.transaction::<_, ServiceError, _>(|| {
+            Checklist::lock(&conn, self.checklist)?;
+
+            self.position = ChecklistItem::position_in(&conn, self.checklist, self.id, None)?;
+
+            Ok(diesel::insert_into(checklist_items::table)
+                .values(&*self)
+                .execute(&conn)?)
+        })
+    }
+
+    pub fn find(pool: &Data<DbPool>, id: Uuid) -> Result<Option<Self>, ServiceError> {
+        let conn = get_conn(pool)?;
+
+        checklist_items::table
+            .find(id)
+            .first::<Self>(&conn)
+            .optional()
+            .map_err(|_| ServiceError::InternalServerError)
+    }
+
+    pub fn update(
+        &self,
+        pool: &Data<DbPool>,
+        data: ChecklistItemUpdate,
+    ) -> Result<Self, ServiceError> {
+        let conn = get_conn(pool)?;
+
+        diesel::update(self)
+            .set(&data)
+            .get_result::<Self>(&conn)
+            .map_err(|_| ServiceError::InternalServerError)
+    }
+
+    /// Moves the item next to another item of the same checklist
+    pub fn move_to(&self, pool: &Data<DbPool>, placement: Placement) -> Result<Self, ServiceError> {
+        let conn = get_conn(pool)?;
+
+        conn.transaction::<_, ServiceError, _>(|| {
+            Checklist::lock(&conn, self.checklist)?;
+
+            let position =
+                ChecklistItem::position_in(&conn, self.checklist, self.id, Some(placement))?;
+
+            Ok(diesel::update(self)
+                .set(checklist_items::position.eq(position))
+                .get_result::<Self>(&conn)?)
+        })
+    }
+
+    /// Unassigns users that can't access `board` from the checklist items of its cards,
+    /// e.g. after cards were moved or copied there or a member left
+    pub(crate) fn prune(conn: &PgConnection, board: Uuid) -> Result<usize, DieselError> {
+        let cards = cards::table
+            .inner_join(lists::table)
+            .filter(lists::board.eq(board))
+            .select(cards::id);
+        let checklists = checklists::table
+            .filter(checklists::card.eq_any(cards))
+            .select(checklists::id);
+        let members = board_members::table
+            .filter(board_members::board.eq(board))
+            .select(board_members::member.nullable());
+        let owner = boards::table.find(board).select(boards::owner.nullable());
+
+        diesel::update(
+            checklist_items::table
+                .filter(checklist_items::checklist.eq_any(checklists))
+                .filter(checklist_items::assignee.ne_all(members))
+                .filter(checklist_items::assignee.ne_all(owner)),
+        )
+        .set(checklist_items::assignee.eq(None::<Uuid>))
+        .execute(conn)
+    }
+
+    pub fn delete(&self, pool: &Data<DbPool>) -> Result<usize, ServiceError> {
+        let conn = get_conn(pool)?;
+
+        diesel::delete(self)
+            .execute(&conn)
+            .map_err(|_| ServiceError::InternalServerError)
+    }
+}
+
+impl ChecklistItemUpdate {
+    /// Returns true if all update fields are None
+    pub fn is_empty(&self) -> bool {
+        self.content.is_none()
+            && self.checked.is_none()
+            && self.assignee.is_none()
+            && self.due_at.is_none()
+    }
+}
@@ -0,0 +1,53 @@
+use std::collections::HashMap;
+
+use super::prelude::*;
+use crate::models::Attachment;
+
+/// What to take along when cloning boards, lists or cards. Assignees and custom field
+/// values are always cloned.
+#[derive(Debug, Clone, Copy, Deserialize)]
+#[serde(default)]
+pub struct CloneOptions {
+    /// Cards of cloned boards and lists
+    pub cards: bool,
+    pub labels: bool,
+    pub checklists: bool,
+    pub attachments: bool,
+    pub comments: bool,
+}
+
+impl Default for CloneOptions {
+    fn default() -> Self {
+        CloneOptions {
+            cards: true,
+            labels: true,
+            checklists: true,
+            attachments: false,
+            comments: false,
+        }
+    }
+}
+
+/// State of a deep clone in progress
+#[derive(Debug)]
+pub struct Cloning {
+    /// Board the clones end up on
+    pub board: Uuid,
+    pub options: CloneOptions,
+    /// Custom fields of the source board by their clones, empty when cloning within a
+    /// board
+    pub fields: HashMap<Uuid, Uuid>,
+    /// Cloned attachments with their originals, their blobs still have to be copied
+    pub attachments: Vec<(Attachment, Attachment)>,
+}
+
+impl Cloning {
+    pub fn new(board: Uuid, options: CloneOptions) -> Self {
+        Cloning {
+            board,
+            options,
+            fields: HashMap::new(),
+            attachments: Vec::new(),
+        }
+    }
+}
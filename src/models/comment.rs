@@ -0,0 +1,150 @@
+use chrono::{DateTime, Utc};
+use diesel::{result::Error as DieselError, PgConnection};
+
+use super::prelude::*;
+use crate::{
+    markdown,
+    models::{Card, User},
+    schema::comments,
+};
+
+#[derive(Debug, Identifiable, Queryable, Insertable, Associations, Serialize, Deserialize)]
+#[belongs_to(Card, foreign_key = "card")]
+#[table_name = "comments"]
+pub struct Comment {
+    #[serde(skip_deserializing)]
+    pub id: Uuid,
+    #[serde(skip_deserializing)]
+    pub card: Uuid,
+    /// `None` once the author has been deleted
+    #[serde(skip_deserializing)]
+    pub author: Option<Uuid>,
+    /// Markdown
+    pub content: String,
+    #[serde(skip_deserializing, default = "Utc::now")]
+    pub created_at: DateTime<Utc>,
+    /// Set when the comment has been edited
+    #[serde(skip_deserializing)]
+    pub edited_at: Option<DateTime<Utc>>,
+}
+
+/// Comment as returned by the API, optionally with its content rendered to HTML
+#[derive(Debug, Serialize)]
+pub struct CommentResponse {
+    #[serde(flatten)]
+    pub comment: Comment,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub content_html: Option<String>,
+}
+
+impl Comment {
+    pub fn new(card: &Card, author: &User, content: String) -> Self {
+        Comment {
+            id: Uuid::new_v4(),
+            card: card.id,
+            author: Some(author.id),
+            content,
+            created_at: Utc::now(),
+            edited_at: None,
+        }
+    }
+
+    pub fn save(&self, pool: &Data<DbPool>) -> Result<usize, ServiceError> {
+        let conn = get_conn(pool)?;
+
+        diesel::insert_into(comments::table)
+            .values(self)
+            .execute(&conn)
+            .map_err(|_| ServiceError::InternalServerError)
+    }
+
+    pub fn find(pool: &Data<DbPool>, id: Uuid) -> Result<Option<Self>, ServiceError> {
+        let conn = get_conn(pool)?;
+
+        comments::table
+            .find(id)
+            .first::<Self>(&conn)
+            .optional()
+            .map_err(|_| ServiceError::InternalServerError)
+    }
+
+    /// Returns a page of the comments of `card`, oldest first, and the total number
+    /// of comments
+    pub fn page(
+        pool: &Data<DbPool>,
+        card: &Card,
+        offset: i64,
+        limit: i64,
+    ) -> Result<(Vec<Self>, i64), ServiceError> {
+        let conn = get_conn(pool)?;
+
+        let total = Comment::belonging_to(card)
+            .count()
+            .get_result::<i64>(&conn)
+            .map_err(|_| ServiceError::InternalServerError)?;
+
+        let comments = Comment::belonging_to(card)
+            .order((comments::created_at, comments::id))
+            .offset(offset)
+            .limit(limit)
+            .load::<Self>(&conn)
+            .map_err(|_| ServiceError::InternalServerError)?;
+
+        Ok((comments, total))
+    }
+
+    /// Replaces the content and marks the comment as edited
+    pub fn edit(&self, pool: &Data<DbPool>, content: String) -> Result<Self, ServiceError> {
+        let conn = get_conn(pool)?;
+
+        diesel::update(self)
+            .set((
+                comments::content.eq(content),
+                comments::edited_at.eq(Utc::now()),
+            ))
+            .get_result::<Self>(&conn)
+            .map_err(|_| ServiceError::InternalServerError)
+    }
+
+    /// Wraps the comment for a response, rendering the content if `html` is set
+    pub fn render(self, html: bool) -> CommentResponse {
+        let content_html = if html {
+            Some(markdown::render(&self.content))
+        } else {
+            None
+        };
+
+        CommentResponse {
+            comment: self,
+            content_html,
+        }
+    }
+
+    /// Copies the comments of card `from` to card `to`, keeping their authors and dates
+    pub(crate) fn copy_all(conn: &PgConnection, from: Uuid, to: Uuid) -> Result<(), DieselError> {
+        let copies = comments::table
+            .filter(comments::card.eq(from))
+            .load::<Self>(conn)?
+            .into_iter()
+            .map(|comment| Comment {
+                id: Uuid::new_v4(),
+                card: to,
+                ..comment
+            })
+            .collect::<Vec<_>>();
+
+        diesel::insert_into(comments::table)
+            .values(&copies)
+            .execute(conn)?;
+
+        Ok(())
+    }
+
+    pub fn delete(&self, pool: &Data<DbPool>) -> Result<usize, ServiceError> {
+        let conn = get_conn(pool)?;
+
+        diesel::delete(self)
+            .execute(&conn)
+            .map_err(|_| ServiceError::InternalServerError)
+    }
+}
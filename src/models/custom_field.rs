@@ -0,0 +1,426 @@
+use chrono::NaiveDate;
+use diesel::{
+    deserialize::{self, FromSql},
+    pg::Pg,
+    result::{DatabaseErrorKind, Error as DieselError},
+    serialize::{self, Output, ToSql},
+    sql_query,
+    sql_types::{Jsonb, Text},
+    PgConnection,
+};
+use serde_json::Value;
+use std::{
+    collections::{BTreeSet, HashMap},
+    io::Write,
+};
+
+use super::prelude::*;
+use crate::{
+    models::{Board, Role, User},
+    schema::{card_fields, cards, custom_fields, lists},
+};
+
+/// Type of the values of a custom field
+#[derive(Debug, Clone, Copy, PartialEq, Eq, AsExpression, FromSqlRow, Serialize, Deserialize)]
+#[sql_type = "Text"]
+#[serde(rename_all = "lowercase")]
+pub enum FieldKind {
+    Text,
+    Number,
+    /// `YYYY-MM-DD`
+    Date,
+    /// One of the options
+    Select,
+    /// List of options
+    MultiSelect,
+    Checkbox,
+    /// Id of a user with access to the board
+    User,
+}
+
+impl ToSql<Text, Pg> for FieldKind {
+    fn to_sql<W: Write>(&self, out: &mut Output<W, Pg>) -> serialize::Result {
+        let kind = match self {
+            FieldKind::Text => "text",
+            FieldKind::Number => "number",
+            FieldKind::Date => "date",
+            FieldKind::Select => "select",
+            FieldKind::MultiSelect => "multiselect",
+            FieldKind::Checkbox => "checkbox",
+            FieldKind::User => "user",
+        };
+
+        ToSql::<Text, Pg>::to_sql(kind, out)
+    }
+}
+
+impl FromSql<Text, Pg> for FieldKind {
+    fn from_sql(bytes: Option<&[u8]>) -> deserialize::Result<Self> {
+        match not_none!(bytes) {
+            b"text" => Ok(FieldKind::Text),
+            b"number" => Ok(FieldKind::Number),
+            b"date" => Ok(FieldKind::Date),
+            b"select" => Ok(FieldKind::Select),
+            b"multiselect" => Ok(FieldKind::MultiSelect),
+            b"checkbox" => Ok(FieldKind::Checkbox),
+            b"user" => Ok(FieldKind::User),
+            _ => Err("Unrecognized field kind".into()),
+        }
+    }
+}
+
+impl FieldKind {
+    fn has_options(self) -> bool {
+        matches!(self, FieldKind::Select | FieldKind::MultiSelect)
+    }
+}
+
+#[derive(Debug, Identifiable, Queryable, Insertable, Associations, Serialize, Deserialize)]
+#[belongs_to(Board, foreign_key = "board")]
+#[table_name = "custom_fields"]
+pub struct CustomField {
+    #[serde(skip_deserializing)]
+    pub id: Uuid,
+    #[serde(skip_deserializing)]
+    pub board: Uuid,
+    pub name: String,
+    pub kind: FieldKind,
+    /// Choices of select fields
+    #[serde(default)]
+    pub options: Vec<String>,
+}
+
+/// The kind of a field can't be changed, existing values would no longer match it
+#[derive(Debug, AsChangeset, Deserialize)]
+#[table_name = "custom_fields"]
+pub struct CustomFieldUpdate {
+    #[serde(skip_deserializing)]
+    pub id: Uuid,
+    pub name: Option<String>,
+    pub options: Option<Vec<String>>,
+}
+
+/// Value of a custom field on a card
+#[derive(Debug, Identifiable, Queryable, Insertable, Associations)]
+#[belongs_to(CustomField, foreign_key = "field")]
+#[primary_key(card, field)]
+#[table_name = "card_fields"]
+pub struct FieldValue {
+    pub card: Uuid,
+    pub field: Uuid,
+    pub value: Value,
+}
+
+/// Only select fields have options, which must be unique
+fn check_options(kind: FieldKind, options: &[String]) -> Result<(), ServiceError> {
+    let unique = options.iter().collect::<BTreeSet<_>>().len() == options.len();
+
+    if (!kind.has_options() && !options.is_empty()) || !unique {
+        return Err(ServiceError::InvalidFieldOptions);
+    }
+
+    Ok(())
+}
+
+/// Field names are unique per board
+fn conflict(e: DieselError) -> ServiceError {
+    match e {
+        DieselError::DatabaseError(DatabaseErrorKind::UniqueViolation, _) => {
+            ServiceError::FieldExists
+        }
+        _ => ServiceError::InternalServerError,
+    }
+}
+
+impl CustomField {
+    pub fn new(
+        board: &Board,
+        name: String,
+        kind: FieldKind,
+        options: Vec<String>,
+    ) -> Result<Self, ServiceError> {
+        check_options(kind, &options)?;
+
+        Ok(CustomField {
+            id: Uuid::new_v4(),
+            board: board.id,
+            name,
+            kind,
+            options,
+        })
+    }
+
+    pub fn save(&self, pool: &Data<DbPool>) -> Result<usize, ServiceError> {
+        let conn = get_conn(pool)?;
+
+        diesel::insert_into(custom_fields::table)
+            .values(self)
+            .execute(&conn)
+            .map_err(conflict)
+    }
+
+    pub fn find(pool: &Data<DbPool>, id: Uuid) -> Result<Option<Self>, ServiceError> {
+        let conn = get_conn(pool)?;
+
+        custom_fields::table
+            .find(id)
+            .first::<Self>(&conn)
+            .optional()
+            .map_err(|_| ServiceError::InternalServerError)
+    }
+
+    /// Returns the fields of `board` ordered by name
+    pub fn for_board(pool: &Data<DbPool>, board: &Board) -> Result<Vec<Self>, ServiceError> {
+        let conn = get_conn(pool)?;
+
+        CustomField::belonging_to(board)
+            .order(custom_fields::name)
+            .load::<Self>(&conn)
+            .map_err(|_| ServiceError::InternalServerError)
+    }
+
+    /// Updates the field. Values using options that were removed lose them.
+    pub fn update(
+        &self,
+        pool: &Data<DbPool>,
+        data: CustomFieldUpdate,
+    ) -> Result<Self, ServiceError> {
+        if let Some(options) = &data.options {
+            check_options(self.kind, options)?;
+        }
+
+        let conn = get_conn(pool)?;
+
+        conn.transaction::<_, ServiceError, _>(|| {
+            let field = diesel::update(self)
+                .set(&data)
+                .get_result::<Self>(&conn)
+                .map_err(conflict)?;
+
+            if data.options.is_some() {
+                field.drop_stale_options(&conn)?;
+            }
+
+            Ok(field)
+        })
+    }
+
+    /// Removes options that no longer exist from the values of the field
+    fn drop_stale_options(&self, conn: &PgConnection) -> Result<(), DieselError> {
+        let options = Value::from(self.options.clone());
+
+        let query = match self.kind {
+            FieldKind::Select => sql_query(
+                "DELETE FROM card_fields WHERE field = $1 AND NOT $2 @> jsonb_build_array(value)",
+            ),
+            // Emptied values are removed afterwards
+            _ => sql_query(
+                "UPDATE card_fields SET value = (
+                    SELECT COALESCE(jsonb_agg(element ORDER BY n), '[]')
+                    FROM jsonb_array_elements(value) WITH ORDINALITY AS e (element, n)
+                    WHERE $2 @> jsonb_build_array(element)
+                ) WHERE field = $1",
+            ),
+        };
+        query
+            .bind::<diesel::sql_types::Uuid, _>(self.id)
+            .bind::<Jsonb, _>(&options)
+            .execute(conn)?;
+
+        diesel::delete(
+            card_fields::table
+                .filter(card_fields::field.eq(self.id))
+                .filter(card_fields::value.eq(Value::Array(Vec::new()))),
+        )
+        .execute(conn)?;
+
+        Ok(())
+    }
+
+    /// Checks a value against the field definition, returning it normalized
+    pub fn check_value(
+        &self,
+        pool: &Data<DbPool>,
+        board: &Board,
+        value: Value,
+    ) -> Result<Value, ServiceError> {
+        let invalid = || ServiceError::InvalidFieldValue(self.name.clone());
+
+        match (self.kind, value) {
+            (FieldKind::Text, Value::String(text)) if !text.trim().is_empty() => {
+                Ok(Value::String(text))
+            }
+            (FieldKind::Number, Value::Number(number)) => Ok(Value::Number(number)),
+            (FieldKind::Date, Value::String(date)) => {
+                NaiveDate::parse_from_str(&date, "%Y-%m-%d").map_err(|_| invalid())?;
+                Ok(Value::String(date))
+            }
+            (FieldKind::Select, Value::String(option)) if self.options.contains(&option) => {
+                Ok(Value::String(option))
+            }
+            (FieldKind::MultiSelect, Value::Array(selected)) => {
+                let mut options = BTreeSet::new();
+                for option in selected {
+                    match option {
+                        Value::String(option) if self.options.contains(&option) => {
+                            options.insert(option);
+                        }
+                        _ => return Err(invalid()),
+                    }
+                }
+
+                // Keep the order of the definition
+                Ok(self
+                    .options
+                    .iter()
+                    .filter(|option| options.contains(*option))
+                    .cloned()
+                    .collect())
+            }
+            (FieldKind::Checkbox, Value::Bool(checked)) => Ok(Value::Bool(checked)),
+            (FieldKind::User, Value::String(id)) => {
+                let id = Uuid::parse_str(&id).map_err(|_| invalid())?;
+                match User::find(pool, id)? {
+                    Some(user) if board.allows(pool, &user, Role::Viewer)? => {
+                        Ok(Value::String(id.to_string()))
+                    }
+                    _ => Err(invalid()),
+                }
+            }
+            _ => Err(invalid()),
+        }
+    }
+
+    /// Parses a filter from a query string into a value that matching values contain
+    pub fn filter_value(&self, filter: &str) -> Option<Value> {
+        match self.kind {
+            FieldKind::Number => filter.parse::<f64>().ok().map(Value::from),
+            FieldKind::Checkbox => filter.parse::<bool>().ok().map(Value::Bool),
+            FieldKind::MultiSelect => Some(Value::from(vec![filter])),
+            _ => Some(Value::from(filter)),
+        }
+    }
+
+    pub fn delete(&self, pool: &Data<DbPool>) -> Result<usize, ServiceError> {
+        let conn = get_conn(pool)?;
+
+        diesel::delete(self)
+            .execute(&conn)
+            .map_err(|_| ServiceError::InternalServerError)
+    }
+}
+
+impl CustomFieldUpdate {
+    /// Returns true if all update fields are None
+    pub fn is_empty(&self) -> bool {
+        self.name.is_none() && self.options.is_none()
+    }
+}
+
+impl FieldValue {
+    /// Sets the values of a card, `None` removes a value
+    pub(crate) fn set_all(
+        conn: &PgConnection,
+        card: Uuid,
+        values: Vec<(Uuid, Option<Value>)>,
+    ) -> Result<(), DieselError> {
+        for (field, value) in values {
+            match value {
+                Some(value) => {
+                    let row = FieldValue { card, field, value };
+                    diesel::insert_into(card_fields::table)
+                        .values(&row)
+                        .on_conflict((card_fields::card, card_fields::field))
+                        .do_update()
+                        .set(card_fields::value.eq(&row.value))
+                        .execute(conn)?
+                }
+                None => diesel::delete(card_fields::table.find((card, field))).execute(conn)?,
+            };
+        }
+
+        Ok(())
+    }
+
+    /// Returns the values of the given cards
+    pub(crate) fn for_cards(conn: &PgConnection, cards: &[Uuid]) -> Result<Vec<Self>, DieselError> {
+        card_fields::table
+            .filter(card_fields::card.eq_any(cards))
+            .load::<Self>(conn)
+    }
+
+    /// Copies the values of card `from` to card `to` if they belong to fields of `board`
+    pub(crate) fn copy_all(
+        conn: &PgConnection,
+        from: Uuid,
+        to: Uuid,
+        board: Uuid,
+    ) -> Result<(), DieselError> {
+        let copies = card_fields::table
+            .inner_join(custom_fields::table)
+            .filter(card_fields::card.eq(from))
+            .filter(custom_fields::board.eq(board))
+            .select((card_fields::field, card_fields::value))
+            .load::<(Uuid, Value)>(conn)?
+            .into_iter()
+            .map(|(field, value)| FieldValue {
+                card: to,
+                field,
+                value,
+            })
+            .collect::<Vec<_>>();
+
+        diesel::insert_into(card_fields::table)
+            .values(&copies)
+            .execute(conn)?;
+
+        Ok(())
+    }
+
+    /// Copies the values of card `from` to card `to` on another board, `fields` maps the
+    /// fields of the source board to their counterparts
+    pub(crate) fn copy_mapped(
+        conn: &PgConnection,
+        from: Uuid,
+        to: Uuid,
+        fields: &HashMap<Uuid, Uuid>,
+    ) -> Result<(), DieselError> {
+        let copies = card_fields::table
+            .filter(card_fields::card.eq(from))
+            .load::<FieldValue>(conn)?
+            .into_iter()
+            .filter_map(|value| {
+                fields.get(&value.field).map(|&field| FieldValue {
+                    card: to,
+                    field,
+                    value: value.value,
+                })
+            })
+            .collect::<Vec<_>>();
+
+        diesel::insert_into(card_fields::table)
+            .values(&copies)
+            .execute(conn)?;
+
+        Ok(())
+    }
+
+    /// Removes values of fields of other boards from the cards of `board`, after cards
+    /// were moved there
+    pub(crate) fn prune(conn: &PgConnection, board: Uuid) -> Result<usize, DieselError> {
+        let cards = cards::table
+            .inner_join(lists::table)
+            .filter(lists::board.eq(board))
+            .select(cards::id);
+        let fields = custom_fields::table
+            .filter(custom_fields::board.eq(board))
+            .select(custom_fields::id);
+
+        diesel::delete(
+            card_fields::table
+                .filter(card_fields::card.eq_any(cards))
+                .filter(card_fields::field.ne_all(fields)),
+        )
+        .execute(conn)
+    }
+}
@@ -0,0 +1,230 @@
+use diesel::{result::Error as DieselError, PgConnection};
+
+use super::prelude::*;
+use crate::{
+    models::User,
+    schema::{group_members, groups},
+};
+
+#[derive(Debug, Identifiable, Queryable, Insertable, Serialize, Deserialize)]
+#[table_name = "groups"]
+pub struct Group {
+    #[serde(skip_deserializing)]
+    pub id: Uuid,
+    pub name: String,
+    /// Where the group is managed, e.g. `local` or the name of a directory service
+    #[serde(skip_deserializing)]
+    pub origin: String,
+    pub external_id: Option<String>,
+}
+
+#[derive(Debug, AsChangeset)]
+#[table_name = "groups"]
+#[changeset_options(treat_none_as_null = "true")]
+pub struct GroupUpdate {
+    pub id: Uuid,
+    pub name: String,
+    pub external_id: Option<String>,
+}
+
+#[derive(Debug, Identifiable, Queryable, Insertable, Associations)]
+#[belongs_to(Group, foreign_key = "group_id")]
+#[belongs_to(User, foreign_key = "member")]
+#[primary_key(group_id, member)]
+#[table_name = "group_members"]
+pub struct GroupMember {
+    pub group_id: Uuid,
+    pub member: Uuid,
+}
+
+/// Change to the members of a group, see [`Group::patch`]
+#[derive(Debug)]
+pub enum MemberChange {
+    Add(Vec<Uuid>),
+    Remove(Vec<Uuid>),
+    Set(Vec<Uuid>),
+}
+
+impl Group {
+    pub fn new(name: String, origin: &str, external_id: Option<String>) -> Self {
+        Group {
+            id: Uuid::new_v4(),
+            name,
+            origin: origin.to_string(),
+            external_id,
+        }
+    }
+
+    pub fn save(&self, pool: &Data<DbPool>) -> Result<usize, ServiceError> {
+        let conn = get_conn(pool)?;
+
+        diesel::insert_into(groups::table)
+            .values(self)
+            .execute(&conn)
+            .map_err(|_| ServiceError::InternalServerError)
+    }
+
+    pub fn find(pool: &Data<DbPool>, id: Uuid) -> Result<Option<Self>, ServiceError> {
+        let conn = get_conn(pool)?;
+
+        groups::table
+            .find(id)
+            .first::<Self>(&conn)
+            .optional()
+            .map_err(|_| ServiceError::InternalServerError)
+    }
+
+    pub fn update(&self, pool: &Data<DbPool>, data: GroupUpdate) -> Result<Self, ServiceError> {
+        let conn = get_conn(pool)?;
+
+        diesel::update(self)
+            .set(&data)
+            .get_result::<Self>(&conn)
+            .map_err(|_| ServiceError::InternalServerError)
+    }
+
+    pub fn delete(&self, pool: &Data<DbPool>) -> Result<usize, ServiceError> {
+        let conn = get_conn(pool)?;
+
+        diesel::delete(self)
+            .execute(&conn)
+            .map_err(|_| ServiceError::InternalServerError)
+    }
+
+    /// Returns ids of all users in the group
+    pub fn members(&self, pool: &Data<DbPool>) -> Result<Vec<Uuid>, ServiceError> {
+        let conn = get_conn(pool)?;
+
+        GroupMember::belonging_to(self)
+            .select(group_members::member)
+            .load::<Uuid>(&conn)
+            .map_err(|_| ServiceError::InternalServerError)
+    }
+
+    pub fn add_members(
+        &self,
+        pool: &Data<DbPool>,
+        members: &[Uuid],
+    ) -> Result<usize, ServiceError> {
+        let conn = get_conn(pool)?;
+
+        self.insert_members(&conn, members)
+            .map_err(|_| ServiceError::InternalServerError)
+    }
+
+    /// Replaces all members of the group with `members`
+    pub fn set_members(&self, pool: &Data<DbPool>, members: &[Uuid]) -> Result<(), ServiceError> {
+        let conn = get_conn(pool)?;
+
+        conn.transaction::<_, DieselError, _>(|| {
+            diesel::delete(GroupMember::belonging_to(self)).execute(&conn)?;
+            self.insert_members(&conn, members)?;
+
+            Ok(())
+        })
+        .map_err(|_| ServiceError::InternalServerError)
+    }
+
+    /// Applies the member changes in order and updates the group, all or nothing
+    pub fn patch(
+        &self,
+        pool: &Data<DbPool>,
+        changes: &[MemberChange],
+        data: GroupUpdate,
+    ) -> Result<Self, ServiceError> {
+        let conn = get_conn(pool)?;
+
+        conn.transaction::<_, ServiceError, _>(|| {
+            for change in changes {
+                match change {
+                    MemberChange::Add(members) => {
+                        self.insert_members(&conn, members)?;
+                    }
+                    MemberChange::Remove(members) => {
+                        diesel::delete(
+                            GroupMember::belonging_to(self)
+                                .filter(group_members::member.eq_any(members)),
+                        )
+                        .execute(&conn)?;
+                    }
+                    MemberChange::Set(members) => {
+                        diesel::delete(GroupMember::belonging_to(self)).execute(&conn)?;
+                        self.insert_members(&conn, members)?;
+                    }
+                }
+            }
+
+            Ok(diesel::update(self).set(&data).get_result::<Self>(&conn)?)
+        })
+    }
+
+    fn insert_members(&self, conn: &PgConnection, members: &[Uuid]) -> Result<usize, DieselError> {
+        let memberships = members
+            .iter()
+            .map(|&member| GroupMember {
+                group_id: self.id,
+                member,
+            })
+            .collect::<Vec<_>>();
+
+        diesel::insert_into(group_members::table)
+            .values(&memberships)
+            .on_conflict_do_nothing()
+            .execute(conn)
+    }
+
+    /// Replaces the memberships `user` has in groups of the given origin with `names`,
+    /// creating groups that don't exist yet
+    pub fn sync_memberships(
+        pool: &Data<DbPool>,
+        user: &User,
+        origin: &str,
+        names: &[String],
+    ) -> Result<(), ServiceError> {
+        let conn = get_conn(pool)?;
+
+        conn.transaction::<_, DieselError, _>(|| {
+            let new_groups = names
+                .iter()
+                .map(|name| Group::new(name.clone(), origin, None))
+                .collect::<Vec<_>>();
+            diesel::insert_into(groups::table)
+                .values(&new_groups)
+                .on_conflict((groups::origin, groups::name))
+                .do_nothing()
+                .execute(&conn)?;
+
+            let group_ids = groups::table
+                .filter(groups::origin.eq(origin))
+                .filter(groups::name.eq_any(names))
+                .select(groups::id)
+                .load::<Uuid>(&conn)?;
+
+            let stale = groups::table
+                .filter(groups::origin.eq(origin))
+                .filter(groups::id.ne_all(&group_ids))
+                .select(groups::id);
+            diesel::delete(
+                group_members::table
+                    .filter(group_members::member.eq(user.id))
+                    .filter(group_members::group_id.eq_any(stale)),
+            )
+            .execute(&conn)?;
+
+            let memberships = group_ids
+                .into_iter()
+                .map(|group_id| GroupMember {
+                    group_id,
+                    member: user.id,
+                })
+                .collect::<Vec<_>>();
+            diesel::insert_into(group_members::table)
+                .values(&memberships)
+                .on_conflict_do_nothing()
+                .execute(&conn)?;
+
+            Ok(())
+        })
+        .map_err(|_| ServiceError::InternalServerError)
+    }
+}
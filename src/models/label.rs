@@ -0,0 +1,224 @@
+use diesel::{
+    result::{DatabaseErrorKind, Error as DieselError},
+    PgConnection,
+};
+
+use super::prelude::*;
+use crate::{
+    models::Board,
+    schema::{card_labels, labels},
+};
+
+#[derive(Debug, Identifiable, Queryable, Insertable, Associations, Serialize, Deserialize)]
+#[belongs_to(Board, foreign_key = "board")]
+#[table_name = "labels"]
+pub struct Label {
+    #[serde(skip_deserializing)]
+    pub id: Uuid,
+    #[serde(skip_deserializing)]
+    pub board: Uuid,
+    pub name: String,
+    /// Hex code like `#61bd4f`
+    pub color: String,
+    pub description: Option<String>,
+}
+
+#[derive(Debug, AsChangeset, Deserialize)]
+#[table_name = "labels"]
+pub struct LabelUpdate {
+    #[serde(skip_deserializing)]
+    pub id: Uuid,
+    pub name: Option<String>,
+    pub color: Option<String>,
+    /// `null` removes the description
+    #[serde(default, deserialize_with = "super::nullable")]
+    pub description: Option<Option<String>>,
+}
+
+/// Normalizes a color to a lowercase hex code
+fn check_color(color: &str) -> Result<String, ServiceError> {
+    let valid = color.len() == 7
+        && color.starts_with('#')
+        && color[1..].chars().all(|c| c.is_ascii_hexdigit());
+
+    if valid {
+        Ok(color.to_lowercase())
+    } else {
+        Err(ServiceError::InvalidColor(color.to_string()))
+    }
+}
+
+/// Label names are unique per board
+fn conflict(e: DieselError) -> ServiceError {
+    match e {
+        DieselError::DatabaseError(DatabaseErrorKind::UniqueViolation, _) => {
+            ServiceError::LabelExists
+        }
+        _ => ServiceError::InternalServerError,
+    }
+}
+
+impl Label {
+    pub fn new(
+        board: &Board,
+        name: String,
+        color: &str,
+        description: Option<String>,
+    ) -> Result<Self, ServiceError> {
+        Ok(Label {
+            id: Uuid::new_v4(),
+            board: board.id,
+            name,
+            color: check_color(color)?,
+            description,
+        })
+    }
+
+    pub fn save(&self, pool: &Data<DbPool>) -> Result<usize, ServiceError> {
+        let conn = get_conn(pool)?;
+
+        diesel::insert_into(labels::table)
+            .values(self)
+            .execute(&conn)
+            .map_err(conflict)
+    }
+
+    pub fn find(pool: &Data<DbPool>, id: Uuid) -> Result<Option<Self>, ServiceError> {
+        let conn = get_conn(pool)?;
+
+        labels::table
+            .find(id)
+            .first::<Self>(&conn)
+            .optional()
+            .map_err(|_| ServiceError::InternalServerError)
+    }
+
+    /// Returns the labels of `board` ordered by name
+    pub fn for_board(pool: &Data<DbPool>, board: &Board) -> Result<Vec<Self>, ServiceError> {
+        let conn = get_conn(pool)?;
+
+        Label::belonging_to(board)
+            .order(labels::name)
+            .load::<Self>(&conn)
+            .map_err(|_| ServiceError::InternalServerError)
+    }
+
+    /// Returns the labels of the given cards as `(card, label)` pairs, ordered by name
+    pub(crate) fn for_cards(
+        conn: &PgConnection,
+        cards: &[Uuid],
+    ) -> Result<Vec<(Uuid, Self)>, DieselError> {
+        card_labels::table
+            .inner_join(labels::table)
+            .filter(card_labels::card.eq_any(cards))
+            .order(labels::name)
+            .select((card_labels::card, labels::all_columns))
+            .load::<(Uuid, Self)>(conn)
+    }
+
+    pub fn update(&self, pool: &Data<DbPool>, mut data: LabelUpdate) -> Result<Self, ServiceError> {
+        if let Some(color) = &data.color {
+            data.color = Some(check_color(color)?);
+        }
+
+        let conn = get_conn(pool)?;
+
+        diesel::update(self)
+            .set(&data)
+            .get_result::<Self>(&conn)
+            .map_err(conflict)
+    }
+
+    /// Adds the label to a card, adding it twice is not an error
+    pub fn attach(&self, pool: &Data<DbPool>, card: Uuid) -> Result<usize, ServiceError> {
+        let conn = get_conn(pool)?;
+
+        diesel::insert_into(card_labels::table)
+            .values((card_labels::card.eq(card), card_labels::label.eq(self.id)))
+            .on_conflict_do_nothing()
+            .execute(&conn)
+            .map_err(|_| ServiceError::InternalServerError)
+    }
+
+    /// Removes the label from a card, returns 0 if the card didn't have it
+    pub fn detach(&self, pool: &Data<DbPool>, card: Uuid) -> Result<usize, ServiceError> {
+        let conn = get_conn(pool)?;
+
+        diesel::delete(card_labels::table.find((card, self.id)))
+            .execute(&conn)
+            .map_err(|_| ServiceError::InternalServerError)
+    }
+
+    /// Gives card `to`, which belongs to `board`, the labels of card `from`. Labels of
+    /// other boards are replaced by the label with the same name on `board`, which is
+    /// created if it doesn't exist. Passing the same card twice relabels a card that was
+    /// moved to `board`.
+    pub(crate) fn copy_links(
+        conn: &PgConnection,
+        from: Uuid,
+        to: Uuid,
+        board: Uuid,
+    ) -> Result<(), DieselError> {
+        let linked = card_labels::table
+            .inner_join(labels::table)
+            .filter(card_labels::card.eq(from))
+            .select(labels::all_columns)
+            .load::<Self>(conn)?;
+
+        if from == to {
+            diesel::delete(card_labels::table.filter(card_labels::card.eq(from))).execute(conn)?;
+        }
+
+        for label in linked {
+            let id = if label.board == board {
+                label.id
+            } else {
+                label.counterpart(conn, board)?
+            };
+
+            diesel::insert_into(card_labels::table)
+                .values((card_labels::card.eq(to), card_labels::label.eq(id)))
+                .on_conflict_do_nothing()
+                .execute(conn)?;
+        }
+
+        Ok(())
+    }
+
+    /// Returns the id of the label with the same name on `board`, creating it as a copy
+    /// of this label if needed
+    fn counterpart(&self, conn: &PgConnection, board: Uuid) -> Result<Uuid, DieselError> {
+        diesel::insert_into(labels::table)
+            .values(&Label {
+                id: Uuid::new_v4(),
+                board,
+                name: self.name.clone(),
+                color: self.color.clone(),
+                description: self.description.clone(),
+            })
+            .on_conflict((labels::board, labels::name))
+            .do_nothing()
+            .execute(conn)?;
+
+        labels::table
+            .filter(labels::board.eq(board))
+            .filter(labels::name.eq(&self.name))
+            .select(labels::id)
+            .first::<Uuid>(conn)
+    }
+
+    pub fn delete(&self, pool: &Data<DbPool>) -> Result<usize, ServiceError> {
+        let conn = get_conn(pool)?;
+
+        diesel::delete(self)
+            .execute(&conn)
+            .map_err(|_| ServiceError::InternalServerError)
+    }
+}
+
+impl LabelUpdate {
+    /// Returns true if all update fields are None
+    pub fn is_empty(&self) -> bool {
+        self.name.is_none() && self.color.is_none() && self.description.is_none()
+    }
+}
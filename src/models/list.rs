@@ -1,5 +1,21 @@
+use chrono::{DateTime, Utc};
+use diesel::{
+    dsl::sql,
+    result::Error as DieselError,
+    sql_types::{BigInt, Double},
+    PgConnection,
+};
+use std::collections::HashMap;
+
 use super::prelude::*;
-use crate::{models::Board, schema::lists};
+use crate::{
+    models::{
+        Assignment, Attachment, Board, Card, ChecklistItem, CloneOptions, Cloning, Estimate,
+        FieldValue, Label, Removed, User,
+    },
+    rank::{self, Parent, Placement, Ranked},
+    schema::{cards, lists},
+};
 
 #[derive(Debug, Identifiable, Queryable, Insertable, Associations, Serialize, Deserialize)]
 #[belongs_to(Board, foreign_key = "board")]
@@ -10,6 +26,29 @@ pub struct List {
     #[serde(skip_deserializing)]
     pub board: Uuid,
     pub name: String,
+    #[serde(skip_deserializing)]
+    pub position: String,
+    /// Maximum number of cards in the list
+    pub wip_limit: Option<i32>,
+    /// Soft limits only flag the list as over the limit instead of rejecting cards
+    #[serde(default)]
+    pub wip_soft: bool,
+    #[serde(skip_deserializing)]
+    pub archived_at: Option<DateTime<Utc>>,
+    #[serde(skip)]
+    pub deleted_at: Option<DateTime<Utc>>,
+    #[serde(skip)]
+    pub deleted_by: Option<Uuid>,
+}
+
+/// List as returned by the API, with the estimates of its cards rolled up
+#[derive(Debug, Serialize)]
+pub struct ListResponse {
+    #[serde(flatten)]
+    pub list: List,
+    pub estimate: Estimate,
+    /// Set if the list holds more cards than its limit allows
+    pub over_limit: bool,
 }
 
 #[derive(Debug, AsChangeset, Deserialize)]
@@ -18,37 +57,120 @@ pub struct ListUpdate {
     #[serde(skip_deserializing)]
     pub id: Uuid,
     pub name: Option<String>,
+    /// `null` removes the limit
+    #[serde(default, deserialize_with = "super::nullable")]
+    pub wip_limit: Option<Option<i32>>,
+    pub wip_soft: Option<bool>,
 }
 
+/// Limits must allow at least one card
+fn check_wip_limit(wip_limit: Option<i32>) -> Result<(), ServiceError> {
+    match wip_limit {
+        Some(limit) if limit < 1 => Err(ServiceError::InvalidWipLimit),
+        _ => Ok(()),
+    }
+}
+
+rank::parent!(List, lists);
+rank::ranked!(List, lists, board);
+
 impl List {
     pub fn new(board: &Board, name: String) -> Self {
         List {
             id: Uuid::new_v4(),
             board: board.id,
             name,
+            position: String::new(),
+            wip_limit: None,
+            wip_soft: false,
+            archived_at: None,
+            deleted_at: None,
+            deleted_by: None,
         }
     }
 
-    pub fn save(&self, pool: &Data<DbPool>) -> Result<usize, ServiceError> {
+    /// Inserts the list after the last list of its board
+    pub fn save(&mut self, pool: &Data<DbPool>) -> Result<usize, ServiceError> {
+        check_wip_limit(self.wip_limit)?;
+
         let conn = get_conn(pool)?;
 
-        diesel::insert_into(lists::table)
-            .values(self)
-            .execute(&conn)
-            .map_err(|_| ServiceError::InternalServerError)
+        conn.transaction::<_, ServiceError, _>(|| {
+            Board::lock(&conn, self.board)?;
+
+            self.position = List::position_in(&conn, self.board, self.id, None)?;
+
+            Ok(diesel::insert_into(lists::table)
+                .values(&*self)
+                .execute(&conn)?)
+        })
     }
 
+    /// Items in the trash aren't found, see [`List::find_trashed`]
     pub fn find(pool: &Data<DbPool>, id: Uuid) -> Result<Option<Self>, ServiceError> {
         let conn = get_conn(pool)?;
 
         lists::table
             .find(id)
+            .filter(lists::deleted_at.is_null())
             .first::<Self>(&conn)
             .optional()
             .map_err(|_| ServiceError::InternalServerError)
     }
 
+    /// Wraps lists for a response with the total estimates of their unarchived cards,
+    /// flagging lists over their limit
+    pub fn render(
+        pool: &Data<DbPool>,
+        lists: Vec<Self>,
+    ) -> Result<Vec<ListResponse>, ServiceError> {
+        let conn = get_conn(pool)?;
+
+        let ids = lists.iter().map(|list| list.id).collect::<Vec<_>>();
+        let totals = cards::table
+            .filter(cards::list.eq_any(&ids))
+            .filter(cards::archived_at.is_null())
+            .group_by(cards::list)
+            .select((
+                cards::list,
+                sql::<BigInt>("COUNT(*)"),
+                sql::<Double>("COALESCE(SUM(cards.estimate), 0)"),
+                sql::<Double>(
+                    "COALESCE(SUM(cards.estimate) FILTER (WHERE NOT cards.completed), 0)",
+                ),
+            ))
+            .load::<(Uuid, i64, f64, f64)>(&conn)
+            .map_err(|_| ServiceError::InternalServerError)?
+            .into_iter()
+            .map(|(list, count, total, remaining)| (list, (count, Estimate { total, remaining })))
+            .collect::<HashMap<_, _>>();
+
+        Ok(lists
+            .into_iter()
+            .map(|list| {
+                let (count, estimate) = totals.get(&list.id).copied().unwrap_or_default();
+
+                ListResponse {
+                    estimate,
+                    over_limit: matches!(list.wip_limit, Some(limit) if count > limit.into()),
+                    list,
+                }
+            })
+            .collect())
+    }
+
+    /// Wraps a single list for a response, see [`List::render`]
+    pub fn render_one(self, pool: &Data<DbPool>) -> Result<ListResponse, ServiceError> {
+        let mut responses = List::render(pool, vec![self])?;
+
+        Ok(responses.remove(0))
+    }
+
+    /// Updates the list. Lowering the limit below the number of cards is allowed, the
+    /// list is then flagged as over the limit.
     pub fn update(&self, pool: &Data<DbPool>, data: ListUpdate) -> Result<Self, ServiceError> {
+        check_wip_limit(data.wip_limit.flatten())?;
+
         let conn = get_conn(pool)?;
 
         diesel::update(self)
@@ -57,18 +179,250 @@ impl List {
             .map_err(|_| ServiceError::InternalServerError)
     }
 
-    pub fn delete(&self, pool: &Data<DbPool>) -> Result<usize, ServiceError> {
+    /// Fails if `card` can't be added to the list without exceeding a hard limit.
+    /// Expects the list to be locked, so concurrent additions can't both pass.
+    pub(crate) fn check_limit(
+        conn: &PgConnection,
+        id: Uuid,
+        card: Uuid,
+    ) -> Result<(), ServiceError> {
+        let (wip_limit, wip_soft) = lists::table
+            .find(id)
+            .select((lists::wip_limit, lists::wip_soft))
+            .first::<(Option<i32>, bool)>(conn)?;
+
+        let limit = match wip_limit {
+            Some(limit) if !wip_soft => limit,
+            _ => return Ok(()),
+        };
+
+        let count = cards::table
+            .filter(cards::list.eq(id))
+            .filter(cards::id.ne(card))
+            .filter(cards::archived_at.is_null())
+            .count()
+            .get_result::<i64>(conn)?;
+
+        if count >= limit.into() {
+            return Err(ServiceError::WipLimitReached(limit));
+        }
+
+        Ok(())
+    }
+
+    /// Moves the list with all its cards into `board`, next to one of its lists or after
+    /// all of them. Concurrent moves into a board are serialized, so they can't end up
+    /// with clashing positions. Labels of the cards are carried over to the new board,
+    /// assignees without access to it and custom field values of the old board are
+    /// dropped.
+    pub fn move_to(
+        &self,
+        pool: &Data<DbPool>,
+        board: Uuid,
+        placement: Option<Placement>,
+    ) -> Result<Self, ServiceError> {
+        let conn = get_conn(pool)?;
+
+        conn.transaction::<_, ServiceError, _>(|| {
+            Board::lock(&conn, board)?;
+            List::lock(&conn, self.id)?;
+
+            let position = List::position_in(&conn, board, self.id, placement)?;
+
+            if board != self.board {
+                let cards = cards::table
+                    .filter(cards::list.eq(self.id))
+                    .select(cards::id)
+                    .load::<Uuid>(&conn)?;
+                for card in cards {
+                    Label::copy_links(&conn, card, card, board)?;
+                }
+            }
+
+            let list = diesel::update(self)
+                .set((lists::board.eq(board), lists::position.eq(position)))
+                .get_result::<Self>(&conn)?;
+            Assignment::prune(&conn, board)?;
+            ChecklistItem::prune(&conn, board)?;
+            FieldValue::prune(&conn, board)?;
+
+            Ok(list)
+        })
+    }
+
+    /// Copies the list and all its unarchived cards with their checklists, labels,
+    /// assignees and custom field values into `board`, keeping the order of the cards.
+    /// Assignees without access to `board` and values of fields of other boards are
+    /// dropped.
+    pub fn copy_to(
+        &self,
+        pool: &Data<DbPool>,
+        board: Uuid,
+        placement: Option<Placement>,
+    ) -> Result<Self, ServiceError> {
+        let conn = get_conn(pool)?;
+
+        conn.transaction::<_, ServiceError, _>(|| {
+            Board::lock(&conn, board)?;
+            List::lock(&conn, self.id)?;
+
+            // The copy doesn't exist yet, so no list is excluded
+            let position = List::position_in(&conn, board, Uuid::nil(), placement)?;
+
+            let mut cloning = Cloning::new(board, CloneOptions::default());
+            let copy = self.clone_to(&conn, board, position, &mut cloning)?;
+            Assignment::prune(&conn, board)?;
+            ChecklistItem::prune(&conn, board)?;
+
+            Ok(copy)
+        })
+    }
+
+    /// Clones the list right after itself, see [`CloneOptions`]. Returns the clone along
+    /// with the cloned attachments, whose blobs still have to be copied.
+    pub fn duplicate(
+        &self,
+        pool: &Data<DbPool>,
+        options: CloneOptions,
+    ) -> Result<(Self, Vec<(Attachment, Attachment)>), ServiceError> {
+        let conn = get_conn(pool)?;
+
+        conn.transaction::<_, ServiceError, _>(|| {
+            Board::lock(&conn, self.board)?;
+
+            // The clone doesn't exist yet, so no list is excluded
+            let position = List::position_in(
+                &conn,
+                self.board,
+                Uuid::nil(),
+                Some(Placement::After(self.id)),
+            )?;
+
+            let mut cloning = Cloning::new(self.board, options);
+            let copy = self.clone_to(&conn, self.board, position, &mut cloning)?;
+
+            Ok((copy, cloning.attachments))
+        })
+    }
+
+    /// Inserts an unarchived copy of the list into `board`, with its unarchived cards in
+    /// the same order if the options of `cloning` include cards
+    pub(crate) fn clone_to(
+        &self,
+        conn: &PgConnection,
+        board: Uuid,
+        position: String,
+        cloning: &mut Cloning,
+    ) -> Result<Self, DieselError> {
+        let copy = diesel::insert_into(lists::table)
+            .values(&List {
+                id: Uuid::new_v4(),
+                board,
+                name: self.name.clone(),
+                position,
+                wip_limit: self.wip_limit,
+                wip_soft: self.wip_soft,
+                archived_at: None,
+                deleted_at: None,
+                deleted_by: None,
+            })
+            .get_result::<Self>(conn)?;
+
+        if cloning.options.cards {
+            let cards = Card::belonging_to(self)
+                .filter(cards::archived_at.is_null())
+                .filter(cards::deleted_at.is_null())
+                .load::<Card>(conn)?;
+            for card in cards {
+                card.clone_to(conn, copy.id, card.position.clone(), cloning)?;
+            }
+        }
+
+        Ok(copy)
+    }
+
+    /// Hides the list from listings until it is restored
+    pub fn archive(&self, pool: &Data<DbPool>) -> Result<Self, ServiceError> {
+        let conn = get_conn(pool)?;
+
+        diesel::update(self)
+            .set(lists::archived_at.eq(Some(Utc::now())))
+            .get_result::<Self>(&conn)
+            .map_err(|_| ServiceError::InternalServerError)
+    }
+
+    pub fn restore(&self, pool: &Data<DbPool>) -> Result<Self, ServiceError> {
+        let conn = get_conn(pool)?;
+
+        diesel::update(self)
+            .set(lists::archived_at.eq(None::<DateTime<Utc>>))
+            .get_result::<Self>(&conn)
+            .map_err(|_| ServiceError::InternalServerError)
+    }
+
+    pub fn is_archived(&self) -> bool {
+        self.archived_at.is_some()
+    }
+
+    pub fn find_trashed(pool: &Data<DbPool>, id: Uuid) -> Result<Option<Self>, ServiceError> {
+        let conn = get_conn(pool)?;
+
+        lists::table
+            .find(id)
+            .filter(lists::deleted_at.is_not_null())
+            .first::<Self>(&conn)
+            .optional()
+            .map_err(|_| ServiceError::InternalServerError)
+    }
+
+    /// Moves an archived list to the trash of `user`, its contents go along with it
+    pub fn trash(&self, pool: &Data<DbPool>, user: &User) -> Result<usize, ServiceError> {
+        if !self.is_archived() {
+            return Err(ServiceError::NotArchived);
+        }
+
         let conn = get_conn(pool)?;
 
-        diesel::delete(self)
+        diesel::update(self)
+            .set((
+                lists::deleted_at.eq(Some(Utc::now())),
+                lists::deleted_by.eq(Some(user.id)),
+            ))
             .execute(&conn)
             .map_err(|_| ServiceError::InternalServerError)
     }
+
+    /// Takes the list out of the trash, it stays archived
+    pub fn recover(&self, pool: &Data<DbPool>) -> Result<Self, ServiceError> {
+        let conn = get_conn(pool)?;
+
+        diesel::update(self)
+            .set((
+                lists::deleted_at.eq(None::<DateTime<Utc>>),
+                lists::deleted_by.eq(None::<Uuid>),
+            ))
+            .get_result::<Self>(&conn)
+            .map_err(|_| ServiceError::InternalServerError)
+    }
+
+    /// Permanently deletes the list with all its cards, only archived lists can be deleted
+    pub fn delete(&self, pool: &Data<DbPool>) -> Result<Removed, ServiceError> {
+        if !self.is_archived() {
+            return Err(ServiceError::NotArchived);
+        }
+
+        let conn = get_conn(pool)?;
+
+        conn.transaction::<_, ServiceError, _>(|| {
+            List::lock(&conn, self.id)?;
+            Ok(Removed::lists(&conn, &[self.id])?)
+        })
+    }
 }
 
 impl ListUpdate {
     /// Returns true if all update fields are None
     pub fn is_empty(&self) -> bool {
-        self.name == None
+        self.name == None && self.wip_limit.is_none() && self.wip_soft.is_none()
     }
 }
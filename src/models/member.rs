@@ -0,0 +1,120 @@
+use diesel::{
+    deserialize::{self, FromSql},
+    pg::Pg,
+    serialize::{self, Output, ToSql},
+    sql_types::Text,
+};
+use std::io::Write;
+
+use super::prelude::*;
+use crate::{
+    models::{Assignment, Board, ChecklistItem},
+    schema::board_members,
+};
+
+/// Access level of a board member, ordered from the least to the most privileged
+#[derive(
+    Debug,
+    Clone,
+    Copy,
+    PartialEq,
+    Eq,
+    PartialOrd,
+    Ord,
+    AsExpression,
+    FromSqlRow,
+    Serialize,
+    Deserialize,
+)]
+#[sql_type = "Text"]
+#[serde(rename_all = "lowercase")]
+pub enum Role {
+    Viewer,
+    Editor,
+    Admin,
+}
+
+impl ToSql<Text, Pg> for Role {
+    fn to_sql<W: Write>(&self, out: &mut Output<W, Pg>) -> serialize::Result {
+        let role = match self {
+            Role::Viewer => "viewer",
+            Role::Editor => "editor",
+            Role::Admin => "admin",
+        };
+
+        ToSql::<Text, Pg>::to_sql(role, out)
+    }
+}
+
+impl FromSql<Text, Pg> for Role {
+    fn from_sql(bytes: Option<&[u8]>) -> deserialize::Result<Self> {
+        match not_none!(bytes) {
+            b"viewer" => Ok(Role::Viewer),
+            b"editor" => Ok(Role::Editor),
+            b"admin" => Ok(Role::Admin),
+            _ => Err("Unrecognized role".into()),
+        }
+    }
+}
+
+#[derive(Debug, Identifiable, Queryable, Insertable, Associations, Serialize, Deserialize)]
+#[belongs_to(Board, foreign_key = "board")]
+#[primary_key(board, member)]
+#[table_name = "board_members"]
+pub struct Member {
+    #[serde(skip_deserializing)]
+    pub board: Uuid,
+    #[serde(skip_deserializing)]
+    pub member: Uuid,
+    pub role: Role,
+}
+
+impl Member {
+    pub fn new(board: &Board, member: Uuid, role: Role) -> Self {
+        Member {
+            board: board.id,
+            member,
+            role,
+        }
+    }
+
+    /// Inserts the membership or changes the role of an existing one
+    pub fn save(&self, pool: &Data<DbPool>) -> Result<usize, ServiceError> {
+        let conn = get_conn(pool)?;
+
+        diesel::insert_into(board_members::table)
+            .values(self)
+            .on_conflict((board_members::board, board_members::member))
+            .do_update()
+            .set(board_members::role.eq(self.role))
+            .execute(&conn)
+            .map_err(|_| ServiceError::InternalServerError)
+    }
+
+    pub fn find(
+        pool: &Data<DbPool>,
+        board_id: Uuid,
+        member_id: Uuid,
+    ) -> Result<Option<Self>, ServiceError> {
+        let conn = get_conn(pool)?;
+
+        board_members::table
+            .find((board_id, member_id))
+            .first::<Self>(&conn)
+            .optional()
+            .map_err(|_| ServiceError::InternalServerError)
+    }
+
+    /// Removes the membership and unassigns the user from the cards of the board
+    pub fn delete(&self, pool: &Data<DbPool>) -> Result<usize, ServiceError> {
+        let conn = get_conn(pool)?;
+
+        conn.transaction::<_, ServiceError, _>(|| {
+            let deleted = diesel::delete(self).execute(&conn)?;
+            Assignment::prune(&conn, self.board)?;
+            ChecklistItem::prune(&conn, self.board)?;
+
+            Ok(deleted)
+        })
+    }
+}
@@ -1,12 +1,55 @@
+mod api_key;
+mod assignee;
+mod attachment;
+mod audit;
 mod board;
 mod card;
+mod checklist;
+mod clone;
+mod comment;
+mod custom_field;
+mod group;
+mod label;
 mod list;
+mod member;
+mod notification;
+mod reminder;
+mod removed;
+mod share;
+mod template;
+mod trash;
 mod user;
 
-pub use board::{Board, BoardUpdate};
-pub use card::{Card, CardUpdate};
-pub use list::{List, ListUpdate};
-pub use user::{User, UserUpdate};
+pub use api_key::ApiKey;
+pub use assignee::{Assignee, Assignment};
+pub use attachment::{Attachment, AttachmentResponse};
+pub use audit::AuditEntry;
+pub use board::{Board, BoardResponse, BoardUpdate};
+pub use card::{Card, CardResponse, CardUpdate, Estimate, Priority};
+pub use checklist::{Checklist, ChecklistItem, ChecklistItemUpdate, ChecklistResponse};
+pub use clone::{CloneOptions, Cloning};
+pub use comment::{Comment, CommentResponse};
+pub use custom_field::{CustomField, CustomFieldUpdate, FieldKind, FieldValue};
+pub use group::{Group, GroupMember, GroupUpdate, MemberChange};
+pub use label::{Label, LabelUpdate};
+pub use list::{List, ListResponse, ListUpdate};
+pub use member::{Member, Role};
+pub use notification::Notification;
+pub use reminder::Reminder;
+pub use removed::Removed;
+pub use share::Share;
+pub use template::Template;
+pub use trash::{TrashItem, TrashKind};
+pub use user::{User, UserProfile, UserUpdate};
+
+/// Tells an explicit `null` apart from a missing field in updates
+fn nullable<'de, D, T>(deserializer: D) -> Result<Option<Option<T>>, D::Error>
+where
+    D: serde::Deserializer<'de>,
+    T: serde::Deserialize<'de>,
+{
+    serde::Deserialize::deserialize(deserializer).map(Some)
+}
 
 /// Global uses that are neccessary in *almost every* model definition
 mod prelude {
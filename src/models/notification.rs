@@ -0,0 +1,100 @@
+use chrono::{DateTime, Utc};
+use diesel::{result::Error as DieselError, PgConnection};
+
+use super::prelude::*;
+use crate::{
+    models::{Card, User},
+    schema::notifications,
+};
+
+#[derive(Debug, Identifiable, Queryable, Insertable, Associations, Serialize)]
+#[belongs_to(User, foreign_key = "account")]
+#[table_name = "notifications"]
+pub struct Notification {
+    pub id: Uuid,
+    pub account: Uuid,
+    pub card: Uuid,
+    pub message: String,
+    pub created_at: DateTime<Utc>,
+    pub read_at: Option<DateTime<Utc>>,
+}
+
+impl Notification {
+    pub fn new(account: Uuid, card: Uuid, message: String) -> Self {
+        Notification {
+            id: Uuid::new_v4(),
+            account,
+            card,
+            message,
+            created_at: Utc::now(),
+            read_at: None,
+        }
+    }
+
+    /// Notification for a card that is about to be due
+    pub fn due(account: Uuid, card: &Card) -> Self {
+        let message = match card.due_at {
+            Some(due_at) if due_at <= Utc::now() => format!("\"{}\" is overdue", card.title),
+            Some(due_at) => format!("\"{}\" is due at {}", card.title, due_at.to_rfc3339()),
+            None => format!("\"{}\" is due", card.title),
+        };
+
+        Notification::new(account, card.id, message)
+    }
+
+    pub(crate) fn save_all(
+        conn: &PgConnection,
+        notifications: &[Self],
+    ) -> Result<usize, DieselError> {
+        diesel::insert_into(notifications::table)
+            .values(notifications)
+            .execute(conn)
+    }
+
+    pub fn find(pool: &Data<DbPool>, id: Uuid) -> Result<Option<Self>, ServiceError> {
+        let conn = get_conn(pool)?;
+
+        notifications::table
+            .find(id)
+            .first::<Self>(&conn)
+            .optional()
+            .map_err(|_| ServiceError::InternalServerError)
+    }
+
+    /// Returns the notifications of `user`, newest first
+    pub fn for_user(
+        pool: &Data<DbPool>,
+        user: &User,
+        unread_only: bool,
+    ) -> Result<Vec<Self>, ServiceError> {
+        let conn = get_conn(pool)?;
+
+        let mut query = Notification::belonging_to(user)
+            .order(notifications::created_at.desc())
+            .into_boxed();
+        if unread_only {
+            query = query.filter(notifications::read_at.is_null());
+        }
+
+        query
+            .load::<Self>(&conn)
+            .map_err(|_| ServiceError::InternalServerError)
+    }
+
+    pub fn mark_read(&self, pool: &Data<DbPool>) -> Result<Self, ServiceError> {
+        let conn = get_conn(pool)?;
+
+        diesel::update(self)
+            .set(notifications::read_at.eq(self.read_at.unwrap_or_else(Utc::now)))
+            .get_result::<Self>(&conn)
+            .map_err(|_| ServiceError::InternalServerError)
+    }
+
+    pub fn delete(&self, pool: &Data<DbPool>) -> Result<usize, ServiceError> {
+        let conn = get_conn(pool)?;
+
+        diesel::delete(self)
+            .execute(&conn)
+            .map_err(|_| ServiceError::InternalServerError)
+    }
+}
@@ -0,0 +1,118 @@
+use chrono::{DateTime, Utc};
+use diesel::{dsl::sql, sql_types::Bool};
+
+use super::prelude::*;
+use crate::{
+    models::{Card, Notification, User},
+    schema::{boards, cards, lists, reminders},
+};
+
+/// Asks for a notification `minutes_before` the card is due
+#[derive(Debug, Identifiable, Queryable, Insertable, Associations, Serialize, Deserialize)]
+#[belongs_to(Card, foreign_key = "card")]
+#[belongs_to(User, foreign_key = "account")]
+#[primary_key(card, account)]
+#[table_name = "reminders"]
+pub struct Reminder {
+    #[serde(skip_deserializing)]
+    pub card: Uuid,
+    #[serde(skip_deserializing)]
+    pub account: Uuid,
+    pub minutes_before: i32,
+    #[serde(skip_deserializing)]
+    pub sent_at: Option<DateTime<Utc>>,
+}
+
+impl Reminder {
+    pub fn new(card: &Card, account: &User, minutes_before: i32) -> Self {
+        Reminder {
+            card: card.id,
+            account: account.id,
+            minutes_before,
+            sent_at: None,
+        }
+    }
+
+    /// Inserts the reminder or replaces the interval of an existing one, which rearms it
+    pub fn save(&self, pool: &Data<DbPool>) -> Result<usize, ServiceError> {
+        let conn = get_conn(pool)?;
+
+        diesel::insert_into(reminders::table)
+            .values(self)
+            .on_conflict((reminders::card, reminders::account))
+            .do_update()
+            .set((
+                reminders::minutes_before.eq(self.minutes_before),
+                reminders::sent_at.eq(None::<DateTime<Utc>>),
+            ))
+            .execute(&conn)
+            .map_err(|_| ServiceError::InternalServerError)
+    }
+
+    pub fn find(
+        pool: &Data<DbPool>,
+        card_id: Uuid,
+        account_id: Uuid,
+    ) -> Result<Option<Self>, ServiceError> {
+        let conn = get_conn(pool)?;
+
+        reminders::table
+            .find((card_id, account_id))
+            .first::<Self>(&conn)
+            .optional()
+            .map_err(|_| ServiceError::InternalServerError)
+    }
+
+    pub fn delete(&self, pool: &Data<DbPool>) -> Result<usize, ServiceError> {
+        let conn = get_conn(pool)?;
+
+        diesel::delete(self)
+            .execute(&conn)
+            .map_err(|_| ServiceError::InternalServerError)
+    }
+
+    /// Creates notifications for all reminders that are due and marks them as sent.
+    /// Reminders of completed or archived cards, including cards in archived lists and boards,
+    /// don't fire. Returns the number of notifications.
+    pub fn fire_due(pool: &Data<DbPool>) -> Result<usize, ServiceError> {
+        let conn = get_conn(pool)?;
+
+        conn.transaction::<_, ServiceError, _>(|| {
+            // Skipping locked rows lets several instances run the job concurrently
+            let due = reminders::table
+                .inner_join(cards::table)
+                .filter(reminders::sent_at.is_null())
+                .filter(cards::completed.eq(false))
+                .filter(cards::archived_at.is_null())
+                .filter(
+                    cards::list.eq_any(
+                        lists::table
+                            .inner_join(boards::table)
+                            .filter(lists::archived_at.is_null())
+                            .filter(boards::archived_at.is_null())
+                            .select(lists::id),
+                    ),
+                )
+                .filter(sql::<Bool>(
+                    "cards.due_at - reminders.minutes_before * INTERVAL '1 minute' <= NOW()",
+                ))
+                .for_update()
+                .skip_locked()
+                .load::<(Reminder, Card)>(&conn)?;
+
+            let notifications = due
+                .iter()
+                .map(|(reminder, card)| Notification::due(reminder.account, card))
+                .collect::<Vec<_>>();
+            Notification::save_all(&conn, &notifications)?;
+
+            for (reminder, _) in &due {
+                diesel::update(reminder)
+                    .set(reminders::sent_at.eq(Utc::now()))
+                    .execute(&conn)?;
+            }
+
+            Ok(notifications.len())
+        })
+    }
+}
@@ -0,0 +1,86 @@
+use diesel::{result::Error as DieselError, PgConnection};
+
+use super::prelude::*;
+use crate::{
+    models::Attachment,
+    schema::{attachments, board_members, boards, cards, checklists, comments, lists, shares},
+};
+
+/// Everything removed by deleting boards, lists or cards, including the deleted items
+#[derive(Debug, Default, Serialize)]
+pub struct Removed {
+    pub boards: usize,
+    pub lists: usize,
+    pub cards: usize,
+    pub checklists: usize,
+    pub comments: usize,
+    pub attachments: usize,
+    /// Keys of the attachment blobs, which are left for the caller to remove once the
+    /// transaction is committed
+    #[serde(skip)]
+    pub blobs: Vec<String>,
+}
+
+impl Removed {
+    /// Deletes the cards with their checklists, comments and attachments
+    pub(crate) fn cards(conn: &PgConnection, ids: &[Uuid]) -> Result<Self, DieselError> {
+        let attachments = diesel::delete(attachments::table.filter(attachments::card.eq_any(ids)))
+            .get_results::<Attachment>(conn)?;
+        let checklists =
+            diesel::delete(checklists::table.filter(checklists::card.eq_any(ids))).execute(conn)?;
+        let comments =
+            diesel::delete(comments::table.filter(comments::card.eq_any(ids))).execute(conn)?;
+        let cards = diesel::delete(cards::table.filter(cards::id.eq_any(ids))).execute(conn)?;
+
+        Ok(Removed {
+            cards,
+            checklists,
+            comments,
+            attachments: attachments.len(),
+            blobs: attachments.iter().flat_map(Attachment::blob_keys).collect(),
+            ..Default::default()
+        })
+    }
+
+    /// Deletes the lists with all their cards
+    pub(crate) fn lists(conn: &PgConnection, ids: &[Uuid]) -> Result<Self, DieselError> {
+        let cards = cards::table
+            .filter(cards::list.eq_any(ids))
+            .select(cards::id)
+            .load::<Uuid>(conn)?;
+
+        let mut removed = Removed::cards(conn, &cards)?;
+        removed.lists = diesel::delete(lists::table.filter(lists::id.eq_any(ids))).execute(conn)?;
+
+        Ok(removed)
+    }
+
+    /// Deletes the boards with all their lists, members and share links. Labels and custom
+    /// fields go with the board.
+    pub(crate) fn boards(conn: &PgConnection, ids: &[Uuid]) -> Result<Self, DieselError> {
+        let lists = lists::table
+            .filter(lists::board.eq_any(ids))
+            .select(lists::id)
+            .load::<Uuid>(conn)?;
+
+        let mut removed = Removed::lists(conn, &lists)?;
+        diesel::delete(board_members::table.filter(board_members::board.eq_any(ids)))
+            .execute(conn)?;
+        diesel::delete(shares::table.filter(shares::board.eq_any(ids))).execute(conn)?;
+        removed.boards =
+            diesel::delete(boards::table.filter(boards::id.eq_any(ids))).execute(conn)?;
+
+        Ok(removed)
+    }
+
+    /// Adds up the removals of several deletes
+    pub(crate) fn add(&mut self, other: Removed) {
+        self.boards += other.boards;
+        self.lists += other.lists;
+        self.cards += other.cards;
+        self.checklists += other.checklists;
+        self.comments += other.comments;
+        self.attachments += other.attachments;
+        self.blobs.extend(other.blobs);
+    }
+}
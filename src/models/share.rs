@@ -0,0 +1,131 @@
+use chrono::{DateTime, Utc};
+use serde_json::Value;
+
+use super::prelude::*;
+use crate::{models::Board, schema::shares};
+
+/// Fields that can be stripped from the shared view of a board. `description` is the
+/// description of the board, `content` the title and description of cards. This only
+/// trims the view, the regular endpoints still serve these fields without login.
+pub const HIDEABLE_FIELDS: &[&str] = &[
+    "description",
+    "content",
+    "labels",
+    "assignees",
+    "fields",
+    "attachments",
+];
+
+#[derive(Debug, Identifiable, Queryable, Insertable, Associations, Serialize, Deserialize)]
+#[belongs_to(Board, foreign_key = "board")]
+#[table_name = "shares"]
+pub struct Share {
+    #[serde(skip_deserializing)]
+    pub id: Uuid,
+    #[serde(skip_deserializing)]
+    pub board: Uuid,
+    #[serde(skip_deserializing)]
+    pub token: String,
+    #[serde(skip_deserializing, default = "Utc::now")]
+    pub created_at: DateTime<Utc>,
+    pub expires_at: Option<DateTime<Utc>>,
+    #[serde(default)]
+    pub hidden_fields: Vec<String>,
+}
+
+impl Share {
+    pub fn new(
+        board: &Board,
+        expires_at: Option<DateTime<Utc>>,
+        hidden_fields: Vec<String>,
+    ) -> Result<Self, ServiceError> {
+        if let Some(field) = hidden_fields
+            .iter()
+            .find(|f| !HIDEABLE_FIELDS.contains(&f.as_str()))
+        {
+            return Err(ServiceError::UnhideableField(field.clone()));
+        }
+
+        Ok(Share {
+            id: Uuid::new_v4(),
+            board: board.id,
+            token: Uuid::new_v4().to_simple().to_string(),
+            created_at: Utc::now(),
+            expires_at,
+            hidden_fields,
+        })
+    }
+
+    pub fn save(&self, pool: &Data<DbPool>) -> Result<usize, ServiceError> {
+        let conn = get_conn(pool)?;
+
+        diesel::insert_into(shares::table)
+            .values(self)
+            .execute(&conn)
+            .map_err(|_| ServiceError::InternalServerError)
+    }
+
+    pub fn find(pool: &Data<DbPool>, id: Uuid) -> Result<Option<Self>, ServiceError> {
+        let conn = get_conn(pool)?;
+
+        shares::table
+            .find(id)
+            .first::<Self>(&conn)
+            .optional()
+            .map_err(|_| ServiceError::InternalServerError)
+    }
+
+    /// Finds a share by its token, ignoring shares that have already expired
+    pub fn find_by_token(pool: &Data<DbPool>, token: &str) -> Result<Option<Self>, ServiceError> {
+        let conn = get_conn(pool)?;
+
+        shares::table
+            .filter(shares::token.eq(token))
+            .filter(
+                shares::expires_at
+                    .is_null()
+                    .or(shares::expires_at.gt(Utc::now())),
+            )
+            .first::<Self>(&conn)
+            .optional()
+            .map_err(|_| ServiceError::InternalServerError)
+    }
+
+    pub fn delete(&self, pool: &Data<DbPool>) -> Result<usize, ServiceError> {
+        let conn = get_conn(pool)?;
+
+        diesel::delete(self)
+            .execute(&conn)
+            .map_err(|_| ServiceError::InternalServerError)
+    }
+
+    /// Removes hidden fields, including their rendered HTML, from a serialized board or list
+    pub fn redact(&self, value: &mut Value) {
+        if let Value::Object(map) = value {
+            for field in &self.hidden_fields {
+                map.remove(field);
+                map.remove(&format!("{}_html", field));
+            }
+        }
+    }
+
+    /// Removes hidden fields from a serialized card, whose title and description are
+    /// hidden as its `content`
+    pub fn redact_card(&self, value: &mut Value) {
+        if let Value::Object(map) = value {
+            for field in &self.hidden_fields {
+                let fields = match field.as_str() {
+                    "content" => vec!["title", "description"],
+                    // Hides the description of the board only
+                    "description" => vec![],
+                    field => vec![field],
+                };
+
+                for field in fields {
+                    map.remove(field);
+                    map.remove(&format!("{}_html", field));
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,367 @@
+use chrono::{DateTime, Utc};
+use diesel::{result::Error as DieselError, PgConnection};
+use serde_json::Value;
+use std::collections::HashMap;
+
+use super::prelude::*;
+use crate::{
+    models::{
+        Board, Card, Checklist, ChecklistItem, CustomField, FieldKind, Label, List, Priority, User,
+    },
+    rank,
+    schema::{
+        boards, card_labels, cards, checklist_items, checklists, custom_fields, labels, lists,
+        templates,
+    },
+};
+
+/// Layout of a board that new boards can be created from
+#[derive(Debug, Identifiable, Queryable, Insertable, Serialize)]
+#[table_name = "templates"]
+pub struct Template {
+    pub id: Uuid,
+    pub owner: Uuid,
+    pub name: String,
+    pub description: Option<String>,
+    /// Public templates can be used by everyone, others only by their owner
+    pub public: bool,
+    /// A serialized [`Layout`]
+    pub content: Value,
+    pub created_at: DateTime<Utc>,
+}
+
+/// Snapshot of the structure of a board
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Layout {
+    pub labels: Vec<LabelLayout>,
+    pub fields: Vec<FieldLayout>,
+    pub lists: Vec<ListLayout>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct LabelLayout {
+    pub name: String,
+    pub color: String,
+    pub description: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct FieldLayout {
+    pub name: String,
+    pub kind: FieldKind,
+    pub options: Vec<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ListLayout {
+    pub name: String,
+    pub wip_limit: Option<i32>,
+    pub wip_soft: bool,
+    /// Empty unless the template was saved with cards
+    pub cards: Vec<CardLayout>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct CardLayout {
+    pub title: String,
+    pub description: Option<String>,
+    pub priority: Option<Priority>,
+    pub estimate: Option<f64>,
+    /// Names of the labels of the card
+    pub labels: Vec<String>,
+    pub checklists: Vec<ChecklistLayout>,
+}
+
+/// Checklist with the contents of its items, which start out unchecked
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ChecklistLayout {
+    pub name: String,
+    pub items: Vec<String>,
+}
+
+impl Layout {
+    /// Takes a snapshot of the unarchived lists of `board`, including their unarchived
+    /// cards if `with_cards` is set
+    fn of_board(conn: &PgConnection, board: &Board, with_cards: bool) -> Result<Self, DieselError> {
+        let labels = Label::belonging_to(board)
+            .order(labels::name)
+            .load::<Label>(conn)?
+            .into_iter()
+            .map(|label| LabelLayout {
+                name: label.name,
+                color: label.color,
+                description: label.description,
+            })
+            .collect();
+
+        let fields = CustomField::belonging_to(board)
+            .order(custom_fields::name)
+            .load::<CustomField>(conn)?
+            .into_iter()
+            .map(|field| FieldLayout {
+                name: field.name,
+                kind: field.kind,
+                options: field.options,
+            })
+            .collect();
+
+        let lists = List::belonging_to(board)
+            .filter(lists::archived_at.is_null())
+            .filter(lists::deleted_at.is_null())
+            .order((lists::position, lists::id))
+            .load::<List>(conn)?;
+
+        let cards = if with_cards {
+            Card::belonging_to(&lists)
+                .filter(cards::archived_at.is_null())
+                .filter(cards::deleted_at.is_null())
+                .order((cards::position, cards::id))
+                .load::<Card>(conn)?
+                .grouped_by(&lists)
+        } else {
+            lists.iter().map(|_| Vec::new()).collect()
+        };
+
+        let ids = cards
+            .iter()
+            .flatten()
+            .map(|card| card.id)
+            .collect::<Vec<_>>();
+        let mut card_labels = HashMap::<Uuid, Vec<String>>::new();
+        for (card, label) in Label::for_cards(conn, &ids)? {
+            card_labels.entry(card).or_default().push(label.name);
+        }
+
+        let checklists = checklists::table
+            .filter(checklists::card.eq_any(&ids))
+            .order((checklists::position, checklists::id))
+            .load::<Checklist>(conn)?;
+        let items = ChecklistItem::belonging_to(&checklists)
+            .order((checklist_items::position, checklist_items::id))
+            .load::<ChecklistItem>(conn)?
+            .grouped_by(&checklists);
+        let mut card_checklists = HashMap::<Uuid, Vec<ChecklistLayout>>::new();
+        for (checklist, items) in checklists.into_iter().zip(items) {
+            card_checklists
+                .entry(checklist.card)
+                .or_default()
+                .push(ChecklistLayout {
+                    name: checklist.name,
+                    items: items.into_iter().map(|item| item.content).collect(),
+                });
+        }
+
+        let lists = lists
+            .into_iter()
+            .zip(cards)
+            .map(|(list, cards)| ListLayout {
+                name: list.name,
+                wip_limit: list.wip_limit,
+                wip_soft: list.wip_soft,
+                cards: cards
+                    .into_iter()
+                    .map(|card| CardLayout {
+                        labels: card_labels.remove(&card.id).unwrap_or_default(),
+                        checklists: card_checklists.remove(&card.id).unwrap_or_default(),
+                        title: card.title,
+                        description: card.description,
+                        priority: card.priority,
+                        estimate: card.estimate,
+                    })
+                    .collect(),
+            })
+            .collect();
+
+        Ok(Layout {
+            labels,
+            fields,
+            lists,
+        })
+    }
+
+    /// Fills the empty `board` with the layout
+    fn apply(self, conn: &PgConnection, board: Uuid) -> Result<(), DieselError> {
+        let mut label_ids = HashMap::new();
+        for label in self.labels {
+            let id = Uuid::new_v4();
+            diesel::insert_into(labels::table)
+                .values(&Label {
+                    id,
+                    board,
+                    name: label.name.clone(),
+                    color: label.color,
+                    description: label.description,
+                })
+                .execute(conn)?;
+            label_ids.insert(label.name, id);
+        }
+
+        let fields = self
+            .fields
+            .into_iter()
+            .map(|field| CustomField {
+                id: Uuid::new_v4(),
+                board,
+                name: field.name,
+                kind: field.kind,
+                options: field.options,
+            })
+            .collect::<Vec<_>>();
+        diesel::insert_into(custom_fields::table)
+            .values(&fields)
+            .execute(conn)?;
+
+        let positions = rank::spread(self.lists.len());
+        for (layout, position) in self.lists.into_iter().zip(positions) {
+            let list = List {
+                id: Uuid::new_v4(),
+                board,
+                name: layout.name,
+                position,
+                wip_limit: layout.wip_limit,
+                wip_soft: layout.wip_soft,
+                archived_at: None,
+                deleted_at: None,
+                deleted_by: None,
+            };
+            diesel::insert_into(lists::table)
+                .values(&list)
+                .execute(conn)?;
+
+            let positions = rank::spread(layout.cards.len());
+            for (layout, position) in layout.cards.into_iter().zip(positions) {
+                let mut card = Card::new(&list, layout.title, layout.description);
+                card.position = position;
+                card.priority = layout.priority;
+                card.estimate = layout.estimate;
+                diesel::insert_into(cards::table)
+                    .values(&card)
+                    .execute(conn)?;
+
+                let links = layout
+                    .labels
+                    .iter()
+                    .filter_map(|name| label_ids.get(name))
+                    .map(|&label| (card_labels::card.eq(card.id), card_labels::label.eq(label)))
+                    .collect::<Vec<_>>();
+                diesel::insert_into(card_labels::table)
+                    .values(&links)
+                    .execute(conn)?;
+
+                let positions = rank::spread(layout.checklists.len());
+                for (layout, position) in layout.checklists.into_iter().zip(positions) {
+                    let mut checklist = Checklist::new(&card, layout.name);
+                    checklist.position = position;
+                    diesel::insert_into(checklists::table)
+                        .values(&checklist)
+                        .execute(conn)?;
+
+                    let positions = rank::spread(layout.items.len());
+                    let items = layout
+                        .items
+                        .into_iter()
+                        .zip(positions)
+                        .map(|(content, position)| ChecklistItem {
+                            position,
+                            ..ChecklistItem::new(&checklist, content, None, None)
+                        })
+                        .collect::<Vec<_>>();
+                    diesel::insert_into(checklist_items::table)
+                        .values(&items)
+                        .execute(conn)?;
+                }
+            }
+        }
+
+        Ok(())
+    }
+}
+
+impl Template {
+    /// Saves the layout of `board` as a template of `owner`, see [`Layout`]
+    pub fn from_board(
+        pool: &Data<DbPool>,
+        board: &Board,
+        owner: &User,
+        name: String,
+        description: Option<String>,
+        public: bool,
+        with_cards: bool,
+    ) -> Result<Self, ServiceError> {
+        let conn = get_conn(pool)?;
+
+        let layout = Layout::of_board(&conn, board, with_cards)?;
+        let content =
+            serde_json::to_value(layout).map_err(|_| ServiceError::InternalServerError)?;
+
+        diesel::insert_into(templates::table)
+            .values(&Template {
+                id: Uuid::new_v4(),
+                owner: owner.id,
+                name,
+                description,
+                public,
+                content,
+                created_at: Utc::now(),
+            })
+            .get_result::<Self>(&conn)
+            .map_err(|_| ServiceError::InternalServerError)
+    }
+
+    pub fn find(pool: &Data<DbPool>, id: Uuid) -> Result<Option<Self>, ServiceError> {
+        let conn = get_conn(pool)?;
+
+        templates::table
+            .find(id)
+            .first::<Self>(&conn)
+            .optional()
+            .map_err(|_| ServiceError::InternalServerError)
+    }
+
+    /// Returns the public templates and those of `user`, ordered by name
+    pub fn usable_by(pool: &Data<DbPool>, user: &User) -> Result<Vec<Self>, ServiceError> {
+        let conn = get_conn(pool)?;
+
+        templates::table
+            .filter(templates::public.or(templates::owner.eq(user.id)))
+            .order((templates::name, templates::id))
+            .load::<Self>(&conn)
+            .map_err(|_| ServiceError::InternalServerError)
+    }
+
+    pub fn is_usable_by(&self, user: &User) -> bool {
+        self.public || self.owner == user.id
+    }
+
+    /// Creates a board of `owner` with the layout of the template
+    pub fn instantiate(
+        &self,
+        pool: &Data<DbPool>,
+        owner: &User,
+        name: String,
+        description: Option<String>,
+    ) -> Result<Board, ServiceError> {
+        let layout = serde_json::from_value::<Layout>(self.content.clone())
+            .map_err(|_| ServiceError::InternalServerError)?;
+        let board = Board::new(owner, name, description);
+
+        let conn = get_conn(pool)?;
+
+        conn.transaction::<_, ServiceError, _>(|| {
+            diesel::insert_into(boards::table)
+                .values(&board)
+                .execute(&conn)?;
+            layout.apply(&conn, board.id)?;
+
+            Ok(board)
+        })
+    }
+
+    pub fn delete(&self, pool: &Data<DbPool>) -> Result<usize, ServiceError> {
+        let conn = get_conn(pool)?;
+
+        diesel::delete(self)
+            .execute(&conn)
+            .map_err(|_| ServiceError::InternalServerError)
+    }
+}
@@ -0,0 +1,108 @@
+use chrono::{DateTime, Utc};
+use std::cmp::Reverse;
+
+use super::prelude::*;
+use crate::{
+    models::{Removed, User},
+    schema::{boards, cards, lists},
+    TrashConfig,
+};
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum TrashKind {
+    Board,
+    List,
+    Card,
+}
+
+/// Board, list or card in the trash of a user
+#[derive(Debug, Serialize)]
+pub struct TrashItem {
+    pub kind: TrashKind,
+    pub id: Uuid,
+    /// Board of a list or card, the board itself for boards
+    pub board: Uuid,
+    /// Name of a board or list, title of a card
+    pub name: String,
+    pub deleted_at: DateTime<Utc>,
+    pub purge_at: DateTime<Utc>,
+}
+
+impl TrashItem {
+    /// Returns the items `user` deleted, most recently deleted first
+    pub fn for_user(
+        pool: &Data<DbPool>,
+        user: &User,
+        config: &TrashConfig,
+    ) -> Result<Vec<Self>, ServiceError> {
+        let conn = get_conn(pool)?;
+
+        let boards = boards::table
+            .filter(boards::deleted_by.eq(user.id))
+            .filter(boards::deleted_at.is_not_null())
+            .select((boards::id, boards::id, boards::name, boards::deleted_at))
+            .load::<(Uuid, Uuid, String, Option<DateTime<Utc>>)>(&conn)?;
+        let lists = lists::table
+            .filter(lists::deleted_by.eq(user.id))
+            .filter(lists::deleted_at.is_not_null())
+            .select((lists::id, lists::board, lists::name, lists::deleted_at))
+            .load::<(Uuid, Uuid, String, Option<DateTime<Utc>>)>(&conn)?;
+        let cards = cards::table
+            .inner_join(lists::table)
+            .filter(cards::deleted_by.eq(user.id))
+            .filter(cards::deleted_at.is_not_null())
+            .select((cards::id, lists::board, cards::title, cards::deleted_at))
+            .load::<(Uuid, Uuid, String, Option<DateTime<Utc>>)>(&conn)?;
+
+        let kinds = boards
+            .into_iter()
+            .map(|row| (TrashKind::Board, row))
+            .chain(lists.into_iter().map(|row| (TrashKind::List, row)))
+            .chain(cards.into_iter().map(|row| (TrashKind::Card, row)));
+
+        let mut items = kinds
+            .filter_map(|(kind, (id, board, name, deleted_at))| {
+                deleted_at.map(|deleted_at| TrashItem {
+                    kind,
+                    id,
+                    board,
+                    name,
+                    deleted_at,
+                    purge_at: config.purge_at(deleted_at),
+                })
+            })
+            .collect::<Vec<_>>();
+        items.sort_by_key(|item| Reverse(item.deleted_at));
+
+        Ok(items)
+    }
+
+    /// Permanently deletes everything that was put into the trash before `cutoff`, along
+    /// with the contents of deleted boards and lists
+    pub fn purge(pool: &Data<DbPool>, cutoff: DateTime<Utc>) -> Result<Removed, ServiceError> {
+        let conn = get_conn(pool)?;
+
+        conn.transaction::<_, ServiceError, _>(|| {
+            let cards = cards::table
+                .filter(cards::deleted_at.lt(cutoff))
+                .select(cards::id)
+                .load::<Uuid>(&conn)?;
+            let mut removed = Removed::cards(&conn, &cards)?;
+
+            let lists = lists::table
+                .filter(lists::deleted_at.lt(cutoff))
+                .select(lists::id)
+                .load::<Uuid>(&conn)?;
+            removed.add(Removed::lists(&conn, &lists)?);
+
+            let boards = boards::table
+                .filter(boards::deleted_at.lt(cutoff))
+                .select(boards::id)
+                .load::<Uuid>(&conn)?;
+            removed.add(Removed::boards(&conn, &boards)?);
+
+            Ok(removed)
+        })
+    }
+}
@@ -5,16 +5,30 @@ use std::{
 };
 
 use super::prelude::*;
-use crate::{schema::users, Claims};
+use crate::{models::ApiKey, schema::users, Claims};
 
 #[derive(Debug, Identifiable, Queryable, Insertable, Serialize, Deserialize)]
 #[table_name = "users"]
 pub struct User {
     #[serde(skip_deserializing)]
     pub id: Uuid,
-    pub mail: String,
+    pub mail: Option<String>,
     #[serde(skip_serializing)]
-    pub password: String,
+    pub password: Option<String>,
+    #[serde(skip_deserializing)]
+    pub admin: bool,
+    pub name: Option<String>,
+    #[serde(skip_deserializing)]
+    pub bot: bool,
+    /// User responsible for the bot, only set on bot accounts
+    #[serde(skip_deserializing)]
+    pub bot_owner: Option<Uuid>,
+    /// Deactivated accounts can't log in or use their existing credentials
+    #[serde(skip_deserializing)]
+    pub active: bool,
+    /// Id of the account in the identity provider that provisioned it
+    #[serde(skip_deserializing)]
+    pub external_id: Option<String>,
 }
 
 #[derive(Debug, AsChangeset, Deserialize)]
@@ -23,6 +37,18 @@ pub struct UserUpdate {
     #[serde(skip_deserializing)]
     pub id: Uuid,
     pub mail: Option<String>,
+    pub name: Option<String>,
+}
+
+/// Fields of a user managed by an identity provider
+#[derive(Debug, AsChangeset)]
+#[table_name = "users"]
+#[changeset_options(treat_none_as_null = "true")]
+pub struct UserProfile {
+    pub mail: String,
+    pub name: Option<String>,
+    pub active: bool,
+    pub external_id: Option<String>,
 }
 
 impl FromRequest for User {
@@ -31,17 +57,27 @@ impl FromRequest for User {
     type Future = Ready<Result<User, Self::Error>>;
 
     fn from_request(req: &HttpRequest, _pld: &mut Payload) -> Self::Future {
+        let pool = req.app_data::<Data<DbPool>>().unwrap();
+
+        // Bots can't log in, so they authenticate with API keys instead of access tokens
+        if let Some(key) = req.headers().get("x-api-key") {
+            return match key.to_str() {
+                Ok(key) => ready(ApiKey::authenticate(pool, key).and_then(User::ensure_active)),
+                Err(_) => ready(Err(ServiceError::InvalidToken)),
+            };
+        }
+
         let claims = match Claims::try_from(req) {
             Ok(c) => c,
             Err(e) => return ready(Err(e)),
         };
 
-        let pool = req.app_data::<Data<DbPool>>().unwrap();
-
+        // Impersonated requests are logged and checked by `AuditEntry::check_request`
         let user_id = Uuid::from_str(claims.sub.as_str()).unwrap();
+
         match User::find(&pool, user_id) {
             Ok(u) => match u {
-                Some(u) => ready(Ok(u)),
+                Some(u) => ready(u.ensure_active()),
                 // FIXME: user could be null if it was deleted after issuing a token
                 // TODO: Invalidate tokens that don't belong to any user
                 None => ready(Err(ServiceError::InternalServerError)),
@@ -52,11 +88,54 @@ impl FromRequest for User {
 }
 
 impl User {
-    pub fn new(mail: String, password: String) -> Self {
+    pub fn new(mail: String, password: String, name: Option<String>) -> Self {
+        User {
+            id: Uuid::new_v4(),
+            mail: Some(mail),
+            password: Some(password),
+            admin: false,
+            name,
+            bot: false,
+            bot_owner: None,
+            active: true,
+            external_id: None,
+        }
+    }
+
+    /// Creates a user managed by an external directory, who can't log in with a local password
+    pub fn new_external(mail: String, name: Option<String>) -> Self {
+        User {
+            id: Uuid::new_v4(),
+            mail: Some(mail),
+            password: None,
+            admin: false,
+            name,
+            bot: false,
+            bot_owner: None,
+            active: true,
+            external_id: None,
+        }
+    }
+
+    pub fn new_bot(owner: &User, name: String) -> Self {
         User {
             id: Uuid::new_v4(),
-            mail,
-            password,
+            mail: None,
+            password: None,
+            admin: false,
+            name: Some(name),
+            bot: true,
+            bot_owner: Some(owner.id),
+            active: true,
+            external_id: None,
+        }
+    }
+
+    pub(crate) fn ensure_active(self) -> Result<Self, ServiceError> {
+        if self.active {
+            Ok(self)
+        } else {
+            Err(ServiceError::InactiveAccount)
         }
     }
 
@@ -79,6 +158,29 @@ impl User {
             .map_err(|_| ServiceError::InternalServerError)
     }
 
+    pub fn find_by_mail(pool: &Data<DbPool>, mail: &str) -> Result<Option<Self>, ServiceError> {
+        let conn = get_conn(pool)?;
+
+        users::table
+            .filter(users::mail.eq(mail))
+            .first::<Self>(&conn)
+            .optional()
+            .map_err(|_| ServiceError::InternalServerError)
+    }
+
+    pub fn find_by_external_id(
+        pool: &Data<DbPool>,
+        external_id: &str,
+    ) -> Result<Option<Self>, ServiceError> {
+        let conn = get_conn(pool)?;
+
+        users::table
+            .filter(users::external_id.eq(external_id))
+            .first::<Self>(&conn)
+            .optional()
+            .map_err(|_| ServiceError::InternalServerError)
+    }
+
     pub fn update(&self, pool: &Data<DbPool>, data: UserUpdate) -> Result<Self, ServiceError> {
         let conn = get_conn(pool)?;
 
@@ -88,6 +190,23 @@ impl User {
             .map_err(|_| ServiceError::InternalServerError)
     }
 
+    /// Updates the profile, bots of the user are (de)activated along with it
+    pub fn update_profile(
+        &self,
+        pool: &Data<DbPool>,
+        data: UserProfile,
+    ) -> Result<Self, ServiceError> {
+        let conn = get_conn(pool)?;
+
+        conn.transaction::<_, ServiceError, _>(|| {
+            diesel::update(users::table.filter(users::bot_owner.eq(self.id)))
+                .set(users::active.eq(data.active))
+                .execute(&conn)?;
+
+            Ok(diesel::update(self).set(&data).get_result::<Self>(&conn)?)
+        })
+    }
+
     pub fn delete(&self, pool: &Data<DbPool>) -> Result<usize, ServiceError> {
         let conn = get_conn(pool)?;
 
@@ -100,6 +219,6 @@ impl User {
 impl UserUpdate {
     /// Returns true if all update fields are None
     pub fn is_empty(&self) -> bool {
-        self.mail == None
+        self.mail == None && self.name == None
     }
 }
@@ -0,0 +1,390 @@
+//! Lexicographic ranks used to order lists and cards.
+//!
+//! Items are sorted by a string rank, so moving an item only requires computing a
+//! rank between its new neighbours instead of renumbering every sibling. Ranks grow
+//! longer when items are repeatedly inserted at the same spot, in which case all
+//! siblings get evenly spaced ranks again.
+
+use diesel::{result::Error as DieselError, PgConnection};
+use serde::Deserialize;
+use uuid::Uuid;
+
+use crate::errors::ServiceError;
+
+/// Digits of the ranks, in ascending byte order
+const DIGITS: &[u8] = b"0123456789ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz";
+
+/// Ranks longer than this trigger a rebalance of all siblings
+const MAX_LENGTH: usize = 32;
+
+/// Where to put an item relative to one of its siblings
+#[derive(Debug, Clone, Copy, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Placement {
+    Before(Uuid),
+    After(Uuid),
+}
+
+impl Placement {
+    /// Builds a placement from the optional neighbours of a move request, `None` means
+    /// after all siblings. Fails if both neighbours are given.
+    pub fn from_neighbours(
+        before: Option<Uuid>,
+        after: Option<Uuid>,
+    ) -> Result<Option<Self>, ServiceError> {
+        match (before, after) {
+            (Some(id), None) => Ok(Some(Placement::Before(id))),
+            (None, Some(id)) => Ok(Some(Placement::After(id))),
+            (None, None) => Ok(None),
+            (Some(_), Some(_)) => Err(ServiceError::InvalidPosition),
+        }
+    }
+
+    /// Returns the index the item should be inserted at, given siblings sorted by rank
+    /// and not containing the item itself. `None` if the neighbour isn't a sibling.
+    pub fn index(&self, siblings: &[(Uuid, String)]) -> Option<usize> {
+        match self {
+            Placement::Before(id) => siblings.iter().position(|(s, _)| s == id),
+            Placement::After(id) => siblings.iter().position(|(s, _)| s == id).map(|i| i + 1),
+        }
+    }
+}
+
+fn digit(rank: &[u8], i: usize) -> Option<usize> {
+    rank.get(i)
+        .map(|c| DIGITS.iter().position(|d| d == c).unwrap_or(0))
+}
+
+/// Returns a rank sorting strictly between `before` and `after`, where `None` means
+/// there is no neighbour on that side. Returns `None` if the siblings need to be
+/// rebalanced first, either because the ranks got too long or there is no room left.
+pub fn between(before: Option<&str>, after: Option<&str>) -> Option<String> {
+    let lower = before.unwrap_or_default().as_bytes();
+    let mut upper = after.map(str::as_bytes);
+
+    if let Some(upper) = upper {
+        if lower >= upper {
+            return None;
+        }
+    }
+
+    let mut rank = Vec::new();
+    for i in 0.. {
+        let lo = digit(lower, i).unwrap_or(0);
+        let hi = match upper {
+            Some(upper) => digit(upper, i).unwrap_or(0),
+            None => DIGITS.len(),
+        };
+
+        if lo == hi {
+            rank.push(DIGITS[lo]);
+            continue;
+        }
+
+        let mid = (lo + hi) / 2;
+        if mid > lo {
+            rank.push(DIGITS[mid]);
+            break;
+        }
+
+        // Adjacent digits, anything longer than `lower` with this prefix fits below `upper`
+        rank.push(DIGITS[lo]);
+        upper = None;
+
+        if rank.len() > MAX_LENGTH {
+            break;
+        }
+    }
+
+    if rank.len() > MAX_LENGTH {
+        return None;
+    }
+
+    String::from_utf8(rank).ok()
+}
+
+/// Returns the rank for an item inserted at `index` among sorted `siblings`
+pub fn at(siblings: &[(Uuid, String)], index: usize) -> Option<String> {
+    let before = index
+        .checked_sub(1)
+        .and_then(|i| siblings.get(i))
+        .map(|(_, rank)| rank.as_str());
+    let after = siblings.get(index).map(|(_, rank)| rank.as_str());
+
+    between(before, after)
+}
+
+/// Returns `count` evenly spaced, ascending ranks
+pub fn spread(count: usize) -> Vec<String> {
+    let base = DIGITS.len() as u128;
+
+    let mut width = 1;
+    while base.pow(width) <= (count as u128 + 1) * 2 {
+        width += 1;
+    }
+    let step = base.pow(width) / (count as u128 + 1);
+
+    (1..=count as u128)
+        .map(|i| {
+            let mut value = i * step;
+            let mut rank = vec![DIGITS[0]; width as usize];
+            for digit in rank.iter_mut().rev() {
+                *digit = DIGITS[(value % base) as usize];
+                value /= base;
+            }
+
+            // A trailing zero digit would leave no room directly before the rank
+            while rank.last() == Some(&DIGITS[0]) {
+                rank.pop();
+            }
+
+            String::from_utf8(rank).unwrap()
+        })
+        .collect()
+}
+
+/// Rows whose children are ordered by rank
+pub(crate) trait Parent {
+    /// Locks the row until the end of the transaction, serializing changes to the order
+    /// of its children
+    fn lock(conn: &PgConnection, id: Uuid) -> Result<Uuid, DieselError>;
+}
+
+/// Rows ordered by rank among the other children of their parent
+pub(crate) trait Ranked {
+    /// Returns ids and positions of the children of `parent` other than `item`, sorted
+    fn siblings(
+        conn: &PgConnection,
+        parent: Uuid,
+        item: Uuid,
+    ) -> Result<Vec<(Uuid, String)>, DieselError>;
+
+    fn set_position(conn: &PgConnection, id: Uuid, position: String) -> Result<(), DieselError>;
+
+    /// Returns the position for `item` placed among the other children of `parent`, or
+    /// after all of them when no placement is given. Positions of the other children are
+    /// rebalanced if there is no room left. Expects the parent to be locked.
+    fn position_in(
+        conn: &PgConnection,
+        parent: Uuid,
+        item: Uuid,
+        placement: Option<Placement>,
+    ) -> Result<String, ServiceError> {
+        let siblings = Self::siblings(conn, parent, item)?;
+
+        let index = match placement {
+            Some(placement) => placement
+                .index(&siblings)
+                .ok_or(ServiceError::InvalidPosition)?,
+            None => siblings.len(),
+        };
+
+        if let Some(position) = at(&siblings, index) {
+            return Ok(position);
+        }
+
+        // Spread out all siblings, leaving a gap at `index`
+        let mut positions = spread(siblings.len() + 1);
+        let position = positions.remove(index);
+        for ((id, _), sibling_position) in siblings.iter().zip(positions) {
+            Self::set_position(conn, *id, sibling_position)?;
+        }
+
+        Ok(position)
+    }
+}
+
+/// Implements [`Parent`] for the model of `table`
+macro_rules! parent {
+    ($model:ty, $table:ident) => {
+        impl $crate::rank::Parent for $model {
+            fn lock(
+                conn: &diesel::PgConnection,
+                id: uuid::Uuid,
+            ) -> Result<uuid::Uuid, diesel::result::Error> {
+                use diesel::prelude::*;
+
+                $table::table
+                    .find(id)
+                    .select($table::id)
+                    .for_update()
+                    .first::<uuid::Uuid>(conn)
+            }
+        }
+    };
+}
+
+/// Implements [`Ranked`] for the model of `table`, whose rows belong to the row referenced
+/// by the `parent` column
+macro_rules! ranked {
+    ($model:ty, $table:ident, $parent:ident) => {
+        impl $crate::rank::Ranked for $model {
+            fn siblings(
+                conn: &diesel::PgConnection,
+                parent: uuid::Uuid,
+                item: uuid::Uuid,
+            ) -> Result<Vec<(uuid::Uuid, String)>, diesel::result::Error> {
+                use diesel::prelude::*;
+
+                $table::table
+                    .filter($table::$parent.eq(parent))
+                    .filter($table::id.ne(item))
+                    .order(($table::position, $table::id))
+                    .select(($table::id, $table::position))
+                    .load::<(uuid::Uuid, String)>(conn)
+            }
+
+            fn set_position(
+                conn: &diesel::PgConnection,
+                id: uuid::Uuid,
+                position: String,
+            ) -> Result<(), diesel::result::Error> {
+                use diesel::prelude::*;
+
+                diesel::update($table::table.find(id))
+                    .set($table::position.eq(position))
+                    .execute(conn)
+                    .map(|_| ())
+            }
+        }
+    };
+}
+
+pub(crate) use {parent, ranked};
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Ranks must leave room before them, see [`spread`]
+    fn assert_valid(rank: &str) {
+        assert!(!rank.is_empty());
+        assert!(!rank.ends_with('0'), "{} ends in a zero digit", rank);
+    }
+
+    fn siblings(ranks: &[&str]) -> Vec<(Uuid, String)> {
+        ranks
+            .iter()
+            .map(|rank| (Uuid::new_v4(), rank.to_string()))
+            .collect()
+    }
+
+    #[test]
+    fn between_neighbours() {
+        for (before, after) in [("1", "3"), ("1", "2"), ("A", "a"), ("1V", "2"), ("y", "z")] {
+            let rank = between(Some(before), Some(after)).unwrap();
+            assert_valid(&rank);
+            assert!(before < rank.as_str() && rank.as_str() < after);
+        }
+    }
+
+    #[test]
+    fn between_unordered_neighbours() {
+        assert_eq!(between(Some("2"), Some("1")), None);
+        assert_eq!(between(Some("1"), Some("1")), None);
+    }
+
+    #[test]
+    fn between_at_the_ends() {
+        let first = between(None, None).unwrap();
+        assert_valid(&first);
+
+        let start = between(None, Some("1")).unwrap();
+        assert_valid(&start);
+        assert!(start.as_str() < "1");
+
+        let end = between(Some("z"), None).unwrap();
+        assert_valid(&end);
+        assert!(end.as_str() > "z");
+    }
+
+    #[test]
+    fn repeated_inserts_at_the_same_gap() {
+        let lower = "1".to_string();
+        let mut upper = "2".to_string();
+
+        let mut inserts = 0;
+        while let Some(rank) = between(Some(&lower), Some(&upper)) {
+            assert_valid(&rank);
+            assert!(lower < rank && rank < upper);
+            assert!(rank.len() <= MAX_LENGTH);
+
+            upper = rank;
+            inserts += 1;
+            assert!(inserts < 10_000, "ranks never ran out");
+        }
+
+        // Plenty of room before a rebalance is needed
+        assert!(inserts > 100);
+    }
+
+    #[test]
+    fn repeated_inserts_at_the_start_and_end() {
+        let mut first = "V".to_string();
+        let mut last = "V".to_string();
+
+        for _ in 0..100 {
+            let rank = between(None, Some(&first)).unwrap();
+            assert_valid(&rank);
+            assert!(rank < first);
+            first = rank;
+
+            let rank = between(Some(&last), None).unwrap();
+            assert_valid(&rank);
+            assert!(rank > last);
+            last = rank;
+        }
+    }
+
+    #[test]
+    fn at_index() {
+        let siblings = siblings(&["4", "8", "C"]);
+
+        let start = at(&siblings, 0).unwrap();
+        assert!(start.as_str() < "4");
+        let middle = at(&siblings, 2).unwrap();
+        assert!("8" < middle.as_str() && middle.as_str() < "C");
+        let end = at(&siblings, 3).unwrap();
+        assert!(end.as_str() > "C");
+
+        for rank in [start, middle, end] {
+            assert_valid(&rank);
+        }
+        assert_valid(&at(&[], 0).unwrap());
+    }
+
+    #[test]
+    fn placement_index() {
+        let siblings = siblings(&["4", "8"]);
+        let (first, second) = (siblings[0].0, siblings[1].0);
+
+        assert_eq!(Placement::Before(first).index(&siblings), Some(0));
+        assert_eq!(Placement::After(first).index(&siblings), Some(1));
+        assert_eq!(Placement::After(second).index(&siblings), Some(2));
+        assert_eq!(Placement::Before(Uuid::nil()).index(&siblings), None);
+    }
+
+    #[test]
+    fn spread_ranks() {
+        assert!(spread(0).is_empty());
+
+        for count in [1, 2, 61, 62, 1000, 5000] {
+            let ranks = spread(count);
+            assert_eq!(ranks.len(), count);
+
+            for rank in &ranks {
+                assert_valid(rank);
+            }
+            for pair in ranks.windows(2) {
+                assert!(pair[0] < pair[1]);
+            }
+
+            // Every gap, including both ends, takes another item
+            assert!(between(None, Some(&ranks[0])).is_some());
+            assert!(between(Some(&ranks[count - 1]), None).is_some());
+            for pair in ranks.windows(2) {
+                assert!(between(Some(&pair[0]), Some(&pair[1])).is_some());
+            }
+        }
+    }
+}
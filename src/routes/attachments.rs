@@ -0,0 +1,228 @@
+use std::slice;
+
+use actix_multipart::Multipart;
+use actix_web::{
+    delete, get, post,
+    web::{Bytes, BytesMut, Data, Path, ServiceConfig},
+    Error, HttpResponse,
+};
+use futures::StreamExt;
+use log::error;
+use uuid::Uuid;
+
+use super::cards::find_card;
+use crate::{
+    errors::ServiceError,
+    models::{Attachment, Board, Role, User},
+    storage::{copy_blob, remove_blobs, BlobStore},
+    thumbnails, AttachmentConfig, DbPool,
+};
+
+pub fn config(cfg: &mut ServiceConfig) {
+    cfg.service(new_attachment)
+        .service(get_attachments)
+        .service(get_attachment)
+        .service(delete_attachment);
+}
+
+/// An uploaded file read from a multipart form
+struct Upload {
+    name: String,
+    content_type: String,
+    data: Bytes,
+}
+
+/// Reads the `file` field of a multipart form, enforcing the configured limits while
+/// the file is streamed in
+async fn read_upload(mut payload: Multipart, config: &AttachmentConfig) -> Result<Upload, Error> {
+    while let Some(field) = payload.next().await {
+        let mut field = field?;
+
+        let disposition = match field.content_disposition() {
+            Some(disposition) if disposition.get_name() == Some("file") => disposition,
+            // Other fields are skipped
+            _ => continue,
+        };
+        let name = disposition.get_filename().unwrap_or("file").to_string();
+
+        let content_type = format!(
+            "{}/{}",
+            field.content_type().type_(),
+            field.content_type().subtype()
+        );
+        if !config.allows_type(&content_type) {
+            Err(ServiceError::UnsupportedFileType(content_type.clone()))?
+        }
+
+        let mut data = BytesMut::new();
+        while let Some(chunk) = field.next().await {
+            let chunk = chunk?;
+            if data.len() + chunk.len() > config.max_size {
+                Err(ServiceError::FileTooLarge(config.max_size))?
+            }
+            data.extend_from_slice(&chunk);
+        }
+
+        return Ok(Upload {
+            name,
+            content_type,
+            data: data.freeze(),
+        });
+    }
+
+    Err(HttpResponse::BadRequest().finish())?
+}
+
+/// Finds an attachment by its full path and checks that the user can see the board
+fn find_attachment(
+    pool: &Data<DbPool>,
+    user: &User,
+    (board_id, list_id, card_id, attachment_id): (Uuid, Uuid, Uuid, Uuid),
+) -> Result<(Board, Attachment), Error> {
+    let (board, card) = find_card(pool, board_id, list_id, card_id)?;
+
+    if !board.allows(pool, user, Role::Viewer)? {
+        Err(HttpResponse::Unauthorized().finish())?
+    }
+
+    match Attachment::find(pool, attachment_id)? {
+        Some(attachment) if attachment.card == card.id => Ok((board, attachment)),
+        _ => Err(HttpResponse::NotFound().finish())?,
+    }
+}
+
+/// Copies the blobs of cloned attachments. Clones whose blobs couldn't be copied are
+/// deleted again, so they don't point to missing files.
+pub(super) async fn copy_blobs(
+    pool: &Data<DbPool>,
+    store: &Data<dyn BlobStore>,
+    copies: Vec<(Attachment, Attachment)>,
+) {
+    for (original, copy) in copies {
+        let keys = copy.blob_keys();
+
+        let mut copied = Vec::new();
+        for (from, to) in original.blob_keys().iter().zip(&keys) {
+            // Only the file itself has the original content type, thumbnails are PNGs
+            let content_type = if copied.is_empty() {
+                original.content_type.as_str()
+            } else {
+                thumbnails::CONTENT_TYPE
+            };
+
+            if let Err(e) = copy_blob(store, from, to, content_type).await {
+                error!("Could not copy blob {} to {}: {}", from, to, e);
+                break;
+            }
+            copied.push(to.clone());
+        }
+
+        if copied.len() < keys.len() {
+            if let Err(e) = copy.delete(pool) {
+                error!("Could not delete attachment {}: {}", copy.id, e);
+            }
+            remove_blobs(store, &copied).await;
+        }
+    }
+}
+
+/// Uploads the `file` field of a multipart form, thumbnails of images are generated
+/// in the background
+#[post("")]
+async fn new_attachment(
+    pool: Data<DbPool>,
+    config: Data<AttachmentConfig>,
+    store: Data<dyn BlobStore>,
+    user: User,
+    Path((board_id, list_id, card_id)): Path<(Uuid, Uuid, Uuid)>,
+    payload: Multipart,
+) -> Result<HttpResponse, Error> {
+    let (board, card) = find_card(&pool, board_id, list_id, card_id)?;
+
+    if !board.allows(&pool, &user, Role::Editor)? {
+        Err(HttpResponse::Unauthorized().finish())?
+    }
+
+    let upload = read_upload(payload, &config).await?;
+
+    let attachment = Attachment::new(
+        &card,
+        &user,
+        upload.name,
+        upload.content_type,
+        upload.data.len(),
+    );
+    store
+        .put(
+            &attachment.storage_key,
+            &attachment.content_type,
+            upload.data.clone(),
+        )
+        .await?;
+
+    if let Err(e) = attachment.save(&pool) {
+        remove_blobs(&store, slice::from_ref(&attachment.storage_key)).await;
+        Err(e)?
+    }
+
+    if thumbnails::supports(&attachment.content_type) {
+        thumbnails::spawn(pool.clone(), store.clone(), &attachment, upload.data);
+    }
+
+    Ok(HttpResponse::Created()
+        .header("Location", format!("/{}", attachment.id))
+        .json(attachment.respond(&config)))
+}
+
+/// Download URLs are only handed out to users who can see the board
+#[get("")]
+async fn get_attachments(
+    pool: Data<DbPool>,
+    config: Data<AttachmentConfig>,
+    user: User,
+    Path((board_id, list_id, card_id)): Path<(Uuid, Uuid, Uuid)>,
+) -> Result<HttpResponse, Error> {
+    let (board, card) = find_card(&pool, board_id, list_id, card_id)?;
+
+    if !board.allows(&pool, &user, Role::Viewer)? {
+        Err(HttpResponse::Unauthorized().finish())?
+    }
+
+    let attachments = Attachment::for_card(&pool, &card)?
+        .into_iter()
+        .map(|attachment| attachment.respond(&config))
+        .collect::<Vec<_>>();
+
+    Ok(HttpResponse::Ok().json(attachments))
+}
+
+#[get("/{attachment_id}")]
+async fn get_attachment(
+    pool: Data<DbPool>,
+    config: Data<AttachmentConfig>,
+    user: User,
+    Path(path): Path<(Uuid, Uuid, Uuid, Uuid)>,
+) -> Result<HttpResponse, Error> {
+    let (_, attachment) = find_attachment(&pool, &user, path)?;
+
+    Ok(HttpResponse::Ok().json(attachment.respond(&config)))
+}
+
+#[delete("/{attachment_id}")]
+async fn delete_attachment(
+    pool: Data<DbPool>,
+    store: Data<dyn BlobStore>,
+    user: User,
+    Path(path): Path<(Uuid, Uuid, Uuid, Uuid)>,
+) -> Result<HttpResponse, Error> {
+    let (board, attachment) = find_attachment(&pool, &user, path)?;
+
+    if !board.allows(&pool, &user, Role::Editor)? {
+        Err(HttpResponse::Unauthorized().finish())?
+    }
+
+    attachment.delete(&pool)?;
+    remove_blobs(&store, &attachment.blob_keys()).await;
+
+    Ok(HttpResponse::NoContent().finish())
+}
@@ -0,0 +1,25 @@
+use actix_web::{
+    get,
+    web::{Data, ServiceConfig},
+    Error, HttpResponse,
+};
+
+use crate::{
+    models::{AuditEntry, User},
+    DbPool,
+};
+
+pub fn config(cfg: &mut ServiceConfig) {
+    cfg.service(get_audit_log);
+}
+
+#[get("")]
+async fn get_audit_log(pool: Data<DbPool>, user: User) -> Result<HttpResponse, Error> {
+    if !user.admin {
+        Err(HttpResponse::Forbidden().finish())?
+    }
+
+    let entries = AuditEntry::all(&pool)?;
+
+    Ok(HttpResponse::Ok().json(entries))
+}
@@ -1,19 +1,18 @@
 use actix_web::{
+    error::BlockingError,
     post,
-    web::{Data, Form, ServiceConfig},
-    Error, HttpResponse,
+    web::{self, Data, Form, Path, ServiceConfig},
+    Error, HttpRequest, HttpResponse,
 };
-use argon2::{Argon2, PasswordHash, PasswordVerifier};
-use diesel::{prelude::*, result::Error as DieselError};
 use jsonwebtoken::{encode, EncodingKey, Header};
 use serde::Deserialize;
 use uuid::Uuid;
 
-use crate::{errors::ServiceError, schema::users, Claims, DbPool, JWTConfig};
+use crate::{auth::AuthBackend, errors::ServiceError, models::User, Claims, DbPool, JWTConfig};
 
 pub fn config(cfg: &mut ServiceConfig) {
     // TODO: Allow only POST
-    cfg.service(login);
+    cfg.service(login).service(impersonate);
 }
 
 #[derive(Deserialize)]
@@ -26,26 +25,56 @@ struct LoginForm {
 async fn login(
     pool: Data<DbPool>,
     jwt_config: Data<JWTConfig>,
+    backend: Data<dyn AuthBackend>,
     Form(data): Form<LoginForm>,
 ) -> Result<HttpResponse, Error> {
-    let conn = pool.get().unwrap();
-
-    let (user_id, password_hash) = users::table
-        .filter(users::mail.eq(data.username))
-        .select((users::id, users::password))
-        .first::<(Uuid, String)>(&conn)
+    // Backends may block on the database, hashing or a directory server
+    let user = web::block(move || backend.authenticate(&pool, &data.username, &data.password))
+        .await
         .map_err(|e| match e {
-            DieselError::NotFound => ServiceError::InvalidCredentials,
-            _ => ServiceError::InternalServerError,
+            BlockingError::Error(e) => e,
+            BlockingError::Canceled => ServiceError::InternalServerError,
         })?;
 
-    let password_hash = PasswordHash::new(&password_hash).unwrap();
+    let claims = Claims::new(user.id.to_string(), jwt_config.expiry);
+
+    let token = encode(
+        &Header::default(),
+        &claims,
+        &EncodingKey::from_secret(jwt_config.key.as_bytes()),
+    )
+    .map_err(|_| ServiceError::InternalServerError)?;
+
+    Ok(HttpResponse::Ok().json(token))
+}
+
+/// Issues a short-lived token that lets an admin act as another user
+#[post("/impersonate/{user_id}")]
+async fn impersonate(
+    req: HttpRequest,
+    pool: Data<DbPool>,
+    jwt_config: Data<JWTConfig>,
+    user: User,
+    Path(user_id): Path<Uuid>,
+) -> Result<HttpResponse, Error> {
+    if Claims::try_from(&req)?.is_impersonated() {
+        Err(ServiceError::ImpersonationForbidden)?
+    }
+
+    if !user.admin {
+        Err(HttpResponse::Forbidden().finish())?
+    }
 
-    Argon2::default()
-        .verify_password(data.password.as_bytes(), &password_hash)
-        .map_err(|_| ServiceError::InvalidCredentials)?;
+    let target = match User::find(&pool, user_id)? {
+        Some(target) => target,
+        None => Err(HttpResponse::NotFound().finish())?,
+    };
 
-    let claims = Claims::new(user_id.to_string(), jwt_config.expiry);
+    let claims = Claims::impersonate(
+        target.id.to_string(),
+        user.id.to_string(),
+        jwt_config.impersonation_expiry,
+    );
 
     let token = encode(
         &Header::default(),
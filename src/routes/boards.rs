@@ -1,15 +1,19 @@
 use actix_web::{
     delete, get, patch, post,
-    web::{Data, Json, Path, ServiceConfig},
+    web::{Data, Json, Path, Query, ServiceConfig},
     Error, HttpResponse,
 };
 use diesel::prelude::*;
+use serde::Deserialize;
 use uuid::Uuid;
 
+use super::attachments::copy_blobs;
 use crate::{
     errors::ServiceError,
     get_conn,
-    models::{Board, BoardUpdate, User},
+    models::{Board, BoardUpdate, CloneOptions, Role, User},
+    schema::{board_members, boards},
+    storage::BlobStore,
     DbPool,
 };
 
@@ -18,9 +22,19 @@ pub fn config(cfg: &mut ServiceConfig) {
         .service(get_board)
         .service(new_board)
         .service(patch_board)
+        .service(clone_board)
+        .service(archive_board)
+        .service(restore_board)
         .service(delete_board);
 }
 
+/// `?archived=true` lists only archived items instead of hiding them
+#[derive(Deserialize)]
+pub(super) struct ArchivedQuery {
+    #[serde(default)]
+    pub(super) archived: bool,
+}
+
 #[post("")]
 async fn new_board(
     pool: Data<DbPool>,
@@ -36,12 +50,31 @@ async fn new_board(
 }
 
 #[get("/me")]
-async fn my_boards(pool: Data<DbPool>, user: User) -> Result<HttpResponse, Error> {
+async fn my_boards(
+    pool: Data<DbPool>,
+    user: User,
+    query: Query<ArchivedQuery>,
+) -> Result<HttpResponse, Error> {
     let conn = get_conn(&pool)?;
 
-    let boards = Board::belonging_to(&user)
+    let shared = board_members::table
+        .filter(board_members::member.eq(user.id))
+        .select(board_members::board);
+
+    let mut boards = boards::table
+        .filter(boards::owner.eq(user.id).or(boards::id.eq_any(shared)))
+        .filter(boards::deleted_at.is_null())
+        .into_boxed();
+    boards = if query.archived {
+        boards.filter(boards::archived_at.is_not_null())
+    } else {
+        boards.filter(boards::archived_at.is_null())
+    };
+
+    let boards = boards
         .load::<Board>(&conn)
         .map_err(|_| ServiceError::InternalServerError)?;
+    let boards = Board::render(&pool, boards)?;
 
     Ok(HttpResponse::Ok().json(boards))
 }
@@ -49,7 +82,7 @@ async fn my_boards(pool: Data<DbPool>, user: User) -> Result<HttpResponse, Error
 #[get("/{board_id}")]
 async fn get_board(pool: Data<DbPool>, Path(board_id): Path<Uuid>) -> Result<HttpResponse, Error> {
     if let Some(board) = Board::find(&pool, board_id)? {
-        Ok(HttpResponse::Ok().json(board))
+        Ok(HttpResponse::Ok().json(board.render_one(&pool)?))
     } else {
         Err(HttpResponse::NotFound().finish())?
     }
@@ -69,7 +102,7 @@ async fn patch_board(
     data.id = board_id;
 
     if let Some(board) = Board::find(&pool, board_id)? {
-        if board.owner != user.id {
+        if !board.allows(&pool, &user, Role::Admin)? {
             Err(HttpResponse::Unauthorized().finish())?
         }
 
@@ -81,6 +114,83 @@ async fn patch_board(
     }
 }
 
+/// Clone of a board, named like the original unless a name is given
+#[derive(Deserialize)]
+struct CloneBoard {
+    name: Option<String>,
+    #[serde(flatten)]
+    options: CloneOptions,
+}
+
+/// Clones the board as a new board of the user, in a single transaction
+#[post("/{board_id}/clone")]
+async fn clone_board(
+    pool: Data<DbPool>,
+    store: Data<dyn BlobStore>,
+    user: User,
+    Path(board_id): Path<Uuid>,
+    Json(data): Json<CloneBoard>,
+) -> Result<HttpResponse, Error> {
+    if let Some(board) = Board::find(&pool, board_id)? {
+        if !board.allows(&pool, &user, Role::Viewer)? {
+            Err(HttpResponse::Unauthorized().finish())?
+        }
+
+        let name = data.name.unwrap_or_else(|| board.name.clone());
+        if name.trim().is_empty() {
+            Err(HttpResponse::BadRequest().finish())?
+        }
+
+        let (copy, attachments) = board.clone_for(&pool, &user, name, data.options)?;
+        copy_blobs(&pool, &store, attachments).await;
+
+        Ok(HttpResponse::Created()
+            .header("Location", format!("/{}", copy.id))
+            .json(copy))
+    } else {
+        Err(HttpResponse::NotFound().finish())?
+    }
+}
+
+#[post("/{board_id}/archive")]
+async fn archive_board(
+    pool: Data<DbPool>,
+    user: User,
+    Path(board_id): Path<Uuid>,
+) -> Result<HttpResponse, Error> {
+    if let Some(board) = Board::find(&pool, board_id)? {
+        if !board.allows(&pool, &user, Role::Admin)? {
+            Err(HttpResponse::Unauthorized().finish())?
+        }
+
+        let board = board.archive(&pool)?;
+
+        Ok(HttpResponse::Ok().json(board))
+    } else {
+        Err(HttpResponse::NotFound().finish())?
+    }
+}
+
+#[post("/{board_id}/restore")]
+async fn restore_board(
+    pool: Data<DbPool>,
+    user: User,
+    Path(board_id): Path<Uuid>,
+) -> Result<HttpResponse, Error> {
+    if let Some(board) = Board::find(&pool, board_id)? {
+        if !board.allows(&pool, &user, Role::Admin)? {
+            Err(HttpResponse::Unauthorized().finish())?
+        }
+
+        let board = board.restore(&pool)?;
+
+        Ok(HttpResponse::Ok().json(board))
+    } else {
+        Err(HttpResponse::NotFound().finish())?
+    }
+}
+
+/// Moves an archived board to the trash of the user
 #[delete("/{board_id}")]
 async fn delete_board(
     pool: Data<DbPool>,
@@ -92,7 +202,7 @@ async fn delete_board(
             Err(HttpResponse::Unauthorized().finish())?
         }
 
-        board.delete(&pool)?;
+        board.trash(&pool, &user)?;
     }
 
     Ok(HttpResponse::NoContent().finish())
@@ -0,0 +1,139 @@
+use actix_web::{
+    delete, get, post,
+    web::{Data, Json, Path, ServiceConfig},
+    Error, HttpResponse,
+};
+use diesel::prelude::*;
+use serde::Deserialize;
+use serde_json::json;
+use uuid::Uuid;
+
+use crate::{
+    errors::ServiceError,
+    get_conn,
+    models::{ApiKey, User},
+    schema::users,
+    DbPool,
+};
+
+pub fn config(cfg: &mut ServiceConfig) {
+    cfg.service(new_bot)
+        .service(my_bots)
+        .service(delete_bot)
+        .service(new_key)
+        .service(get_keys)
+        .service(delete_key);
+}
+
+#[derive(Deserialize)]
+struct NameForm {
+    name: String,
+}
+
+/// Returns the bot if it exists and is owned by `user`
+fn find_owned_bot(pool: &Data<DbPool>, user: &User, bot_id: Uuid) -> Result<User, HttpResponse> {
+    match User::find(pool, bot_id) {
+        Ok(Some(bot)) if bot.bot_owner == Some(user.id) => Ok(bot),
+        Ok(Some(_)) => Err(HttpResponse::Unauthorized().finish()),
+        Ok(None) => Err(HttpResponse::NotFound().finish()),
+        Err(_) => Err(HttpResponse::InternalServerError().finish()),
+    }
+}
+
+#[post("")]
+async fn new_bot(
+    pool: Data<DbPool>,
+    user: User,
+    Json(data): Json<NameForm>,
+) -> Result<HttpResponse, Error> {
+    if user.bot {
+        Err(HttpResponse::Forbidden().finish())?
+    }
+
+    let bot = User::new_bot(&user, data.name);
+    bot.save(&pool)?;
+
+    Ok(HttpResponse::Created()
+        .header("Location", format!("/{}", bot.id))
+        .json(bot))
+}
+
+#[get("")]
+async fn my_bots(pool: Data<DbPool>, user: User) -> Result<HttpResponse, Error> {
+    let conn = get_conn(&pool)?;
+
+    let bots = users::table
+        .filter(users::bot_owner.eq(user.id))
+        .load::<User>(&conn)
+        .map_err(|_| ServiceError::InternalServerError)?;
+
+    Ok(HttpResponse::Ok().json(bots))
+}
+
+#[delete("/{bot_id}")]
+async fn delete_bot(
+    pool: Data<DbPool>,
+    user: User,
+    Path(bot_id): Path<Uuid>,
+) -> Result<HttpResponse, Error> {
+    let bot = find_owned_bot(&pool, &user, bot_id)?;
+
+    // API keys and board memberships are removed along with the account
+    bot.delete(&pool)?;
+
+    Ok(HttpResponse::NoContent().finish())
+}
+
+/// Creates an API key for the bot. The key is only ever returned in this response.
+#[post("/{bot_id}/keys")]
+async fn new_key(
+    pool: Data<DbPool>,
+    user: User,
+    Path(bot_id): Path<Uuid>,
+    Json(data): Json<NameForm>,
+) -> Result<HttpResponse, Error> {
+    let bot = find_owned_bot(&pool, &user, bot_id)?;
+
+    let (key, token) = ApiKey::generate(&bot, data.name);
+    key.save(&pool)?;
+
+    Ok(HttpResponse::Created()
+        .header("Location", format!("/{}", key.id))
+        .json(json!({ "key": key, "token": token })))
+}
+
+#[get("/{bot_id}/keys")]
+async fn get_keys(
+    pool: Data<DbPool>,
+    user: User,
+    Path(bot_id): Path<Uuid>,
+) -> Result<HttpResponse, Error> {
+    let conn = get_conn(&pool)?;
+
+    let bot = find_owned_bot(&pool, &user, bot_id)?;
+
+    let keys = ApiKey::belonging_to(&bot)
+        .load::<ApiKey>(&conn)
+        .map_err(|_| ServiceError::InternalServerError)?;
+
+    Ok(HttpResponse::Ok().json(keys))
+}
+
+#[delete("/{bot_id}/keys/{key_id}")]
+async fn delete_key(
+    pool: Data<DbPool>,
+    user: User,
+    Path((bot_id, key_id)): Path<(Uuid, Uuid)>,
+) -> Result<HttpResponse, Error> {
+    let bot = find_owned_bot(&pool, &user, bot_id)?;
+
+    if let Some(key) = ApiKey::find(&pool, key_id)? {
+        if key.account != bot.id {
+            Err(HttpResponse::BadRequest().finish())?
+        }
+
+        key.delete(&pool)?;
+    }
+
+    Ok(HttpResponse::NoContent().finish())
+}
@@ -1,16 +1,31 @@
 use actix_web::{
-    delete, get, patch, post,
-    web::{Data, Json, Path, ServiceConfig},
+    delete, get, patch, post, put,
+    web::{Data, Json, Path, Query, ServiceConfig},
     Error, HttpResponse,
 };
-use diesel::prelude::*;
+use chrono::{Duration, Utc};
+use diesel::{
+    dsl::sql,
+    prelude::*,
+    sql_types::{Bool, Jsonb},
+};
+use serde::Deserialize;
+use serde_json::Value;
+use std::collections::HashMap;
 use uuid::Uuid;
 
+use super::attachments::copy_blobs;
 use crate::{
     errors::ServiceError,
     get_conn,
-    models::{Board, Card, CardUpdate, List, User},
-    DbPool,
+    models::{
+        Assignment, Board, Card, CardUpdate, CloneOptions, CustomField, FieldKind, Label, List,
+        Role, User,
+    },
+    rank::Placement,
+    schema::{card_assignees, card_fields, cards},
+    storage::BlobStore,
+    AttachmentConfig, DbPool,
 };
 
 pub fn config(cfg: &mut ServiceConfig) {
@@ -18,7 +33,149 @@ pub fn config(cfg: &mut ServiceConfig) {
         .service(get_cards)
         .service(get_card)
         .service(patch_card)
-        .service(delete_card);
+        .service(move_card)
+        .service(clone_card)
+        .service(archive_card)
+        .service(restore_card)
+        .service(delete_card)
+        .service(add_label)
+        .service(remove_label)
+        .service(add_assignee)
+        .service(remove_assignee);
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub(super) enum Render {
+    Html,
+}
+
+/// `?render=html` adds the rendered description to returned cards
+#[derive(Deserialize)]
+pub(super) struct RenderQuery {
+    render: Option<Render>,
+}
+
+impl RenderQuery {
+    pub(super) fn html(&self) -> bool {
+        matches!(self.render, Some(Render::Html))
+    }
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "lowercase")]
+enum Due {
+    Overdue,
+    Soon,
+}
+
+/// Card orderings, cards without a priority or estimate come last
+#[derive(Deserialize)]
+#[serde(rename_all = "lowercase")]
+enum Sort {
+    Position,
+    Priority,
+    Estimate,
+}
+
+/// Card listing filters, `?due=soon` matches incomplete cards due within the next
+/// `within` hours (24 by default). `?assignee=` takes a user id or `me`. Custom fields
+/// are filtered with `?field.<id>=<value>`, which matches multi-select values
+/// containing the option. `?archived=true` lists only archived cards.
+#[derive(Deserialize)]
+struct CardsQuery {
+    render: Option<Render>,
+    due: Option<Due>,
+    within: Option<u32>,
+    assignee: Option<String>,
+    sort: Option<Sort>,
+    #[serde(default)]
+    archived: bool,
+}
+
+/// Card with the values of its custom fields by field id
+#[derive(Deserialize)]
+struct NewCard {
+    #[serde(flatten)]
+    card: Card,
+    #[serde(default)]
+    fields: HashMap<Uuid, Value>,
+}
+
+/// Card update, `null` removes the value of a custom field
+#[derive(Deserialize)]
+struct CardPatch {
+    #[serde(flatten)]
+    update: CardUpdate,
+    #[serde(default)]
+    fields: HashMap<Uuid, Value>,
+}
+
+/// Checks custom field values against the fields of the board
+fn check_fields(
+    pool: &Data<DbPool>,
+    board: &Board,
+    values: HashMap<Uuid, Value>,
+) -> Result<Vec<(Uuid, Option<Value>)>, Error> {
+    let mut checked = Vec::new();
+    for (id, value) in values {
+        let field = match CustomField::find(pool, id)? {
+            Some(field) if field.board == board.id => field,
+            _ => Err(HttpResponse::BadRequest().finish())?,
+        };
+
+        let value = match value {
+            Value::Null => None,
+            value => Some(field.check_value(pool, board, value)?),
+        };
+        checked.push((id, value));
+    }
+
+    Ok(checked)
+}
+
+/// Resolves a user filter, `me` stands for the current user
+fn user_filter(filter: &str, user: &Option<User>) -> Result<Uuid, Error> {
+    match (filter, user) {
+        ("me", Some(user)) => Ok(user.id),
+        ("me", None) => Err(ServiceError::MissingToken)?,
+        (id, _) => match Uuid::parse_str(id) {
+            Ok(id) => Ok(id),
+            Err(_) => Err(HttpResponse::BadRequest().finish())?,
+        },
+    }
+}
+
+/// Finds a card by its full path, failing with 404 if any part doesn't match
+pub(super) fn find_card(
+    pool: &Data<DbPool>,
+    board_id: Uuid,
+    list_id: Uuid,
+    card_id: Uuid,
+) -> Result<(Board, Card), Error> {
+    let board = Board::find(pool, board_id)?;
+    let list = List::find(pool, list_id)?;
+    let card = Card::find(pool, card_id)?;
+
+    match (board, list, card) {
+        (Some(board), Some(list), Some(card)) if list.board == board.id && card.list == list.id => {
+            Ok((board, card))
+        }
+        _ => Err(HttpResponse::NotFound().finish())?,
+    }
+}
+
+/// Attachments are only listed to users who can see the board, since their URLs allow
+/// downloading them
+fn lists_attachments(
+    pool: &Data<DbPool>,
+    board: &Board,
+    user: &Option<User>,
+) -> Result<bool, ServiceError> {
+    match user {
+        Some(user) => board.allows(pool, user, Role::Viewer),
+        None => Ok(false),
+    }
 }
 
 #[post("")]
@@ -26,10 +183,14 @@ async fn new_card(
     pool: Data<DbPool>,
     user: User,
     Path((board_id, list_id)): Path<(Uuid, Uuid)>,
-    Json(data): Json<Card>,
+    Json(NewCard { card: data, fields }): Json<NewCard>,
 ) -> Result<HttpResponse, Error> {
+    if data.title.trim().is_empty() {
+        Err(HttpResponse::BadRequest().finish())?
+    }
+
     if let Some(board) = Board::find(&pool, board_id)? {
-        if board.owner != user.id {
+        if !board.allows(&pool, &user, Role::Editor)? {
             Err(ServiceError::InvalidCredentials)?
         }
 
@@ -38,8 +199,15 @@ async fn new_card(
                 Err(HttpResponse::Unauthorized().finish())?
             }
 
-            let card = Card::new(&list, data.content, data.labels);
-            card.save(&pool)?;
+            let fields = check_fields(&pool, &board, fields)?;
+
+            let mut card = Card::new(&list, data.title, data.description);
+            card.start_at = data.start_at;
+            card.due_at = data.due_at;
+            card.completed = data.completed;
+            card.priority = data.priority;
+            card.estimate = data.estimate;
+            card.save(&pool, fields)?;
 
             Ok(HttpResponse::Created()
                 .header("Location", format!("/{}", card.id))
@@ -55,9 +223,14 @@ async fn new_card(
 #[get("")]
 async fn get_cards(
     pool: Data<DbPool>,
+    config: Data<AttachmentConfig>,
+    user: Option<User>,
     Path((board_id, list_id)): Path<(Uuid, Uuid)>,
+    query: Query<CardsQuery>,
+    filters: Query<Vec<(String, String)>>,
 ) -> Result<HttpResponse, Error> {
     let conn = get_conn(&pool)?;
+    let html = matches!(query.render, Some(Render::Html));
 
     if let Some(board) = Board::find(&pool, board_id)? {
         if let Some(list) = List::find(&pool, list_id)? {
@@ -65,9 +238,93 @@ async fn get_cards(
                 Err(HttpResponse::NotFound().finish())?
             }
 
-            let cards = Card::belonging_to(&list)
+            let mut cards = Card::belonging_to(&list)
+                .filter(cards::deleted_at.is_null())
+                .into_boxed();
+            cards = if query.archived {
+                cards.filter(cards::archived_at.is_not_null())
+            } else {
+                cards.filter(cards::archived_at.is_null())
+            };
+
+            let now = Utc::now();
+            match query.due {
+                Some(Due::Overdue) => {
+                    cards = cards
+                        .filter(cards::completed.eq(false))
+                        .filter(cards::due_at.lt(now));
+                }
+                Some(Due::Soon) => {
+                    let within = Duration::hours(query.within.unwrap_or(24).into());
+                    cards = cards
+                        .filter(cards::completed.eq(false))
+                        .filter(cards::due_at.ge(now))
+                        .filter(cards::due_at.le(now + within));
+                }
+                None => {}
+            }
+
+            if let Some(assignee) = &query.assignee {
+                let assignee = user_filter(assignee, &user)?;
+
+                cards = cards.filter(
+                    cards::id.eq_any(
+                        card_assignees::table
+                            .filter(card_assignees::account.eq(assignee))
+                            .select(card_assignees::card),
+                    ),
+                );
+            }
+
+            for (key, filter) in filters.iter() {
+                let id = match key.strip_prefix("field.").map(Uuid::parse_str) {
+                    Some(Ok(id)) => id,
+                    Some(Err(_)) => Err(HttpResponse::BadRequest().finish())?,
+                    None => continue,
+                };
+                let field = match CustomField::find(&pool, id)? {
+                    Some(field) if field.board == board.id => field,
+                    _ => Err(HttpResponse::BadRequest().finish())?,
+                };
+
+                let value = match field.kind {
+                    FieldKind::User => field.filter_value(&user_filter(filter, &user)?.to_string()),
+                    _ => field.filter_value(filter),
+                };
+                let value = match value {
+                    Some(value) => value,
+                    None => Err(HttpResponse::BadRequest().finish())?,
+                };
+
+                cards = cards.filter(
+                    cards::id.eq_any(
+                        card_fields::table
+                            .filter(card_fields::field.eq(field.id))
+                            .filter(sql::<Bool>("card_fields.value @> ").bind::<Jsonb, _>(value))
+                            .select(card_fields::card),
+                    ),
+                );
+            }
+
+            cards = match query.sort {
+                Some(Sort::Position) | None => cards.order((cards::position, cards::id)),
+                Some(Sort::Priority) => cards.order((
+                    cards::priority.desc().nulls_last(),
+                    cards::position,
+                    cards::id,
+                )),
+                Some(Sort::Estimate) => cards.order((
+                    cards::estimate.desc().nulls_last(),
+                    cards::position,
+                    cards::id,
+                )),
+            };
+
+            let cards = cards
                 .load::<Card>(&conn)
                 .map_err(|_| ServiceError::InternalServerError)?;
+            let attachments = lists_attachments(&pool, &board, &user)?;
+            let cards = Card::render(&pool, &config, cards, html, attachments)?;
 
             Ok(HttpResponse::Ok().json(cards))
         } else {
@@ -81,7 +338,10 @@ async fn get_cards(
 #[get("/{card_id}")]
 async fn get_card(
     pool: Data<DbPool>,
+    config: Data<AttachmentConfig>,
+    user: Option<User>,
     Path((board_id, list_id, card_id)): Path<(Uuid, Uuid, Uuid)>,
+    query: Query<RenderQuery>,
 ) -> Result<HttpResponse, Error> {
     if let Some(board) = Board::find(&pool, board_id)? {
         if let Some(list) = List::find(&pool, list_id)? {
@@ -90,6 +350,9 @@ async fn get_card(
                     Err(HttpResponse::NotFound().finish())?
                 }
 
+                let attachments = lists_attachments(&pool, &board, &user)?;
+                let card = card.render_one(&pool, &config, query.html(), attachments)?;
+
                 Ok(HttpResponse::Ok().json(card))
             } else {
                 Err(HttpResponse::NotFound().finish())?
@@ -105,24 +368,104 @@ async fn get_card(
 #[patch("/{card_id}")]
 async fn patch_card(
     pool: Data<DbPool>,
+    config: Data<AttachmentConfig>,
     user: User,
     Path((board_id, list_id, card_id)): Path<(Uuid, Uuid, Uuid)>,
-    Json(mut data): Json<CardUpdate>,
+    query: Query<RenderQuery>,
+    Json(CardPatch {
+        update: mut data,
+        fields,
+    }): Json<CardPatch>,
 ) -> Result<HttpResponse, Error> {
-    if data.is_empty() {
+    if data.is_empty() && fields.is_empty() {
         Err(ServiceError::EmptyUpdate)?
     }
 
+    if matches!(&data.title, Some(title) if title.trim().is_empty()) {
+        Err(HttpResponse::BadRequest().finish())?
+    }
+
     if let Some(board) = Board::find(&pool, board_id)? {
         if let Some(list) = List::find(&pool, list_id)? {
             if let Some(card) = Card::find(&pool, card_id)? {
-                if user.id != board.owner || list.board != board.id || card.list != list.id {
+                if !board.allows(&pool, &user, Role::Editor)?
+                    || list.board != board.id
+                    || card.list != list.id
+                {
                     Err(HttpResponse::Unauthorized().finish())?
                 }
 
+                let fields = check_fields(&pool, &board, fields)?;
+
                 data.id = card_id;
 
-                let card = card.update(&pool, data)?;
+                let card = card.update(&pool, data, fields)?;
+
+                let card = card.render_one(&pool, &config, query.html(), true)?;
+
+                Ok(HttpResponse::Ok().json(card))
+            } else {
+                Err(HttpResponse::NotFound().finish())?
+            }
+        } else {
+            Err(HttpResponse::NotFound().finish())?
+        }
+    } else {
+        Err(HttpResponse::NotFound().finish())?
+    }
+}
+
+#[derive(Deserialize)]
+struct MoveForm {
+    /// Target list, defaults to the current list of the card
+    list: Option<Uuid>,
+    before: Option<Uuid>,
+    after: Option<Uuid>,
+}
+
+/// Moves the card to another list, possibly on another board, before or after one of
+/// its cards. Without a neighbour the card is put at the end of the list.
+#[post("/{card_id}/move")]
+async fn move_card(
+    pool: Data<DbPool>,
+    user: User,
+    Path((board_id, list_id, card_id)): Path<(Uuid, Uuid, Uuid)>,
+    Json(data): Json<MoveForm>,
+) -> Result<HttpResponse, Error> {
+    let placement = Placement::from_neighbours(data.before, data.after)?;
+
+    if let Some(board) = Board::find(&pool, board_id)? {
+        if let Some(list) = List::find(&pool, list_id)? {
+            if let Some(card) = Card::find(&pool, card_id)? {
+                if !board.allows(&pool, &user, Role::Editor)?
+                    || list.board != board.id
+                    || card.list != list.id
+                {
+                    Err(HttpResponse::Unauthorized().finish())?
+                }
+
+                let target = match data.list {
+                    Some(target_id) if target_id != list.id => {
+                        match List::find(&pool, target_id)? {
+                            Some(target) => target,
+                            None => Err(HttpResponse::NotFound().finish())?,
+                        }
+                    }
+                    _ => list,
+                };
+
+                if target.board != board.id {
+                    match Board::find(&pool, target.board)? {
+                        Some(target_board) => {
+                            if !target_board.allows(&pool, &user, Role::Editor)? {
+                                Err(HttpResponse::Unauthorized().finish())?
+                            }
+                        }
+                        None => Err(HttpResponse::NotFound().finish())?,
+                    }
+                }
+
+                let card = card.move_to(&pool, target.id, placement)?;
 
                 Ok(HttpResponse::Ok().json(card))
             } else {
@@ -136,6 +479,64 @@ async fn patch_card(
     }
 }
 
+/// Clones the card right after itself, in a single transaction
+#[post("/{card_id}/clone")]
+async fn clone_card(
+    pool: Data<DbPool>,
+    store: Data<dyn BlobStore>,
+    user: User,
+    Path((board_id, list_id, card_id)): Path<(Uuid, Uuid, Uuid)>,
+    Json(options): Json<CloneOptions>,
+) -> Result<HttpResponse, Error> {
+    let (board, card) = find_card(&pool, board_id, list_id, card_id)?;
+
+    if !board.allows(&pool, &user, Role::Editor)? {
+        Err(HttpResponse::Unauthorized().finish())?
+    }
+
+    let (copy, attachments) = card.duplicate(&pool, options)?;
+    copy_blobs(&pool, &store, attachments).await;
+
+    Ok(HttpResponse::Created()
+        .header("Location", format!("/{}", copy.id))
+        .json(copy))
+}
+
+#[post("/{card_id}/archive")]
+async fn archive_card(
+    pool: Data<DbPool>,
+    user: User,
+    Path((board_id, list_id, card_id)): Path<(Uuid, Uuid, Uuid)>,
+) -> Result<HttpResponse, Error> {
+    let (board, card) = find_card(&pool, board_id, list_id, card_id)?;
+
+    if !board.allows(&pool, &user, Role::Editor)? {
+        Err(HttpResponse::Unauthorized().finish())?
+    }
+
+    let card = card.archive(&pool)?;
+
+    Ok(HttpResponse::Ok().json(card))
+}
+
+#[post("/{card_id}/restore")]
+async fn restore_card(
+    pool: Data<DbPool>,
+    user: User,
+    Path((board_id, list_id, card_id)): Path<(Uuid, Uuid, Uuid)>,
+) -> Result<HttpResponse, Error> {
+    let (board, card) = find_card(&pool, board_id, list_id, card_id)?;
+
+    if !board.allows(&pool, &user, Role::Editor)? {
+        Err(HttpResponse::Unauthorized().finish())?
+    }
+
+    let card = card.restore(&pool)?;
+
+    Ok(HttpResponse::Ok().json(card))
+}
+
+/// Moves an archived card to the trash of the user
 #[delete("/{card_id}")]
 async fn delete_card(
     pool: Data<DbPool>,
@@ -145,14 +546,106 @@ async fn delete_card(
     if let Some(board) = Board::find(&pool, board_id)? {
         if let Some(list) = List::find(&pool, list_id)? {
             if let Some(card) = Card::find(&pool, card_id)? {
-                if user.id != board.owner || list.board != board.id || card.list != list.id {
+                if !board.allows(&pool, &user, Role::Editor)?
+                    || list.board != board.id
+                    || card.list != list.id
+                {
                     Err(HttpResponse::Unauthorized().finish())?
                 }
 
-                card.delete(&pool)?;
+                card.trash(&pool, &user)?;
             }
         }
     }
 
     Ok(HttpResponse::NoContent().finish())
 }
+
+/// Finds a card and a label of its board, checking that the user can edit the card
+fn find_card_label(
+    pool: &Data<DbPool>,
+    user: &User,
+    (board_id, list_id, card_id, label_id): (Uuid, Uuid, Uuid, Uuid),
+) -> Result<(Card, Label), Error> {
+    let (board, card) = find_card(pool, board_id, list_id, card_id)?;
+
+    if !board.allows(pool, user, Role::Editor)? {
+        Err(HttpResponse::Unauthorized().finish())?
+    }
+
+    match Label::find(pool, label_id)? {
+        Some(label) if label.board == board.id => Ok((card, label)),
+        _ => Err(HttpResponse::NotFound().finish())?,
+    }
+}
+
+#[put("/{card_id}/labels/{label_id}")]
+async fn add_label(
+    pool: Data<DbPool>,
+    user: User,
+    Path(path): Path<(Uuid, Uuid, Uuid, Uuid)>,
+) -> Result<HttpResponse, Error> {
+    let (card, label) = find_card_label(&pool, &user, path)?;
+
+    label.attach(&pool, card.id)?;
+
+    Ok(HttpResponse::NoContent().finish())
+}
+
+#[delete("/{card_id}/labels/{label_id}")]
+async fn remove_label(
+    pool: Data<DbPool>,
+    user: User,
+    Path(path): Path<(Uuid, Uuid, Uuid, Uuid)>,
+) -> Result<HttpResponse, Error> {
+    let (card, label) = find_card_label(&pool, &user, path)?;
+
+    if label.detach(&pool, card.id)? == 0 {
+        Err(HttpResponse::NotFound().finish())?
+    }
+
+    Ok(HttpResponse::NoContent().finish())
+}
+
+/// Assigns a user with access to the board to the card
+#[put("/{card_id}/assignees/{user_id}")]
+async fn add_assignee(
+    pool: Data<DbPool>,
+    user: User,
+    Path((board_id, list_id, card_id, user_id)): Path<(Uuid, Uuid, Uuid, Uuid)>,
+) -> Result<HttpResponse, Error> {
+    let (board, card) = find_card(&pool, board_id, list_id, card_id)?;
+
+    if !board.allows(&pool, &user, Role::Editor)? {
+        Err(HttpResponse::Unauthorized().finish())?
+    }
+
+    let assignee = match User::find(&pool, user_id)? {
+        Some(assignee) if board.allows(&pool, &assignee, Role::Viewer)? => assignee,
+        _ => Err(HttpResponse::BadRequest().finish())?,
+    };
+
+    Assignment::new(&card, &assignee).save(&pool)?;
+
+    Ok(HttpResponse::NoContent().finish())
+}
+
+#[delete("/{card_id}/assignees/{user_id}")]
+async fn remove_assignee(
+    pool: Data<DbPool>,
+    user: User,
+    Path((board_id, list_id, card_id, user_id)): Path<(Uuid, Uuid, Uuid, Uuid)>,
+) -> Result<HttpResponse, Error> {
+    let (board, card) = find_card(&pool, board_id, list_id, card_id)?;
+
+    // Users are allowed to unassign themselves
+    if user.id != user_id && !board.allows(&pool, &user, Role::Editor)? {
+        Err(HttpResponse::Unauthorized().finish())?
+    }
+
+    if Assignment::delete(&pool, card.id, user_id)? == 0 {
+        Err(HttpResponse::NotFound().finish())?
+    }
+
+    Ok(HttpResponse::NoContent().finish())
+}
@@ -0,0 +1,216 @@
+use actix_web::{
+    delete, get, patch, post,
+    web::{Data, Json, Path, ServiceConfig},
+    Error, HttpResponse,
+};
+use uuid::Uuid;
+
+use super::cards::find_card;
+use crate::{
+    errors::ServiceError,
+    models::{Board, Checklist, ChecklistItem, ChecklistItemUpdate, Role, User},
+    rank::Placement,
+    DbPool,
+};
+
+pub fn config(cfg: &mut ServiceConfig) {
+    cfg.service(new_checklist)
+        .service(get_checklists)
+        .service(patch_checklist)
+        .service(move_checklist)
+        .service(delete_checklist)
+        .service(new_item)
+        .service(patch_item)
+        .service(move_item)
+        .service(delete_item);
+}
+
+/// Finds a checklist by its full path and checks that the user can edit the card
+fn find_checklist(
+    pool: &Data<DbPool>,
+    user: &User,
+    (board_id, list_id, card_id, checklist_id): (Uuid, Uuid, Uuid, Uuid),
+) -> Result<(Board, Checklist), Error> {
+    let (board, card) = find_card(pool, board_id, list_id, card_id)?;
+
+    if !board.allows(pool, user, Role::Editor)? {
+        Err(HttpResponse::Unauthorized().finish())?
+    }
+
+    match Checklist::find(pool, checklist_id)? {
+        Some(checklist) if checklist.card == card.id => Ok((board, checklist)),
+        _ => Err(HttpResponse::NotFound().finish())?,
+    }
+}
+
+/// Finds a checklist item by its full path and checks that the user can edit the card
+fn find_item(
+    pool: &Data<DbPool>,
+    user: &User,
+    (board_id, list_id, card_id, checklist_id, item_id): (Uuid, Uuid, Uuid, Uuid, Uuid),
+) -> Result<(Board, ChecklistItem), Error> {
+    let (board, checklist) =
+        find_checklist(pool, user, (board_id, list_id, card_id, checklist_id))?;
+
+    match ChecklistItem::find(pool, item_id)? {
+        Some(item) if item.checklist == checklist.id => Ok((board, item)),
+        _ => Err(HttpResponse::NotFound().finish())?,
+    }
+}
+
+/// Items can only be assigned to users with access to the board
+fn check_assignee(pool: &Data<DbPool>, board: &Board, assignee: Option<Uuid>) -> Result<(), Error> {
+    if let Some(assignee) = assignee {
+        match User::find(pool, assignee)? {
+            Some(assignee) if board.allows(pool, &assignee, Role::Viewer)? => {}
+            _ => Err(HttpResponse::BadRequest().finish())?,
+        }
+    }
+
+    Ok(())
+}
+
+#[post("")]
+async fn new_checklist(
+    pool: Data<DbPool>,
+    user: User,
+    Path((board_id, list_id, card_id)): Path<(Uuid, Uuid, Uuid)>,
+    Json(data): Json<Checklist>,
+) -> Result<HttpResponse, Error> {
+    let (board, card) = find_card(&pool, board_id, list_id, card_id)?;
+
+    if !board.allows(&pool, &user, Role::Editor)? {
+        Err(HttpResponse::Unauthorized().finish())?
+    }
+
+    let mut checklist = Checklist::new(&card, data.name);
+    checklist.save(&pool)?;
+
+    Ok(HttpResponse::Created()
+        .header("Location", format!("/{}", checklist.id))
+        .json(checklist))
+}
+
+/// Returns all checklists of the card with their items
+#[get("")]
+async fn get_checklists(
+    pool: Data<DbPool>,
+    Path((board_id, list_id, card_id)): Path<(Uuid, Uuid, Uuid)>,
+) -> Result<HttpResponse, Error> {
+    let (_, card) = find_card(&pool, board_id, list_id, card_id)?;
+
+    let checklists = Checklist::for_card(&pool, &card)?;
+
+    Ok(HttpResponse::Ok().json(checklists))
+}
+
+#[patch("/{checklist_id}")]
+async fn patch_checklist(
+    pool: Data<DbPool>,
+    user: User,
+    Path(path): Path<(Uuid, Uuid, Uuid, Uuid)>,
+    Json(data): Json<Checklist>,
+) -> Result<HttpResponse, Error> {
+    let (_, checklist) = find_checklist(&pool, &user, path)?;
+
+    let checklist = checklist.rename(&pool, data.name)?;
+
+    Ok(HttpResponse::Ok().json(checklist))
+}
+
+/// Moves the checklist before or after another checklist of the card
+#[post("/{checklist_id}/move")]
+async fn move_checklist(
+    pool: Data<DbPool>,
+    user: User,
+    Path(path): Path<(Uuid, Uuid, Uuid, Uuid)>,
+    Json(placement): Json<Placement>,
+) -> Result<HttpResponse, Error> {
+    let (_, checklist) = find_checklist(&pool, &user, path)?;
+
+    let checklist = checklist.move_to(&pool, placement)?;
+
+    Ok(HttpResponse::Ok().json(checklist))
+}
+
+#[delete("/{checklist_id}")]
+async fn delete_checklist(
+    pool: Data<DbPool>,
+    user: User,
+    Path(path): Path<(Uuid, Uuid, Uuid, Uuid)>,
+) -> Result<HttpResponse, Error> {
+    let (_, checklist) = find_checklist(&pool, &user, path)?;
+
+    checklist.delete(&pool)?;
+
+    Ok(HttpResponse::NoContent().finish())
+}
+
+#[post("/{checklist_id}/items")]
+async fn new_item(
+    pool: Data<DbPool>,
+    user: User,
+    Path(path): Path<(Uuid, Uuid, Uuid, Uuid)>,
+    Json(data): Json<ChecklistItem>,
+) -> Result<HttpResponse, Error> {
+    let (board, checklist) = find_checklist(&pool, &user, path)?;
+
+    check_assignee(&pool, &board, data.assignee)?;
+
+    let mut item = ChecklistItem::new(&checklist, data.content, data.assignee, data.due_at);
+    item.checked = data.checked;
+    item.save(&pool)?;
+
+    Ok(HttpResponse::Created()
+        .header("Location", format!("/{}", item.id))
+        .json(item))
+}
+
+#[patch("/{checklist_id}/items/{item_id}")]
+async fn patch_item(
+    pool: Data<DbPool>,
+    user: User,
+    Path(path): Path<(Uuid, Uuid, Uuid, Uuid, Uuid)>,
+    Json(mut data): Json<ChecklistItemUpdate>,
+) -> Result<HttpResponse, Error> {
+    if data.is_empty() {
+        Err(ServiceError::EmptyUpdate)?
+    }
+
+    let (board, item) = find_item(&pool, &user, path)?;
+
+    check_assignee(&pool, &board, data.assignee.flatten())?;
+
+    data.id = item.id;
+    let item = item.update(&pool, data)?;
+
+    Ok(HttpResponse::Ok().json(item))
+}
+
+/// Moves the item before or after another item of the checklist
+#[post("/{checklist_id}/items/{item_id}/move")]
+async fn move_item(
+    pool: Data<DbPool>,
+    user: User,
+    Path(path): Path<(Uuid, Uuid, Uuid, Uuid, Uuid)>,
+    Json(placement): Json<Placement>,
+) -> Result<HttpResponse, Error> {
+    let (_, item) = find_item(&pool, &user, path)?;
+
+    let item = item.move_to(&pool, placement)?;
+
+    Ok(HttpResponse::Ok().json(item))
+}
+
+#[delete("/{checklist_id}/items/{item_id}")]
+async fn delete_item(
+    pool: Data<DbPool>,
+    user: User,
+    Path(path): Path<(Uuid, Uuid, Uuid, Uuid, Uuid)>,
+) -> Result<HttpResponse, Error> {
+    let (_, item) = find_item(&pool, &user, path)?;
+
+    item.delete(&pool)?;
+
+    Ok(HttpResponse::NoContent().finish())
+}
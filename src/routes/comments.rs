@@ -0,0 +1,143 @@
+use actix_web::{
+    delete, get, patch, post,
+    web::{Data, Json, Path, Query, ServiceConfig},
+    Error, HttpResponse,
+};
+use serde::Deserialize;
+use uuid::Uuid;
+
+use super::cards::{find_card, Render};
+use crate::{
+    models::{Board, Comment, Role, User},
+    DbPool,
+};
+
+pub fn config(cfg: &mut ServiceConfig) {
+    cfg.service(new_comment)
+        .service(get_comments)
+        .service(patch_comment)
+        .service(delete_comment);
+}
+
+/// `?page=` is 1-based, `?render=html` adds the rendered content
+#[derive(Deserialize)]
+struct CommentsQuery {
+    render: Option<Render>,
+    page: Option<i64>,
+    per_page: Option<i64>,
+}
+
+/// Finds a comment by its full path and checks that the user can see the board
+fn find_comment(
+    pool: &Data<DbPool>,
+    user: &User,
+    (board_id, list_id, card_id, comment_id): (Uuid, Uuid, Uuid, Uuid),
+) -> Result<(Board, Comment), Error> {
+    let (board, card) = find_card(pool, board_id, list_id, card_id)?;
+
+    if !board.allows(pool, user, Role::Viewer)? {
+        Err(HttpResponse::Unauthorized().finish())?
+    }
+
+    match Comment::find(pool, comment_id)? {
+        Some(comment) if comment.card == card.id => Ok((board, comment)),
+        _ => Err(HttpResponse::NotFound().finish())?,
+    }
+}
+
+/// Everyone with access to the board can comment
+#[post("")]
+async fn new_comment(
+    pool: Data<DbPool>,
+    user: User,
+    Path((board_id, list_id, card_id)): Path<(Uuid, Uuid, Uuid)>,
+    Json(data): Json<Comment>,
+) -> Result<HttpResponse, Error> {
+    if data.content.trim().is_empty() {
+        Err(HttpResponse::BadRequest().finish())?
+    }
+
+    let (board, card) = find_card(&pool, board_id, list_id, card_id)?;
+
+    if !board.allows(&pool, &user, Role::Viewer)? {
+        Err(HttpResponse::Unauthorized().finish())?
+    }
+
+    let comment = Comment::new(&card, &user, data.content);
+    comment.save(&pool)?;
+
+    Ok(HttpResponse::Created()
+        .header("Location", format!("/{}", comment.id))
+        .json(comment))
+}
+
+/// Returns a page of comments, oldest first. The total number of comments is sent in
+/// the `X-Total-Count` header.
+#[get("")]
+async fn get_comments(
+    pool: Data<DbPool>,
+    user: User,
+    Path((board_id, list_id, card_id)): Path<(Uuid, Uuid, Uuid)>,
+    query: Query<CommentsQuery>,
+) -> Result<HttpResponse, Error> {
+    let (board, card) = find_card(&pool, board_id, list_id, card_id)?;
+
+    if !board.allows(&pool, &user, Role::Viewer)? {
+        Err(HttpResponse::Unauthorized().finish())?
+    }
+
+    let per_page = query.per_page.unwrap_or(50).clamp(1, 100);
+    let offset = (query.page.unwrap_or(1).max(1) - 1) * per_page;
+    let html = matches!(query.render, Some(Render::Html));
+
+    let (comments, total) = Comment::page(&pool, &card, offset, per_page)?;
+    let comments = comments
+        .into_iter()
+        .map(|comment| comment.render(html))
+        .collect::<Vec<_>>();
+
+    Ok(HttpResponse::Ok()
+        .header("X-Total-Count", total.to_string())
+        .json(comments))
+}
+
+/// Only the author can edit a comment, as long as they can still see the board
+#[patch("/{comment_id}")]
+async fn patch_comment(
+    pool: Data<DbPool>,
+    user: User,
+    Path(path): Path<(Uuid, Uuid, Uuid, Uuid)>,
+    Json(data): Json<Comment>,
+) -> Result<HttpResponse, Error> {
+    if data.content.trim().is_empty() {
+        Err(HttpResponse::BadRequest().finish())?
+    }
+
+    let (_, comment) = find_comment(&pool, &user, path)?;
+
+    if comment.author != Some(user.id) {
+        Err(HttpResponse::Unauthorized().finish())?
+    }
+
+    let comment = comment.edit(&pool, data.content)?;
+
+    Ok(HttpResponse::Ok().json(comment))
+}
+
+/// Comments can be deleted by their author and board admins
+#[delete("/{comment_id}")]
+async fn delete_comment(
+    pool: Data<DbPool>,
+    user: User,
+    Path(path): Path<(Uuid, Uuid, Uuid, Uuid)>,
+) -> Result<HttpResponse, Error> {
+    let (board, comment) = find_comment(&pool, &user, path)?;
+
+    if comment.author != Some(user.id) && !board.allows(&pool, &user, Role::Admin)? {
+        Err(HttpResponse::Unauthorized().finish())?
+    }
+
+    comment.delete(&pool)?;
+
+    Ok(HttpResponse::NoContent().finish())
+}
@@ -0,0 +1,76 @@
+use actix_web::{
+    get,
+    http::header::{ContentDisposition, DispositionParam, DispositionType},
+    web::{Data, Path, Query, ServiceConfig},
+    Error, HttpResponse,
+};
+use serde::Deserialize;
+use uuid::Uuid;
+
+use crate::{models::Attachment, storage::BlobStore, thumbnails, AttachmentConfig, DbPool};
+
+pub fn config(cfg: &mut ServiceConfig) {
+    cfg.service(download_attachment);
+}
+
+#[derive(Deserialize)]
+struct Signature {
+    /// Serves the thumbnail of this size instead of the file
+    size: Option<u32>,
+    expires: i64,
+    signature: String,
+}
+
+/// Serves the contents of an attachment or one of its thumbnails. The signature from a
+/// short-lived URL handed out by the attachment endpoints replaces authentication, so
+/// links work in browsers.
+#[get("/{attachment_id}")]
+async fn download_attachment(
+    pool: Data<DbPool>,
+    config: Data<AttachmentConfig>,
+    store: Data<dyn BlobStore>,
+    Path(attachment_id): Path<Uuid>,
+    query: Query<Signature>,
+) -> Result<HttpResponse, Error> {
+    if !config.verify(&attachment_id, query.size, query.expires, &query.signature) {
+        Err(HttpResponse::Forbidden().finish())?
+    }
+
+    let attachment = match Attachment::find(&pool, attachment_id)? {
+        Some(attachment) => attachment,
+        None => Err(HttpResponse::NotFound().finish())?,
+    };
+
+    if let Some(size) = query.size {
+        if !attachment.thumbnails.contains(&(size as i32)) {
+            Err(HttpResponse::NotFound().finish())?
+        }
+
+        let key = Attachment::thumbnail_key(&attachment.storage_key, size);
+        let data = match store.get(&key).await? {
+            Some(data) => data,
+            None => Err(HttpResponse::NotFound().finish())?,
+        };
+
+        // Thumbnails are always PNGs encoded by us, so they can be shown inline
+        return Ok(HttpResponse::Ok()
+            .content_type(thumbnails::CONTENT_TYPE)
+            .header("X-Content-Type-Options", "nosniff")
+            .body(data));
+    }
+
+    let data = match store.get(&attachment.storage_key).await? {
+        Some(data) => data,
+        None => Err(HttpResponse::NotFound().finish())?,
+    };
+
+    // Uploaded files are never rendered inline, so they can't run scripts on our origin
+    Ok(HttpResponse::Ok()
+        .content_type(attachment.content_type)
+        .set(ContentDisposition {
+            disposition: DispositionType::Attachment,
+            parameters: vec![DispositionParam::Filename(attachment.name)],
+        })
+        .header("X-Content-Type-Options", "nosniff")
+        .body(data))
+}
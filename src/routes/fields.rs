@@ -0,0 +1,115 @@
+use actix_web::{
+    delete, get, patch, post,
+    web::{Data, Json, Path, ServiceConfig},
+    Error, HttpResponse,
+};
+use uuid::Uuid;
+
+use crate::{
+    errors::ServiceError,
+    models::{Board, CustomField, CustomFieldUpdate, Role, User},
+    DbPool,
+};
+
+pub fn config(cfg: &mut ServiceConfig) {
+    cfg.service(new_field)
+        .service(get_fields)
+        .service(patch_field)
+        .service(delete_field);
+}
+
+/// Finds a custom field of the board and checks that the user administers the board
+fn find_field(
+    pool: &Data<DbPool>,
+    user: &User,
+    (board_id, field_id): (Uuid, Uuid),
+) -> Result<CustomField, Error> {
+    let board = match Board::find(pool, board_id)? {
+        Some(board) => board,
+        None => Err(HttpResponse::NotFound().finish())?,
+    };
+
+    if !board.allows(pool, user, Role::Admin)? {
+        Err(HttpResponse::Unauthorized().finish())?
+    }
+
+    match CustomField::find(pool, field_id)? {
+        Some(field) if field.board == board.id => Ok(field),
+        _ => Err(HttpResponse::NotFound().finish())?,
+    }
+}
+
+#[post("")]
+async fn new_field(
+    pool: Data<DbPool>,
+    user: User,
+    Path(board_id): Path<Uuid>,
+    Json(data): Json<CustomField>,
+) -> Result<HttpResponse, Error> {
+    if data.name.trim().is_empty() {
+        Err(HttpResponse::BadRequest().finish())?
+    }
+
+    if let Some(board) = Board::find(&pool, board_id)? {
+        if !board.allows(&pool, &user, Role::Admin)? {
+            Err(HttpResponse::Unauthorized().finish())?
+        }
+
+        let field = CustomField::new(&board, data.name, data.kind, data.options)?;
+        field.save(&pool)?;
+
+        Ok(HttpResponse::Created()
+            .header("Location", format!("/{}", field.id))
+            .json(field))
+    } else {
+        Err(HttpResponse::NotFound().finish())?
+    }
+}
+
+#[get("")]
+async fn get_fields(pool: Data<DbPool>, Path(board_id): Path<Uuid>) -> Result<HttpResponse, Error> {
+    if let Some(board) = Board::find(&pool, board_id)? {
+        let fields = CustomField::for_board(&pool, &board)?;
+
+        Ok(HttpResponse::Ok().json(fields))
+    } else {
+        Err(HttpResponse::NotFound().finish())?
+    }
+}
+
+/// Removing options also removes them from the values of cards
+#[patch("/{field_id}")]
+async fn patch_field(
+    pool: Data<DbPool>,
+    user: User,
+    Path(path): Path<(Uuid, Uuid)>,
+    Json(mut data): Json<CustomFieldUpdate>,
+) -> Result<HttpResponse, Error> {
+    if data.is_empty() {
+        Err(ServiceError::EmptyUpdate)?
+    }
+    if matches!(&data.name, Some(name) if name.trim().is_empty()) {
+        Err(HttpResponse::BadRequest().finish())?
+    }
+
+    let field = find_field(&pool, &user, path)?;
+
+    data.id = field.id;
+    let field = field.update(&pool, data)?;
+
+    Ok(HttpResponse::Ok().json(field))
+}
+
+/// Deleting a field removes its values from all cards
+#[delete("/{field_id}")]
+async fn delete_field(
+    pool: Data<DbPool>,
+    user: User,
+    Path(path): Path<(Uuid, Uuid)>,
+) -> Result<HttpResponse, Error> {
+    let field = find_field(&pool, &user, path)?;
+
+    field.delete(&pool)?;
+
+    Ok(HttpResponse::NoContent().finish())
+}
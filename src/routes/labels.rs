@@ -0,0 +1,115 @@
+use actix_web::{
+    delete, get, patch, post,
+    web::{Data, Json, Path, ServiceConfig},
+    Error, HttpResponse,
+};
+use uuid::Uuid;
+
+use crate::{
+    errors::ServiceError,
+    models::{Board, Label, LabelUpdate, Role, User},
+    DbPool,
+};
+
+pub fn config(cfg: &mut ServiceConfig) {
+    cfg.service(new_label)
+        .service(get_labels)
+        .service(patch_label)
+        .service(delete_label);
+}
+
+/// Finds a label of the board and checks that the user can edit the board
+fn find_label(
+    pool: &Data<DbPool>,
+    user: &User,
+    (board_id, label_id): (Uuid, Uuid),
+) -> Result<Label, Error> {
+    let board = match Board::find(pool, board_id)? {
+        Some(board) => board,
+        None => Err(HttpResponse::NotFound().finish())?,
+    };
+
+    if !board.allows(pool, user, Role::Editor)? {
+        Err(HttpResponse::Unauthorized().finish())?
+    }
+
+    match Label::find(pool, label_id)? {
+        Some(label) if label.board == board.id => Ok(label),
+        _ => Err(HttpResponse::NotFound().finish())?,
+    }
+}
+
+#[post("")]
+async fn new_label(
+    pool: Data<DbPool>,
+    user: User,
+    Path(board_id): Path<Uuid>,
+    Json(data): Json<Label>,
+) -> Result<HttpResponse, Error> {
+    if data.name.trim().is_empty() {
+        Err(HttpResponse::BadRequest().finish())?
+    }
+
+    if let Some(board) = Board::find(&pool, board_id)? {
+        if !board.allows(&pool, &user, Role::Editor)? {
+            Err(HttpResponse::Unauthorized().finish())?
+        }
+
+        let label = Label::new(&board, data.name, &data.color, data.description)?;
+        label.save(&pool)?;
+
+        Ok(HttpResponse::Created()
+            .header("Location", format!("/{}", label.id))
+            .json(label))
+    } else {
+        Err(HttpResponse::NotFound().finish())?
+    }
+}
+
+#[get("")]
+async fn get_labels(pool: Data<DbPool>, Path(board_id): Path<Uuid>) -> Result<HttpResponse, Error> {
+    if let Some(board) = Board::find(&pool, board_id)? {
+        let labels = Label::for_board(&pool, &board)?;
+
+        Ok(HttpResponse::Ok().json(labels))
+    } else {
+        Err(HttpResponse::NotFound().finish())?
+    }
+}
+
+/// Renaming a label renames it on all cards
+#[patch("/{label_id}")]
+async fn patch_label(
+    pool: Data<DbPool>,
+    user: User,
+    Path(path): Path<(Uuid, Uuid)>,
+    Json(mut data): Json<LabelUpdate>,
+) -> Result<HttpResponse, Error> {
+    if data.is_empty() {
+        Err(ServiceError::EmptyUpdate)?
+    }
+    if matches!(&data.name, Some(name) if name.trim().is_empty()) {
+        Err(HttpResponse::BadRequest().finish())?
+    }
+
+    let label = find_label(&pool, &user, path)?;
+
+    data.id = label.id;
+    let label = label.update(&pool, data)?;
+
+    Ok(HttpResponse::Ok().json(label))
+}
+
+/// Deleting a label removes it from all cards
+#[delete("/{label_id}")]
+async fn delete_label(
+    pool: Data<DbPool>,
+    user: User,
+    Path(path): Path<(Uuid, Uuid)>,
+) -> Result<HttpResponse, Error> {
+    let label = find_label(&pool, &user, path)?;
+
+    label.delete(&pool)?;
+
+    Ok(HttpResponse::NoContent().finish())
+}
@@ -1,15 +1,20 @@
 use actix_web::{
     delete, get, patch, post,
-    web::{Data, Json, Path, ServiceConfig},
+    web::{Data, Json, Path, Query, ServiceConfig},
     Error, HttpResponse,
 };
 use diesel::prelude::*;
+use serde::Deserialize;
 use uuid::Uuid;
 
+use super::{attachments::copy_blobs, boards::ArchivedQuery};
 use crate::{
     errors::ServiceError,
     get_conn,
-    models::{Board, List, ListUpdate, User},
+    models::{Board, CloneOptions, List, ListUpdate, Role, User},
+    rank::Placement,
+    schema::lists,
+    storage::BlobStore,
     DbPool,
 };
 
@@ -18,6 +23,11 @@ pub fn config(cfg: &mut ServiceConfig) {
         .service(get_lists)
         .service(get_list)
         .service(patch_list)
+        .service(move_list)
+        .service(copy_list)
+        .service(clone_list)
+        .service(archive_list)
+        .service(restore_list)
         .service(delete_list);
 }
 
@@ -29,11 +39,13 @@ async fn new_list(
     Json(data): Json<List>,
 ) -> Result<HttpResponse, Error> {
     if let Some(board) = Board::find(&pool, board_id)? {
-        if board.owner != user.id {
+        if !board.allows(&pool, &user, Role::Editor)? {
             Err(HttpResponse::Unauthorized().finish())?
         }
 
-        let list = List::new(&board, data.name);
+        let mut list = List::new(&board, data.name);
+        list.wip_limit = data.wip_limit;
+        list.wip_soft = data.wip_soft;
         list.save(&pool)?;
 
         Ok(HttpResponse::Created()
@@ -45,13 +57,28 @@ async fn new_list(
 }
 
 #[get("")]
-async fn get_lists(pool: Data<DbPool>, Path(board_id): Path<Uuid>) -> Result<HttpResponse, Error> {
+async fn get_lists(
+    pool: Data<DbPool>,
+    Path(board_id): Path<Uuid>,
+    query: Query<ArchivedQuery>,
+) -> Result<HttpResponse, Error> {
     let conn = get_conn(&pool)?;
 
     if let Some(board) = Board::find(&pool, board_id)? {
-        let lists = List::belonging_to(&board)
+        let mut lists = List::belonging_to(&board)
+            .filter(lists::deleted_at.is_null())
+            .into_boxed();
+        lists = if query.archived {
+            lists.filter(lists::archived_at.is_not_null())
+        } else {
+            lists.filter(lists::archived_at.is_null())
+        };
+
+        let lists = lists
+            .order((lists::position, lists::id))
             .load::<List>(&conn)
             .map_err(|_| ServiceError::InternalServerError)?;
+        let lists = List::render(&pool, lists)?;
 
         Ok(HttpResponse::Ok().json(lists))
     } else {
@@ -66,7 +93,7 @@ async fn get_list(
 ) -> Result<HttpResponse, Error> {
     if let Some(_) = Board::find(&pool, board_id)? {
         if let Some(list) = List::find(&pool, list_id)? {
-            Ok(HttpResponse::Ok().json(list))
+            Ok(HttpResponse::Ok().json(list.render_one(&pool)?))
         } else {
             Err(HttpResponse::NotFound().finish())?
         }
@@ -87,7 +114,7 @@ async fn patch_list(
     }
 
     if let Some(board) = Board::find(&pool, board_id)? {
-        if user.id != board.owner {
+        if !board.allows(&pool, &user, Role::Editor)? {
             Err(HttpResponse::Unauthorized().finish())?
         }
 
@@ -109,6 +136,168 @@ async fn patch_list(
     }
 }
 
+#[derive(Deserialize)]
+struct MoveForm {
+    /// Target board, defaults to the current board of the list
+    board: Option<Uuid>,
+    before: Option<Uuid>,
+    after: Option<Uuid>,
+}
+
+/// Finds the board a list is moved or copied to and checks that the user can edit it
+fn find_target(
+    pool: &Data<DbPool>,
+    user: &User,
+    board: Board,
+    target_id: Option<Uuid>,
+) -> Result<Board, Error> {
+    let target = match target_id {
+        Some(target_id) if target_id != board.id => match Board::find(pool, target_id)? {
+            Some(target) => target,
+            None => Err(HttpResponse::NotFound().finish())?,
+        },
+        _ => board,
+    };
+
+    if !target.allows(pool, user, Role::Editor)? {
+        Err(HttpResponse::Unauthorized().finish())?
+    }
+
+    Ok(target)
+}
+
+/// Moves the list with its cards to another board, before or after one of its lists.
+/// Without a neighbour the list is put at the end of the board.
+#[post("/{list_id}/move")]
+async fn move_list(
+    pool: Data<DbPool>,
+    user: User,
+    Path((board_id, list_id)): Path<(Uuid, Uuid)>,
+    Json(data): Json<MoveForm>,
+) -> Result<HttpResponse, Error> {
+    let placement = Placement::from_neighbours(data.before, data.after)?;
+
+    if let Some(board) = Board::find(&pool, board_id)? {
+        if !board.allows(&pool, &user, Role::Editor)? {
+            Err(HttpResponse::Unauthorized().finish())?
+        }
+
+        if let Some(list) = List::find(&pool, list_id)? {
+            if board.id != list.board {
+                Err(HttpResponse::Unauthorized().finish())?
+            }
+
+            let target = find_target(&pool, &user, board, data.board)?;
+            let list = list.move_to(&pool, target.id, placement)?;
+
+            Ok(HttpResponse::Ok().json(list))
+        } else {
+            Err(HttpResponse::NotFound().finish())?
+        }
+    } else {
+        Err(HttpResponse::NotFound().finish())?
+    }
+}
+
+/// Copies the list with its cards to a board, before or after one of its lists
+#[post("/{list_id}/copy")]
+async fn copy_list(
+    pool: Data<DbPool>,
+    user: User,
+    Path((board_id, list_id)): Path<(Uuid, Uuid)>,
+    Json(data): Json<MoveForm>,
+) -> Result<HttpResponse, Error> {
+    let placement = Placement::from_neighbours(data.before, data.after)?;
+
+    if let Some(board) = Board::find(&pool, board_id)? {
+        // Copying leaves the source untouched, so reading it is enough
+        if !board.allows(&pool, &user, Role::Viewer)? {
+            Err(HttpResponse::Unauthorized().finish())?
+        }
+
+        if let Some(list) = List::find(&pool, list_id)? {
+            if board.id != list.board {
+                Err(HttpResponse::Unauthorized().finish())?
+            }
+
+            let target = find_target(&pool, &user, board, data.board)?;
+            let copy = list.copy_to(&pool, target.id, placement)?;
+
+            Ok(HttpResponse::Created()
+                .header(
+                    "Location",
+                    format!("/boards/{}/lists/{}", target.id, copy.id),
+                )
+                .json(copy))
+        } else {
+            Err(HttpResponse::NotFound().finish())?
+        }
+    } else {
+        Err(HttpResponse::NotFound().finish())?
+    }
+}
+
+/// Finds a list of the board and checks that the user can edit the board
+fn find_list(
+    pool: &Data<DbPool>,
+    user: &User,
+    (board_id, list_id): (Uuid, Uuid),
+) -> Result<List, Error> {
+    let board = match Board::find(pool, board_id)? {
+        Some(board) => board,
+        None => Err(HttpResponse::NotFound().finish())?,
+    };
+
+    if !board.allows(pool, user, Role::Editor)? {
+        Err(HttpResponse::Unauthorized().finish())?
+    }
+
+    match List::find(pool, list_id)? {
+        Some(list) if list.board == board.id => Ok(list),
+        _ => Err(HttpResponse::NotFound().finish())?,
+    }
+}
+
+/// Clones the list right after itself, in a single transaction
+#[post("/{list_id}/clone")]
+async fn clone_list(
+    pool: Data<DbPool>,
+    store: Data<dyn BlobStore>,
+    user: User,
+    Path(path): Path<(Uuid, Uuid)>,
+    Json(options): Json<CloneOptions>,
+) -> Result<HttpResponse, Error> {
+    let (copy, attachments) = find_list(&pool, &user, path)?.duplicate(&pool, options)?;
+    copy_blobs(&pool, &store, attachments).await;
+
+    Ok(HttpResponse::Created()
+        .header("Location", format!("/{}", copy.id))
+        .json(copy))
+}
+
+#[post("/{list_id}/archive")]
+async fn archive_list(
+    pool: Data<DbPool>,
+    user: User,
+    Path(path): Path<(Uuid, Uuid)>,
+) -> Result<HttpResponse, Error> {
+    let list = find_list(&pool, &user, path)?.archive(&pool)?;
+
+    Ok(HttpResponse::Ok().json(list))
+}
+
+#[post("/{list_id}/restore")]
+async fn restore_list(
+    pool: Data<DbPool>,
+    user: User,
+    Path(path): Path<(Uuid, Uuid)>,
+) -> Result<HttpResponse, Error> {
+    let list = find_list(&pool, &user, path)?.restore(&pool)?;
+
+    Ok(HttpResponse::Ok().json(list))
+}
+
+/// Moves an archived list to the trash of the user
 #[delete("/{list_id}")]
 async fn delete_list(
     pool: Data<DbPool>,
@@ -116,7 +305,7 @@ async fn delete_list(
     Path((board_id, list_id)): Path<(Uuid, Uuid)>,
 ) -> Result<HttpResponse, Error> {
     if let Some(board) = Board::find(&pool, board_id)? {
-        if user.id != board.owner {
+        if !board.allows(&pool, &user, Role::Editor)? {
             Err(HttpResponse::Unauthorized().finish())?
         }
 
@@ -125,7 +314,7 @@ async fn delete_list(
                 Err(HttpResponse::BadRequest().finish())?
             }
 
-            list.delete(&pool)?;
+            list.trash(&pool, &user)?;
         }
     }
 
@@ -0,0 +1,93 @@
+use actix_web::{
+    delete, get, put,
+    web::{Data, Json, Path, ServiceConfig},
+    Error, HttpResponse,
+};
+use diesel::prelude::*;
+use uuid::Uuid;
+
+use crate::{
+    errors::ServiceError,
+    get_conn,
+    models::{Board, Member, Role, User},
+    DbPool,
+};
+
+pub fn config(cfg: &mut ServiceConfig) {
+    cfg.service(get_members)
+        .service(put_member)
+        .service(delete_member);
+}
+
+#[get("")]
+async fn get_members(
+    pool: Data<DbPool>,
+    user: User,
+    Path(board_id): Path<Uuid>,
+) -> Result<HttpResponse, Error> {
+    let conn = get_conn(&pool)?;
+
+    if let Some(board) = Board::find(&pool, board_id)? {
+        if !board.allows(&pool, &user, Role::Viewer)? {
+            Err(HttpResponse::Unauthorized().finish())?
+        }
+
+        let members = Member::belonging_to(&board)
+            .load::<Member>(&conn)
+            .map_err(|_| ServiceError::InternalServerError)?;
+
+        Ok(HttpResponse::Ok().json(members))
+    } else {
+        Err(HttpResponse::NotFound().finish())?
+    }
+}
+
+/// Grants a role on the board to a user or a bot
+#[put("/{user_id}")]
+async fn put_member(
+    pool: Data<DbPool>,
+    user: User,
+    Path((board_id, user_id)): Path<(Uuid, Uuid)>,
+    Json(data): Json<Member>,
+) -> Result<HttpResponse, Error> {
+    if let Some(board) = Board::find(&pool, board_id)? {
+        if !board.allows(&pool, &user, Role::Admin)? {
+            Err(HttpResponse::Unauthorized().finish())?
+        }
+
+        if board.owner == user_id {
+            Err(HttpResponse::BadRequest().finish())?
+        }
+
+        if User::find(&pool, user_id)?.is_none() {
+            Err(HttpResponse::NotFound().finish())?
+        }
+
+        let member = Member::new(&board, user_id, data.role);
+        member.save(&pool)?;
+
+        Ok(HttpResponse::Ok().json(member))
+    } else {
+        Err(HttpResponse::NotFound().finish())?
+    }
+}
+
+#[delete("/{user_id}")]
+async fn delete_member(
+    pool: Data<DbPool>,
+    user: User,
+    Path((board_id, user_id)): Path<(Uuid, Uuid)>,
+) -> Result<HttpResponse, Error> {
+    if let Some(board) = Board::find(&pool, board_id)? {
+        // Members are allowed to leave a board on their own
+        if user.id != user_id && !board.allows(&pool, &user, Role::Admin)? {
+            Err(HttpResponse::Unauthorized().finish())?
+        }
+
+        if let Some(member) = Member::find(&pool, board_id, user_id)? {
+            member.delete(&pool)?;
+        }
+    }
+
+    Ok(HttpResponse::NoContent().finish())
+}
@@ -1,21 +1,60 @@
 use actix_web::web::{scope, ServiceConfig};
 
+mod attachments;
+mod audit;
 mod auth;
 mod boards;
+mod bots;
 mod cards;
+mod checklists;
+mod comments;
+mod downloads;
+mod fields;
+mod labels;
 mod lists;
+mod members;
+mod notifications;
+mod reminders;
+mod scim;
+mod shared;
+mod shares;
+mod templates;
+mod trash;
 mod users;
 
 pub fn config(cfg: &mut ServiceConfig) {
     cfg.service(scope("/auth").configure(auth::config))
+        .service(scope("/audit").configure(audit::config))
         .service(scope("/users").configure(users::config))
+        .service(scope("/bots").configure(bots::config))
+        .service(scope("/notifications").configure(notifications::config))
+        .service(scope("/trash").configure(trash::config))
+        .service(scope("/templates").configure(templates::config))
         .service(
             scope("/boards")
+                .service(scope("/{board_id}/shares").configure(shares::config))
+                .service(scope("/{board_id}/members").configure(members::config))
+                .service(scope("/{board_id}/labels").configure(labels::config))
+                .service(scope("/{board_id}/fields").configure(fields::config))
                 .service(
                     scope("/{board_id}/lists")
-                        .service(scope("/{list_id}/cards").configure(cards::config))
+                        .service(
+                            scope("/{list_id}/cards")
+                                .service(
+                                    scope("/{card_id}/checklists").configure(checklists::config),
+                                )
+                                .service(
+                                    scope("/{card_id}/attachments").configure(attachments::config),
+                                )
+                                .service(scope("/{card_id}/comments").configure(comments::config))
+                                .service(scope("/{card_id}/reminder").configure(reminders::config))
+                                .configure(cards::config),
+                        )
                         .configure(lists::config),
                 )
                 .configure(boards::config),
-        );
+        )
+        .service(scope("/attachments").configure(downloads::config))
+        .service(scope("/shared").configure(shared::config))
+        .service(scope("/scim/v2").configure(scim::config));
 }
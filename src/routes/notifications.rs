@@ -0,0 +1,66 @@
+use actix_web::{
+    delete, get, post,
+    web::{Data, Path, Query, ServiceConfig},
+    Error, HttpResponse,
+};
+use serde::Deserialize;
+use uuid::Uuid;
+
+use crate::{
+    models::{Notification, User},
+    DbPool,
+};
+
+pub fn config(cfg: &mut ServiceConfig) {
+    cfg.service(get_notifications)
+        .service(read_notification)
+        .service(delete_notification);
+}
+
+#[derive(Deserialize)]
+struct NotificationsQuery {
+    #[serde(default)]
+    unread: bool,
+}
+
+/// Returns the notifications of the current user, newest first
+#[get("")]
+async fn get_notifications(
+    pool: Data<DbPool>,
+    user: User,
+    query: Query<NotificationsQuery>,
+) -> Result<HttpResponse, Error> {
+    let notifications = Notification::for_user(&pool, &user, query.unread)?;
+
+    Ok(HttpResponse::Ok().json(notifications))
+}
+
+/// Finds a notification of `user`
+fn find_own(pool: &Data<DbPool>, user: &User, id: Uuid) -> Result<Notification, Error> {
+    match Notification::find(pool, id)? {
+        Some(notification) if notification.account == user.id => Ok(notification),
+        _ => Err(HttpResponse::NotFound().finish())?,
+    }
+}
+
+#[post("/{notification_id}/read")]
+async fn read_notification(
+    pool: Data<DbPool>,
+    user: User,
+    Path(notification_id): Path<Uuid>,
+) -> Result<HttpResponse, Error> {
+    let notification = find_own(&pool, &user, notification_id)?.mark_read(&pool)?;
+
+    Ok(HttpResponse::Ok().json(notification))
+}
+
+#[delete("/{notification_id}")]
+async fn delete_notification(
+    pool: Data<DbPool>,
+    user: User,
+    Path(notification_id): Path<Uuid>,
+) -> Result<HttpResponse, Error> {
+    find_own(&pool, &user, notification_id)?.delete(&pool)?;
+
+    Ok(HttpResponse::NoContent().finish())
+}
@@ -0,0 +1,76 @@
+use actix_web::{
+    delete, get, put,
+    web::{Data, Json, Path, ServiceConfig},
+    Error, HttpResponse,
+};
+use uuid::Uuid;
+
+use super::cards::find_card;
+use crate::{
+    models::{Reminder, Role, User},
+    DbPool,
+};
+
+pub fn config(cfg: &mut ServiceConfig) {
+    cfg.service(get_reminder)
+        .service(put_reminder)
+        .service(delete_reminder);
+}
+
+/// Returns the reminder of the current user
+#[get("")]
+async fn get_reminder(
+    pool: Data<DbPool>,
+    user: User,
+    Path((board_id, list_id, card_id)): Path<(Uuid, Uuid, Uuid)>,
+) -> Result<HttpResponse, Error> {
+    let (board, card) = find_card(&pool, board_id, list_id, card_id)?;
+
+    if !board.allows(&pool, &user, Role::Viewer)? {
+        Err(HttpResponse::Unauthorized().finish())?
+    }
+
+    match Reminder::find(&pool, card.id, user.id)? {
+        Some(reminder) => Ok(HttpResponse::Ok().json(reminder)),
+        None => Err(HttpResponse::NotFound().finish())?,
+    }
+}
+
+/// Sets the reminder of the current user, replacing an existing one
+#[put("")]
+async fn put_reminder(
+    pool: Data<DbPool>,
+    user: User,
+    Path((board_id, list_id, card_id)): Path<(Uuid, Uuid, Uuid)>,
+    Json(data): Json<Reminder>,
+) -> Result<HttpResponse, Error> {
+    if data.minutes_before < 0 {
+        Err(HttpResponse::BadRequest().finish())?
+    }
+
+    let (board, card) = find_card(&pool, board_id, list_id, card_id)?;
+
+    if !board.allows(&pool, &user, Role::Viewer)? {
+        Err(HttpResponse::Unauthorized().finish())?
+    }
+
+    let reminder = Reminder::new(&card, &user, data.minutes_before);
+    reminder.save(&pool)?;
+
+    Ok(HttpResponse::Ok().json(reminder))
+}
+
+#[delete("")]
+async fn delete_reminder(
+    pool: Data<DbPool>,
+    user: User,
+    Path((board_id, list_id, card_id)): Path<(Uuid, Uuid, Uuid)>,
+) -> Result<HttpResponse, Error> {
+    let (_, card) = find_card(&pool, board_id, list_id, card_id)?;
+
+    if let Some(reminder) = Reminder::find(&pool, card.id, user.id)? {
+        reminder.delete(&pool)?;
+    }
+
+    Ok(HttpResponse::NoContent().finish())
+}
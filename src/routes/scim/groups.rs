@@ -0,0 +1,315 @@
+use actix_web::{
+    delete, get,
+    http::StatusCode,
+    patch, post, put,
+    web::{Data, Json, Path, Query, ServiceConfig},
+    Error, HttpResponse,
+};
+use diesel::{pg::Pg, prelude::*};
+use serde::Deserialize;
+use serde_json::{json, Value};
+use std::str::FromStr;
+use uuid::Uuid;
+
+use super::{
+    error, invalid_value, list_response, not_found, string_value, ListQuery, PatchOp, PatchRequest,
+    ScimClient, GROUP_SCHEMA, ORIGIN,
+};
+use crate::{
+    errors::ServiceError,
+    get_conn,
+    models::{Group, GroupUpdate, MemberChange, User},
+    schema::{groups, users},
+    DbPool,
+};
+
+pub fn config(cfg: &mut ServiceConfig) {
+    cfg.service(get_groups)
+        .service(get_group)
+        .service(new_group)
+        .service(replace_group)
+        .service(patch_group)
+        .service(delete_group);
+}
+
+#[derive(Deserialize)]
+struct MemberRef {
+    value: Uuid,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct ScimGroup {
+    display_name: String,
+    external_id: Option<String>,
+    #[serde(default)]
+    members: Vec<MemberRef>,
+}
+
+fn to_resource(pool: &Data<DbPool>, group: &Group) -> Result<Value, ServiceError> {
+    let conn = get_conn(pool)?;
+
+    let members = users::table
+        .filter(users::id.eq_any(group.members(pool)?))
+        .load::<User>(&conn)
+        .map_err(|_| ServiceError::InternalServerError)?
+        .into_iter()
+        .map(|user| {
+            json!({
+                "value": user.id,
+                "display": user.name.or(user.mail),
+                "$ref": format!("/scim/v2/Users/{}", user.id),
+            })
+        })
+        .collect::<Vec<_>>();
+
+    Ok(json!({
+        "schemas": [GROUP_SCHEMA],
+        "id": group.id,
+        "externalId": group.external_id,
+        "displayName": group.name,
+        "members": members,
+        "meta": {
+            "resourceType": "Group",
+            "location": format!("/scim/v2/Groups/{}", group.id),
+        },
+    }))
+}
+
+fn respond(pool: &Data<DbPool>, status: StatusCode, group: &Group) -> Result<HttpResponse, Error> {
+    Ok(HttpResponse::build(status)
+        .content_type("application/scim+json")
+        .json(to_resource(pool, group)?))
+}
+
+/// Only groups provisioned through SCIM are visible to identity providers
+fn find_group(pool: &Data<DbPool>, group_id: Uuid) -> Result<Group, HttpResponse> {
+    match Group::find(pool, group_id) {
+        Ok(Some(group)) if group.origin == ORIGIN => Ok(group),
+        Ok(_) => Err(not_found()),
+        Err(_) => Err(HttpResponse::InternalServerError().finish()),
+    }
+}
+
+/// Drops ids that don't belong to any user visible to the identity provider
+fn known_users(pool: &Data<DbPool>, members: Vec<MemberRef>) -> Result<Vec<Uuid>, ServiceError> {
+    let conn = get_conn(pool)?;
+
+    let ids = members.into_iter().map(|m| m.value).collect::<Vec<_>>();
+
+    users::table
+        .filter(users::id.eq_any(ids))
+        .filter(users::bot.eq(false))
+        .select(users::id)
+        .load::<Uuid>(&conn)
+        .map_err(|_| ServiceError::InternalServerError)
+}
+
+/// Reads member ids from a list of `{"value": "<id>"}` objects
+fn member_ids(pool: &Data<DbPool>, value: &Value) -> Result<Vec<Uuid>, Error> {
+    let members = serde_json::from_value::<Vec<MemberRef>>(value.clone())
+        .map_err(|_| invalid_value("Expected a list of members"))?;
+
+    Ok(known_users(pool, members)?)
+}
+
+/// Parses paths like `members[value eq "<id>"]` used to remove a single member
+fn member_filter(path: &str) -> Option<Uuid> {
+    let filter = path.strip_prefix("members[value eq ")?.strip_suffix(']')?;
+    let id = serde_json::from_str::<String>(filter).ok()?;
+
+    Uuid::from_str(&id).ok()
+}
+
+fn filtered(filter: &Option<(String, String)>) -> Result<groups::BoxedQuery<'static, Pg>, Error> {
+    let query = groups::table.filter(groups::origin.eq(ORIGIN)).into_boxed();
+
+    match filter {
+        Some((attribute, value)) => match attribute.as_str() {
+            "displayname" => Ok(query.filter(groups::name.eq(value.clone()))),
+            "externalid" => Ok(query.filter(groups::external_id.eq(value.clone()))),
+            _ => Err(error(
+                StatusCode::BAD_REQUEST,
+                Some("invalidFilter"),
+                "Unsupported filter attribute",
+            ))?,
+        },
+        None => Ok(query),
+    }
+}
+
+#[get("")]
+async fn get_groups(
+    _client: ScimClient,
+    pool: Data<DbPool>,
+    query: Query<ListQuery>,
+) -> Result<HttpResponse, Error> {
+    let conn = get_conn(&pool)?;
+
+    let filter = query.filter()?;
+    let (offset, limit) = query.page();
+
+    let total = filtered(&filter)?
+        .count()
+        .get_result::<i64>(&conn)
+        .map_err(|_| ServiceError::InternalServerError)?;
+
+    let groups = filtered(&filter)?
+        .order(groups::name)
+        .offset(offset)
+        .limit(limit)
+        .load::<Group>(&conn)
+        .map_err(|_| ServiceError::InternalServerError)?;
+
+    let resources = groups
+        .iter()
+        .map(|group| to_resource(&pool, group))
+        .collect::<Result<Vec<_>, _>>()?;
+
+    Ok(list_response(resources, total, offset))
+}
+
+#[get("/{group_id}")]
+async fn get_group(
+    _client: ScimClient,
+    pool: Data<DbPool>,
+    Path(group_id): Path<Uuid>,
+) -> Result<HttpResponse, Error> {
+    let group = find_group(&pool, group_id)?;
+
+    respond(&pool, StatusCode::OK, &group)
+}
+
+#[post("")]
+async fn new_group(
+    _client: ScimClient,
+    pool: Data<DbPool>,
+    Json(data): Json<ScimGroup>,
+) -> Result<HttpResponse, Error> {
+    let conn = get_conn(&pool)?;
+
+    let count = groups::table
+        .filter(groups::origin.eq(ORIGIN))
+        .filter(groups::name.eq(&data.display_name))
+        .count()
+        .get_result::<i64>(&conn)
+        .map_err(|_| ServiceError::InternalServerError)?;
+
+    if count > 0 {
+        Err(error(
+            StatusCode::CONFLICT,
+            Some("uniqueness"),
+            "Group with this displayName already exists",
+        ))?
+    }
+
+    let group = Group::new(data.display_name, ORIGIN, data.external_id);
+    group.save(&pool)?;
+
+    let members = known_users(&pool, data.members)?;
+    group.add_members(&pool, &members)?;
+
+    respond(&pool, StatusCode::CREATED, &group)
+}
+
+#[put("/{group_id}")]
+async fn replace_group(
+    _client: ScimClient,
+    pool: Data<DbPool>,
+    Path(group_id): Path<Uuid>,
+    Json(data): Json<ScimGroup>,
+) -> Result<HttpResponse, Error> {
+    let group = find_group(&pool, group_id)?;
+
+    let update = GroupUpdate {
+        id: group.id,
+        name: data.display_name,
+        external_id: data.external_id,
+    };
+    let group = group.update(&pool, update)?;
+
+    let members = known_users(&pool, data.members)?;
+    group.set_members(&pool, &members)?;
+
+    respond(&pool, StatusCode::OK, &group)
+}
+
+#[patch("/{group_id}")]
+async fn patch_group(
+    _client: ScimClient,
+    pool: Data<DbPool>,
+    Path(group_id): Path<Uuid>,
+    Json(data): Json<PatchRequest>,
+) -> Result<HttpResponse, Error> {
+    let group = find_group(&pool, group_id)?;
+
+    let mut update = GroupUpdate {
+        id: group.id,
+        name: group.name.clone(),
+        external_id: group.external_id.clone(),
+    };
+
+    // Operations are validated first and applied together, so a failing one leaves the
+    // group untouched
+    let mut changes = Vec::new();
+    for operation in &data.operations {
+        let op = operation.op()?;
+
+        // Single members are removed with a filter in the path and no value
+        if let Some(member) = operation.path.as_deref().and_then(member_filter) {
+            if op != PatchOp::Remove {
+                Err(invalid_value("Member filters can only be used for removal"))?
+            }
+
+            changes.push(MemberChange::Remove(vec![member]));
+            continue;
+        }
+
+        for (attribute, value) in operation.attributes()? {
+            match (attribute.as_str(), &op) {
+                ("members", PatchOp::Add) => {
+                    changes.push(MemberChange::Add(member_ids(&pool, &value)?));
+                }
+                ("members", PatchOp::Replace) => {
+                    changes.push(MemberChange::Set(member_ids(&pool, &value)?));
+                }
+                ("members", PatchOp::Remove) if value.is_null() => {
+                    changes.push(MemberChange::Set(Vec::new()));
+                }
+                ("members", PatchOp::Remove) => {
+                    changes.push(MemberChange::Remove(member_ids(&pool, &value)?));
+                }
+                ("displayname", PatchOp::Remove) => Err(invalid_value("displayName is required"))?,
+                ("displayname", _) => {
+                    update.name = string_value(&value)?
+                        .ok_or_else(|| invalid_value("displayName is required"))?;
+                }
+                ("externalid", PatchOp::Remove) => update.external_id = None,
+                ("externalid", _) => update.external_id = string_value(&value)?,
+                _ => Err(error(
+                    StatusCode::BAD_REQUEST,
+                    Some("invalidPath"),
+                    "Unsupported attribute",
+                ))?,
+            }
+        }
+    }
+
+    let group = group.patch(&pool, &changes, update)?;
+
+    respond(&pool, StatusCode::OK, &group)
+}
+
+#[delete("/{group_id}")]
+async fn delete_group(
+    _client: ScimClient,
+    pool: Data<DbPool>,
+    Path(group_id): Path<Uuid>,
+) -> Result<HttpResponse, Error> {
+    let group = find_group(&pool, group_id)?;
+
+    // Memberships are removed along with the group
+    group.delete(&pool)?;
+
+    Ok(HttpResponse::NoContent().finish())
+}
@@ -0,0 +1,218 @@
+//! SCIM 2.0 provisioning endpoints (RFC 7643, RFC 7644) used by identity providers
+
+use actix_web::{
+    dev::Payload,
+    http::StatusCode,
+    web::{scope, Data, ServiceConfig},
+    FromRequest, HttpRequest, HttpResponse,
+};
+use serde::Deserialize;
+use serde_json::{json, Value};
+use std::future::{ready, Ready};
+
+use crate::{errors::ServiceError, ScimConfig};
+
+mod groups;
+mod users;
+
+const USER_SCHEMA: &str = "urn:ietf:params:scim:schemas:core:2.0:User";
+const GROUP_SCHEMA: &str = "urn:ietf:params:scim:schemas:core:2.0:Group";
+const LIST_SCHEMA: &str = "urn:ietf:params:scim:api:messages:2.0:ListResponse";
+const ERROR_SCHEMA: &str = "urn:ietf:params:scim:api:messages:2.0:Error";
+
+/// Origin of groups provisioned through SCIM
+const ORIGIN: &str = "scim";
+
+pub fn config(cfg: &mut ServiceConfig) {
+    cfg.service(scope("/Users").configure(users::config))
+        .service(scope("/Groups").configure(groups::config));
+}
+
+/// Extractor guarding the SCIM endpoints with the token from [`ScimConfig`]
+pub struct ScimClient;
+
+impl FromRequest for ScimClient {
+    type Config = ();
+    type Error = ServiceError;
+    type Future = Ready<Result<Self, Self::Error>>;
+
+    fn from_request(req: &HttpRequest, _pld: &mut Payload) -> Self::Future {
+        let config = req.app_data::<Data<ScimConfig>>().unwrap();
+
+        let header = match req.headers().get("authorization") {
+            Some(header) => header.to_str().unwrap_or_default(),
+            None => return ready(Err(ServiceError::MissingToken)),
+        };
+        let token = header.trim_start_matches("Bearer ");
+
+        match &config.token {
+            Some(expected) if constant_time_eq(expected.as_bytes(), token.as_bytes()) => {
+                ready(Ok(ScimClient))
+            }
+            _ => ready(Err(ServiceError::InvalidToken)),
+        }
+    }
+}
+
+fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    a.len() == b.len() && a.iter().zip(b).fold(0, |acc, (x, y)| acc | (x ^ y)) == 0
+}
+
+/// Builds a SCIM error response
+fn error(status: StatusCode, scim_type: Option<&str>, detail: &str) -> HttpResponse {
+    let mut body = json!({
+        "schemas": [ERROR_SCHEMA],
+        "status": status.as_u16().to_string(),
+        "detail": detail,
+    });
+    if let Some(scim_type) = scim_type {
+        body["scimType"] = json!(scim_type);
+    }
+
+    HttpResponse::build(status)
+        .content_type("application/scim+json")
+        .json(body)
+}
+
+fn not_found() -> HttpResponse {
+    error(StatusCode::NOT_FOUND, None, "Resource not found")
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct ListQuery {
+    filter: Option<String>,
+    start_index: Option<i64>,
+    count: Option<i64>,
+}
+
+impl ListQuery {
+    /// Returns the (offset, limit) of the requested page. SCIM indexes are 1-based.
+    fn page(&self) -> (i64, i64) {
+        let offset = self.start_index.unwrap_or(1).max(1) - 1;
+        let limit = self.count.unwrap_or(100).clamp(0, 1000);
+
+        (offset, limit)
+    }
+
+    /// Parses the filter, only `<attribute> eq "<value>"` expressions are supported
+    fn filter(&self) -> Result<Option<(String, String)>, HttpResponse> {
+        let filter = match &self.filter {
+            Some(filter) => filter.trim(),
+            None => return Ok(None),
+        };
+
+        let invalid = || {
+            error(
+                StatusCode::BAD_REQUEST,
+                Some("invalidFilter"),
+                "Only `<attribute> eq \"<value>\"` filters are supported",
+            )
+        };
+
+        let mut parts = filter.splitn(3, ' ');
+        let (attribute, op, value) = match (parts.next(), parts.next(), parts.next()) {
+            (Some(attribute), Some(op), Some(value)) => (attribute, op, value),
+            _ => return Err(invalid()),
+        };
+
+        if !op.eq_ignore_ascii_case("eq") {
+            return Err(invalid());
+        }
+
+        let value = serde_json::from_str::<String>(value).map_err(|_| invalid())?;
+
+        Ok(Some((attribute.to_lowercase(), value)))
+    }
+}
+
+fn list_response(resources: Vec<Value>, total: i64, offset: i64) -> HttpResponse {
+    HttpResponse::Ok()
+        .content_type("application/scim+json")
+        .json(json!({
+            "schemas": [LIST_SCHEMA],
+            "totalResults": total,
+            "startIndex": offset + 1,
+            "itemsPerPage": resources.len(),
+            "Resources": resources,
+        }))
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "PascalCase")]
+struct PatchRequest {
+    operations: Vec<PatchOperation>,
+}
+
+#[derive(Deserialize)]
+struct PatchOperation {
+    op: String,
+    path: Option<String>,
+    value: Option<Value>,
+}
+
+#[derive(Debug, PartialEq)]
+enum PatchOp {
+    Add,
+    Remove,
+    Replace,
+}
+
+impl PatchOperation {
+    fn op(&self) -> Result<PatchOp, HttpResponse> {
+        // Some identity providers capitalize operation names
+        match self.op.to_lowercase().as_str() {
+            "add" => Ok(PatchOp::Add),
+            "remove" => Ok(PatchOp::Remove),
+            "replace" => Ok(PatchOp::Replace),
+            _ => Err(error(
+                StatusCode::BAD_REQUEST,
+                Some("invalidSyntax"),
+                "Unknown patch operation",
+            )),
+        }
+    }
+
+    /// Flattens the operation into (attribute, value) pairs. Operations without a path
+    /// carry an object with attributes as keys.
+    fn attributes(&self) -> Result<Vec<(String, Value)>, HttpResponse> {
+        match (&self.path, &self.value) {
+            (Some(path), value) => Ok(vec![(
+                path.to_lowercase(),
+                value.clone().unwrap_or(Value::Null),
+            )]),
+            (None, Some(Value::Object(map))) => Ok(map
+                .iter()
+                .map(|(k, v)| (k.to_lowercase(), v.clone()))
+                .collect()),
+            (None, _) => Err(error(
+                StatusCode::BAD_REQUEST,
+                Some("noTarget"),
+                "Patch operation has no path",
+            )),
+        }
+    }
+}
+
+fn invalid_value(detail: &str) -> HttpResponse {
+    error(StatusCode::BAD_REQUEST, Some("invalidValue"), detail)
+}
+
+/// Reads a string attribute, `null` clears it
+fn string_value(value: &Value) -> Result<Option<String>, HttpResponse> {
+    match value {
+        Value::String(s) => Ok(Some(s.clone())),
+        Value::Null => Ok(None),
+        _ => Err(invalid_value("Expected a string")),
+    }
+}
+
+/// Reads a boolean attribute. Some identity providers send booleans as strings.
+fn bool_value(value: &Value) -> Result<bool, HttpResponse> {
+    match value {
+        Value::Bool(b) => Ok(*b),
+        Value::String(s) if s.eq_ignore_ascii_case("true") => Ok(true),
+        Value::String(s) if s.eq_ignore_ascii_case("false") => Ok(false),
+        _ => Err(invalid_value("Expected a boolean")),
+    }
+}
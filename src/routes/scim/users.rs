@@ -0,0 +1,267 @@
+use actix_web::{
+    delete, get,
+    http::StatusCode,
+    patch, post, put,
+    web::{Data, Json, Path, Query, ServiceConfig},
+    Error, HttpResponse,
+};
+use diesel::{pg::Pg, prelude::*};
+use serde::Deserialize;
+use serde_json::{json, Value};
+use uuid::Uuid;
+
+use super::{
+    bool_value, error, invalid_value, list_response, not_found, string_value, ListQuery, PatchOp,
+    PatchRequest, ScimClient, USER_SCHEMA,
+};
+use crate::{
+    errors::ServiceError,
+    get_conn,
+    models::{User, UserProfile},
+    schema::users,
+    DbPool,
+};
+
+pub fn config(cfg: &mut ServiceConfig) {
+    cfg.service(get_users)
+        .service(get_user)
+        .service(new_user)
+        .service(replace_user)
+        .service(patch_user)
+        .service(delete_user);
+}
+
+#[derive(Deserialize)]
+struct Name {
+    formatted: Option<String>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct ScimUser {
+    user_name: String,
+    external_id: Option<String>,
+    display_name: Option<String>,
+    name: Option<Name>,
+    active: Option<bool>,
+}
+
+impl ScimUser {
+    fn into_profile(self) -> UserProfile {
+        UserProfile {
+            mail: self.user_name,
+            name: self.display_name.or(self.name.and_then(|n| n.formatted)),
+            active: self.active.unwrap_or(true),
+            external_id: self.external_id,
+        }
+    }
+}
+
+fn to_resource(user: &User) -> Value {
+    json!({
+        "schemas": [USER_SCHEMA],
+        "id": user.id,
+        "externalId": user.external_id,
+        "userName": user.mail,
+        "displayName": user.name,
+        "name": { "formatted": user.name },
+        "emails": [{ "value": user.mail, "primary": true }],
+        "active": user.active,
+        "meta": {
+            "resourceType": "User",
+            "location": format!("/scim/v2/Users/{}", user.id),
+        },
+    })
+}
+
+fn respond(status: StatusCode, user: &User) -> HttpResponse {
+    HttpResponse::build(status)
+        .content_type("application/scim+json")
+        .json(to_resource(user))
+}
+
+/// Bots are managed by their owners, so they are invisible to identity providers
+fn find_user(pool: &Data<DbPool>, user_id: Uuid) -> Result<User, HttpResponse> {
+    match User::find(pool, user_id) {
+        Ok(Some(user)) if !user.bot => Ok(user),
+        Ok(_) => Err(not_found()),
+        Err(_) => Err(HttpResponse::InternalServerError().finish()),
+    }
+}
+
+/// Fails if another user already uses the mail address
+fn ensure_unique(pool: &Data<DbPool>, mail: &str, user_id: Option<Uuid>) -> Result<(), Error> {
+    if let Some(existing) = User::find_by_mail(pool, mail)? {
+        if Some(existing.id) != user_id {
+            Err(error(
+                StatusCode::CONFLICT,
+                Some("uniqueness"),
+                "User with this userName already exists",
+            ))?
+        }
+    }
+
+    Ok(())
+}
+
+/// Builds a query for non-bot users matching a parsed filter
+fn filtered(filter: &Option<(String, String)>) -> Result<users::BoxedQuery<'static, Pg>, Error> {
+    let query = users::table.filter(users::bot.eq(false)).into_boxed();
+
+    match filter {
+        Some((attribute, value)) => match attribute.as_str() {
+            "username" => Ok(query.filter(users::mail.eq(value.clone()))),
+            "externalid" => Ok(query.filter(users::external_id.eq(value.clone()))),
+            _ => Err(error(
+                StatusCode::BAD_REQUEST,
+                Some("invalidFilter"),
+                "Unsupported filter attribute",
+            ))?,
+        },
+        None => Ok(query),
+    }
+}
+
+#[get("")]
+async fn get_users(
+    _client: ScimClient,
+    pool: Data<DbPool>,
+    query: Query<ListQuery>,
+) -> Result<HttpResponse, Error> {
+    let conn = get_conn(&pool)?;
+
+    let filter = query.filter()?;
+    let (offset, limit) = query.page();
+
+    let total = filtered(&filter)?
+        .count()
+        .get_result::<i64>(&conn)
+        .map_err(|_| ServiceError::InternalServerError)?;
+
+    let users = filtered(&filter)?
+        .order(users::mail)
+        .offset(offset)
+        .limit(limit)
+        .load::<User>(&conn)
+        .map_err(|_| ServiceError::InternalServerError)?;
+
+    let resources = users.iter().map(to_resource).collect();
+
+    Ok(list_response(resources, total, offset))
+}
+
+#[get("/{user_id}")]
+async fn get_user(
+    _client: ScimClient,
+    pool: Data<DbPool>,
+    Path(user_id): Path<Uuid>,
+) -> Result<HttpResponse, Error> {
+    let user = find_user(&pool, user_id)?;
+
+    Ok(respond(StatusCode::OK, &user))
+}
+
+#[post("")]
+async fn new_user(
+    _client: ScimClient,
+    pool: Data<DbPool>,
+    Json(data): Json<ScimUser>,
+) -> Result<HttpResponse, Error> {
+    ensure_unique(&pool, &data.user_name, None)?;
+
+    let profile = data.into_profile();
+
+    let mut user = User::new_external(profile.mail, profile.name);
+    user.active = profile.active;
+    user.external_id = profile.external_id;
+    user.save(&pool)?;
+
+    Ok(respond(StatusCode::CREATED, &user))
+}
+
+#[put("/{user_id}")]
+async fn replace_user(
+    _client: ScimClient,
+    pool: Data<DbPool>,
+    Path(user_id): Path<Uuid>,
+    Json(data): Json<ScimUser>,
+) -> Result<HttpResponse, Error> {
+    let user = find_user(&pool, user_id)?;
+
+    ensure_unique(&pool, &data.user_name, Some(user.id))?;
+
+    let user = user.update_profile(&pool, data.into_profile())?;
+
+    Ok(respond(StatusCode::OK, &user))
+}
+
+#[patch("/{user_id}")]
+async fn patch_user(
+    _client: ScimClient,
+    pool: Data<DbPool>,
+    Path(user_id): Path<Uuid>,
+    Json(data): Json<PatchRequest>,
+) -> Result<HttpResponse, Error> {
+    let user = find_user(&pool, user_id)?;
+
+    let mut profile = UserProfile {
+        mail: user.mail.clone().unwrap_or_default(),
+        name: user.name.clone(),
+        active: user.active,
+        external_id: user.external_id.clone(),
+    };
+
+    for operation in &data.operations {
+        let op = operation.op()?;
+
+        for (attribute, value) in operation.attributes()? {
+            let value = if op == PatchOp::Remove {
+                Value::Null
+            } else {
+                value
+            };
+
+            match attribute.as_str() {
+                "username" => {
+                    profile.mail = string_value(&value)?
+                        .ok_or_else(|| invalid_value("userName is required"))?
+                }
+                "displayname" | "name.formatted" => profile.name = string_value(&value)?,
+                "name" => profile.name = string_value(&value["formatted"])?,
+                "externalid" => profile.external_id = string_value(&value)?,
+                "active" => profile.active = bool_value(&value)?,
+                _ => Err(error(
+                    StatusCode::BAD_REQUEST,
+                    Some("invalidPath"),
+                    "Unsupported attribute",
+                ))?,
+            }
+        }
+    }
+
+    ensure_unique(&pool, &profile.mail, Some(user.id))?;
+
+    let user = user.update_profile(&pool, profile)?;
+
+    Ok(respond(StatusCode::OK, &user))
+}
+
+/// Users own boards and other data, so deleting them only deactivates the account
+#[delete("/{user_id}")]
+async fn delete_user(
+    _client: ScimClient,
+    pool: Data<DbPool>,
+    Path(user_id): Path<Uuid>,
+) -> Result<HttpResponse, Error> {
+    let user = find_user(&pool, user_id)?;
+
+    let profile = UserProfile {
+        mail: user.mail.clone().unwrap_or_default(),
+        name: user.name.clone(),
+        active: false,
+        external_id: user.external_id.clone(),
+    };
+    user.update_profile(&pool, profile)?;
+
+    Ok(HttpResponse::NoContent().finish())
+}
@@ -0,0 +1,81 @@
+use actix_web::{
+    get,
+    web::{Data, Path, Query, ServiceConfig},
+    Error, HttpResponse,
+};
+use diesel::prelude::*;
+use serde_json::{json, to_value, Value};
+
+use super::cards::RenderQuery;
+use crate::{
+    errors::ServiceError,
+    get_conn,
+    models::{Board, Card, List, Share},
+    schema::{cards, lists},
+    AttachmentConfig, DbPool,
+};
+
+pub fn config(cfg: &mut ServiceConfig) {
+    cfg.service(get_shared_board);
+}
+
+/// Read-only view of a board with its lists and cards, accessible without authentication.
+/// Archived boards, lists and cards aren't shown.
+#[get("/{token}")]
+async fn get_shared_board(
+    pool: Data<DbPool>,
+    config: Data<AttachmentConfig>,
+    Path(token): Path<String>,
+    query: Query<RenderQuery>,
+) -> Result<HttpResponse, Error> {
+    let conn = get_conn(&pool)?;
+
+    let share = match Share::find_by_token(&pool, &token)? {
+        Some(share) => share,
+        None => Err(HttpResponse::NotFound().finish())?,
+    };
+
+    let board = match Board::find(&pool, share.board)? {
+        Some(board) if !board.is_archived() => board,
+        _ => Err(HttpResponse::NotFound().finish())?,
+    };
+
+    let lists = List::belonging_to(&board)
+        .filter(lists::archived_at.is_null())
+        .order((lists::position, lists::id))
+        .load::<List>(&conn)
+        .map_err(|_| ServiceError::InternalServerError)?;
+    let cards = Card::belonging_to(&lists)
+        .filter(cards::archived_at.is_null())
+        .order((cards::position, cards::id))
+        .load::<Card>(&conn)
+        .map_err(|_| ServiceError::InternalServerError)?
+        .grouped_by(&lists);
+
+    let lists = List::render(&pool, lists)?
+        .into_iter()
+        .zip(cards)
+        .map(|(list, cards)| {
+            let cards = Card::render(&pool, &config, cards, query.html(), true)?
+                .into_iter()
+                .map(|card| {
+                    let mut card = to_value(card)?;
+                    share.redact_card(&mut card);
+                    Ok(card)
+                })
+                .collect::<Result<Vec<Value>, serde_json::Error>>()
+                .map_err(|_| ServiceError::InternalServerError)?;
+
+            let mut list = to_value(list).map_err(|_| ServiceError::InternalServerError)?;
+            share.redact(&mut list);
+            list["cards"] = Value::Array(cards);
+            Ok(list)
+        })
+        .collect::<Result<Vec<Value>, ServiceError>>()?;
+
+    let mut board =
+        to_value(board.render_one(&pool)?).map_err(|_| ServiceError::InternalServerError)?;
+    share.redact(&mut board);
+
+    Ok(HttpResponse::Ok().json(json!({ "board": board, "lists": lists })))
+}
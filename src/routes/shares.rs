@@ -0,0 +1,89 @@
+use actix_web::{
+    delete, get, post,
+    web::{Data, Json, Path, ServiceConfig},
+    Error, HttpResponse,
+};
+use diesel::prelude::*;
+use uuid::Uuid;
+
+use crate::{
+    errors::ServiceError,
+    get_conn,
+    models::{Board, Role, Share, User},
+    DbPool,
+};
+
+pub fn config(cfg: &mut ServiceConfig) {
+    cfg.service(new_share)
+        .service(get_shares)
+        .service(delete_share);
+}
+
+#[post("")]
+async fn new_share(
+    pool: Data<DbPool>,
+    user: User,
+    Path(board_id): Path<Uuid>,
+    Json(data): Json<Share>,
+) -> Result<HttpResponse, Error> {
+    if let Some(board) = Board::find(&pool, board_id)? {
+        if !board.allows(&pool, &user, Role::Admin)? {
+            Err(HttpResponse::Unauthorized().finish())?
+        }
+
+        let share = Share::new(&board, data.expires_at, data.hidden_fields)?;
+        share.save(&pool)?;
+
+        Ok(HttpResponse::Created()
+            .header("Location", format!("/{}", share.id))
+            .json(share))
+    } else {
+        Err(HttpResponse::NotFound().finish())?
+    }
+}
+
+#[get("")]
+async fn get_shares(
+    pool: Data<DbPool>,
+    user: User,
+    Path(board_id): Path<Uuid>,
+) -> Result<HttpResponse, Error> {
+    let conn = get_conn(&pool)?;
+
+    if let Some(board) = Board::find(&pool, board_id)? {
+        if !board.allows(&pool, &user, Role::Admin)? {
+            Err(HttpResponse::Unauthorized().finish())?
+        }
+
+        let shares = Share::belonging_to(&board)
+            .load::<Share>(&conn)
+            .map_err(|_| ServiceError::InternalServerError)?;
+
+        Ok(HttpResponse::Ok().json(shares))
+    } else {
+        Err(HttpResponse::NotFound().finish())?
+    }
+}
+
+#[delete("/{share_id}")]
+async fn delete_share(
+    pool: Data<DbPool>,
+    user: User,
+    Path((board_id, share_id)): Path<(Uuid, Uuid)>,
+) -> Result<HttpResponse, Error> {
+    if let Some(board) = Board::find(&pool, board_id)? {
+        if !board.allows(&pool, &user, Role::Admin)? {
+            Err(HttpResponse::Unauthorized().finish())?
+        }
+
+        if let Some(share) = Share::find(&pool, share_id)? {
+            if share.board != board.id {
+                Err(HttpResponse::BadRequest().finish())?
+            }
+
+            share.delete(&pool)?;
+        }
+    }
+
+    Ok(HttpResponse::NoContent().finish())
+}
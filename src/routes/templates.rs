@@ -0,0 +1,141 @@
+use actix_web::{
+    delete, get, post,
+    web::{Data, Json, Path, ServiceConfig},
+    Error, HttpResponse,
+};
+use serde::Deserialize;
+use uuid::Uuid;
+
+use crate::{
+    models::{Board, Role, Template, User},
+    DbPool,
+};
+
+pub fn config(cfg: &mut ServiceConfig) {
+    cfg.service(new_template)
+        .service(get_templates)
+        .service(get_template)
+        .service(delete_template)
+        .service(new_board);
+}
+
+/// Saves the layout of `board` as a template, with its cards if `cards` is set
+#[derive(Deserialize)]
+struct NewTemplate {
+    board: Uuid,
+    name: String,
+    description: Option<String>,
+    #[serde(default)]
+    public: bool,
+    #[serde(default)]
+    cards: bool,
+}
+
+#[derive(Deserialize)]
+struct NewBoard {
+    name: String,
+    description: Option<String>,
+}
+
+/// Finds a template the user can use
+fn find_usable(pool: &Data<DbPool>, user: &User, id: Uuid) -> Result<Template, Error> {
+    match Template::find(pool, id)? {
+        Some(template) if template.is_usable_by(user) => Ok(template),
+        _ => Err(HttpResponse::NotFound().finish())?,
+    }
+}
+
+/// Editors of a board can save it as a template, only its admins can publish one
+#[post("")]
+async fn new_template(
+    pool: Data<DbPool>,
+    user: User,
+    Json(data): Json<NewTemplate>,
+) -> Result<HttpResponse, Error> {
+    if data.name.trim().is_empty() {
+        Err(HttpResponse::BadRequest().finish())?
+    }
+
+    if let Some(board) = Board::find(&pool, data.board)? {
+        let role = if data.public {
+            Role::Admin
+        } else {
+            Role::Editor
+        };
+        if !board.allows(&pool, &user, role)? {
+            Err(HttpResponse::Unauthorized().finish())?
+        }
+
+        let template = Template::from_board(
+            &pool,
+            &board,
+            &user,
+            data.name,
+            data.description,
+            data.public,
+            data.cards,
+        )?;
+
+        Ok(HttpResponse::Created()
+            .header("Location", format!("/{}", template.id))
+            .json(template))
+    } else {
+        Err(HttpResponse::NotFound().finish())?
+    }
+}
+
+/// Returns the public templates and those of the current user
+#[get("")]
+async fn get_templates(pool: Data<DbPool>, user: User) -> Result<HttpResponse, Error> {
+    let templates = Template::usable_by(&pool, &user)?;
+
+    Ok(HttpResponse::Ok().json(templates))
+}
+
+#[get("/{template_id}")]
+async fn get_template(
+    pool: Data<DbPool>,
+    user: User,
+    Path(template_id): Path<Uuid>,
+) -> Result<HttpResponse, Error> {
+    let template = find_usable(&pool, &user, template_id)?;
+
+    Ok(HttpResponse::Ok().json(template))
+}
+
+#[delete("/{template_id}")]
+async fn delete_template(
+    pool: Data<DbPool>,
+    user: User,
+    Path(template_id): Path<Uuid>,
+) -> Result<HttpResponse, Error> {
+    if let Some(template) = Template::find(&pool, template_id)? {
+        if template.owner != user.id {
+            Err(HttpResponse::Unauthorized().finish())?
+        }
+
+        template.delete(&pool)?;
+    }
+
+    Ok(HttpResponse::NoContent().finish())
+}
+
+/// Creates a board owned by the current user from the template
+#[post("/{template_id}/boards")]
+async fn new_board(
+    pool: Data<DbPool>,
+    user: User,
+    Path(template_id): Path<Uuid>,
+    Json(data): Json<NewBoard>,
+) -> Result<HttpResponse, Error> {
+    if data.name.trim().is_empty() {
+        Err(HttpResponse::BadRequest().finish())?
+    }
+
+    let template = find_usable(&pool, &user, template_id)?;
+    let board = template.instantiate(&pool, &user, data.name, data.description)?;
+
+    Ok(HttpResponse::Created()
+        .header("Location", format!("/boards/{}", board.id))
+        .json(board))
+}
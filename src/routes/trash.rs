@@ -0,0 +1,122 @@
+use actix_web::{
+    delete, get, post,
+    web::{Data, Path, ServiceConfig},
+    Error, HttpResponse,
+};
+use uuid::Uuid;
+
+use crate::{
+    models::{Board, Card, List, Role, TrashItem, TrashKind, User},
+    storage::{remove_blobs, BlobStore},
+    DbPool, TrashConfig,
+};
+
+pub fn config(cfg: &mut ServiceConfig) {
+    cfg.service(get_trash)
+        .service(restore_item)
+        .service(delete_item);
+}
+
+/// Returns the boards, lists and cards the current user deleted, most recent first
+#[get("")]
+async fn get_trash(
+    pool: Data<DbPool>,
+    config: Data<TrashConfig>,
+    user: User,
+) -> Result<HttpResponse, Error> {
+    let items = TrashItem::for_user(&pool, &user, &config)?;
+
+    Ok(HttpResponse::Ok().json(items))
+}
+
+/// Fails unless the board is outside of the trash and `user` can edit it
+fn check_board(pool: &Data<DbPool>, user: &User, board_id: Uuid) -> Result<(), Error> {
+    let board = match Board::find(pool, board_id)? {
+        Some(board) => board,
+        None => Err(HttpResponse::NotFound().finish())?,
+    };
+
+    if !board.allows(pool, user, Role::Editor)? {
+        Err(HttpResponse::Unauthorized().finish())?
+    }
+
+    Ok(())
+}
+
+/// Finds a board `user` deleted, only owners can delete boards
+fn trashed_board(pool: &Data<DbPool>, user: &User, id: Uuid) -> Result<Board, Error> {
+    match Board::find_trashed(pool, id)? {
+        Some(board) if board.deleted_by == Some(user.id) && board.owner == user.id => Ok(board),
+        _ => Err(HttpResponse::NotFound().finish())?,
+    }
+}
+
+/// Finds a list `user` deleted and checks that they can still edit its board
+fn trashed_list(pool: &Data<DbPool>, user: &User, id: Uuid) -> Result<List, Error> {
+    let list = match List::find_trashed(pool, id)? {
+        Some(list) if list.deleted_by == Some(user.id) => list,
+        _ => Err(HttpResponse::NotFound().finish())?,
+    };
+
+    check_board(pool, user, list.board)?;
+
+    Ok(list)
+}
+
+/// Finds a card `user` deleted and checks that they can still edit its board
+fn trashed_card(pool: &Data<DbPool>, user: &User, id: Uuid) -> Result<Card, Error> {
+    let card = match Card::find_trashed(pool, id)? {
+        Some(card) if card.deleted_by == Some(user.id) => card,
+        _ => Err(HttpResponse::NotFound().finish())?,
+    };
+
+    match List::find(pool, card.list)? {
+        Some(list) => check_board(pool, user, list.board)?,
+        None => Err(HttpResponse::NotFound().finish())?,
+    }
+
+    Ok(card)
+}
+
+/// Takes an item out of the trash along with its contents. Lists and cards can only be
+/// restored while their board and list aren't in the trash themselves.
+#[post("/{kind}/{id}/restore")]
+async fn restore_item(
+    pool: Data<DbPool>,
+    user: User,
+    Path((kind, id)): Path<(TrashKind, Uuid)>,
+) -> Result<HttpResponse, Error> {
+    match kind {
+        TrashKind::Board => {
+            let board = trashed_board(&pool, &user, id)?.recover(&pool)?;
+            Ok(HttpResponse::Ok().json(board))
+        }
+        TrashKind::List => {
+            let list = trashed_list(&pool, &user, id)?.recover(&pool)?;
+            Ok(HttpResponse::Ok().json(list))
+        }
+        TrashKind::Card => {
+            let card = trashed_card(&pool, &user, id)?.recover(&pool)?;
+            Ok(HttpResponse::Ok().json(card))
+        }
+    }
+}
+
+/// Permanently deletes an item with its contents before its retention ends, returning
+/// the numbers of removed items
+#[delete("/{kind}/{id}")]
+async fn delete_item(
+    pool: Data<DbPool>,
+    store: Data<dyn BlobStore>,
+    user: User,
+    Path((kind, id)): Path<(TrashKind, Uuid)>,
+) -> Result<HttpResponse, Error> {
+    let removed = match kind {
+        TrashKind::Board => trashed_board(&pool, &user, id)?.delete(&pool)?,
+        TrashKind::List => trashed_list(&pool, &user, id)?.delete(&pool)?,
+        TrashKind::Card => trashed_card(&pool, &user, id)?.delete(&pool)?,
+    };
+    remove_blobs(&store, &removed.blobs).await;
+
+    Ok(HttpResponse::Ok().json(removed))
+}
@@ -38,8 +38,13 @@ async fn get_user(pool: Data<DbPool>, Path(user_id): Path<Uuid>) -> Result<HttpR
 async fn new_user(pool: Data<DbPool>, Json(data): Json<User>) -> Result<HttpResponse, Error> {
     let conn = get_conn(&pool)?;
 
+    let (mail, password) = match (data.mail, data.password) {
+        (Some(mail), Some(password)) => (mail, password),
+        _ => Err(HttpResponse::BadRequest().finish())?,
+    };
+
     let count = users::table
-        .filter(users::mail.eq(data.mail.clone()))
+        .filter(users::mail.eq(mail.clone()))
         .count()
         .get_result::<i64>(&conn)
         .map_err(|_| ServiceError::InternalServerError)?;
@@ -50,11 +55,11 @@ async fn new_user(pool: Data<DbPool>, Json(data): Json<User>) -> Result<HttpResp
 
     let salt = SaltString::generate(&mut OsRng);
     let password_hash = Argon2::default()
-        .hash_password(data.password.as_bytes(), &salt)
+        .hash_password(password.as_bytes(), &salt)
         .unwrap()
         .to_string();
 
-    let user = User::new(data.mail, password_hash);
+    let user = User::new(mail, password_hash, data.name);
     user.save(&pool)?;
 
     Ok(HttpResponse::Created()
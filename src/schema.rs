@@ -1,9 +1,78 @@
+table! {
+    api_keys (id) {
+        id -> Uuid,
+        account -> Uuid,
+        name -> Text,
+        hash -> Text,
+        created_at -> Timestamptz,
+        last_used_at -> Nullable<Timestamptz>,
+    }
+}
+
+table! {
+    attachments (id) {
+        id -> Uuid,
+        card -> Uuid,
+        uploader -> Nullable<Uuid>,
+        name -> Text,
+        content_type -> Text,
+        size -> Int8,
+        storage_key -> Text,
+        created_at -> Timestamptz,
+        thumbnails -> Array<Int4>,
+    }
+}
+
+table! {
+    audit_log (id) {
+        id -> Uuid,
+        actor -> Uuid,
+        subject -> Uuid,
+        method -> Text,
+        path -> Text,
+        created_at -> Timestamptz,
+    }
+}
+
+table! {
+    board_members (board, member) {
+        board -> Uuid,
+        member -> Uuid,
+        role -> Text,
+    }
+}
+
 table! {
     boards (id) {
         id -> Uuid,
         owner -> Uuid,
         name -> Text,
         description -> Nullable<Text>,
+        archived_at -> Nullable<Timestamptz>,
+        deleted_at -> Nullable<Timestamptz>,
+        deleted_by -> Nullable<Uuid>,
+    }
+}
+
+table! {
+    card_assignees (card, account) {
+        card -> Uuid,
+        account -> Uuid,
+    }
+}
+
+table! {
+    card_fields (card, field) {
+        card -> Uuid,
+        field -> Uuid,
+        value -> Jsonb,
+    }
+}
+
+table! {
+    card_labels (card, label) {
+        card -> Uuid,
+        label -> Uuid,
     }
 }
 
@@ -11,8 +80,85 @@ table! {
     cards (id) {
         id -> Uuid,
         list -> Uuid,
-        content -> Nullable<Text>,
-        labels -> Nullable<Array<Text>>,
+        title -> Text,
+        description -> Nullable<Text>,
+        position -> Text,
+        start_at -> Nullable<Timestamptz>,
+        due_at -> Nullable<Timestamptz>,
+        completed -> Bool,
+        priority -> Nullable<Int2>,
+        estimate -> Nullable<Float8>,
+        archived_at -> Nullable<Timestamptz>,
+        deleted_at -> Nullable<Timestamptz>,
+        deleted_by -> Nullable<Uuid>,
+    }
+}
+
+table! {
+    checklist_items (id) {
+        id -> Uuid,
+        checklist -> Uuid,
+        content -> Text,
+        checked -> Bool,
+        assignee -> Nullable<Uuid>,
+        due_at -> Nullable<Timestamptz>,
+        position -> Text,
+    }
+}
+
+table! {
+    checklists (id) {
+        id -> Uuid,
+        card -> Uuid,
+        name -> Text,
+        position -> Text,
+    }
+}
+
+table! {
+    comments (id) {
+        id -> Uuid,
+        card -> Uuid,
+        author -> Nullable<Uuid>,
+        content -> Text,
+        created_at -> Timestamptz,
+        edited_at -> Nullable<Timestamptz>,
+    }
+}
+
+table! {
+    custom_fields (id) {
+        id -> Uuid,
+        board -> Uuid,
+        name -> Text,
+        kind -> Text,
+        options -> Array<Text>,
+    }
+}
+
+table! {
+    group_members (group_id, member) {
+        group_id -> Uuid,
+        member -> Uuid,
+    }
+}
+
+table! {
+    groups (id) {
+        id -> Uuid,
+        name -> Text,
+        origin -> Text,
+        external_id -> Nullable<Text>,
+    }
+}
+
+table! {
+    labels (id) {
+        id -> Uuid,
+        board -> Uuid,
+        name -> Text,
+        color -> Text,
+        description -> Nullable<Text>,
     }
 }
 
@@ -21,19 +167,125 @@ table! {
         id -> Uuid,
         board -> Uuid,
         name -> Text,
+        position -> Text,
+        wip_limit -> Nullable<Int4>,
+        wip_soft -> Bool,
+        archived_at -> Nullable<Timestamptz>,
+        deleted_at -> Nullable<Timestamptz>,
+        deleted_by -> Nullable<Uuid>,
+    }
+}
+
+table! {
+    notifications (id) {
+        id -> Uuid,
+        account -> Uuid,
+        card -> Uuid,
+        message -> Text,
+        created_at -> Timestamptz,
+        read_at -> Nullable<Timestamptz>,
+    }
+}
+
+table! {
+    reminders (card, account) {
+        card -> Uuid,
+        account -> Uuid,
+        minutes_before -> Int4,
+        sent_at -> Nullable<Timestamptz>,
+    }
+}
+
+table! {
+    shares (id) {
+        id -> Uuid,
+        board -> Uuid,
+        token -> Text,
+        created_at -> Timestamptz,
+        expires_at -> Nullable<Timestamptz>,
+        hidden_fields -> Array<Text>,
+    }
+}
+
+table! {
+    templates (id) {
+        id -> Uuid,
+        owner -> Uuid,
+        name -> Text,
+        description -> Nullable<Text>,
+        public -> Bool,
+        content -> Jsonb,
+        created_at -> Timestamptz,
     }
 }
 
 table! {
     users (id) {
         id -> Uuid,
-        mail -> Text,
-        password -> Text,
+        mail -> Nullable<Text>,
+        password -> Nullable<Text>,
+        admin -> Bool,
+        name -> Nullable<Text>,
+        bot -> Bool,
+        bot_owner -> Nullable<Uuid>,
+        active -> Bool,
+        external_id -> Nullable<Text>,
     }
 }
 
+joinable!(api_keys -> users (account));
+joinable!(attachments -> cards (card));
+joinable!(attachments -> users (uploader));
+joinable!(board_members -> boards (board));
+joinable!(board_members -> users (member));
 joinable!(boards -> users (owner));
+joinable!(card_assignees -> cards (card));
+joinable!(card_assignees -> users (account));
+joinable!(card_fields -> cards (card));
+joinable!(card_fields -> custom_fields (field));
+joinable!(card_labels -> cards (card));
+joinable!(card_labels -> labels (label));
 joinable!(cards -> lists (list));
+joinable!(cards -> users (deleted_by));
+joinable!(checklist_items -> checklists (checklist));
+joinable!(checklist_items -> users (assignee));
+joinable!(checklists -> cards (card));
+joinable!(comments -> cards (card));
+joinable!(comments -> users (author));
+joinable!(custom_fields -> boards (board));
+joinable!(group_members -> groups (group_id));
+joinable!(group_members -> users (member));
+joinable!(labels -> boards (board));
 joinable!(lists -> boards (board));
+joinable!(lists -> users (deleted_by));
+joinable!(notifications -> cards (card));
+joinable!(notifications -> users (account));
+joinable!(reminders -> cards (card));
+joinable!(reminders -> users (account));
+joinable!(shares -> boards (board));
+joinable!(templates -> users (owner));
 
-allow_tables_to_appear_in_same_query!(boards, cards, lists, users,);
+allow_tables_to_appear_in_same_query!(
+    api_keys,
+    attachments,
+    audit_log,
+    board_members,
+    boards,
+    card_assignees,
+    card_fields,
+    card_labels,
+    cards,
+    checklist_items,
+    checklists,
+    comments,
+    custom_fields,
+    group_members,
+    groups,
+    labels,
+    lists,
+    notifications,
+    reminders,
+    shares,
+    templates,
+    users,
+);
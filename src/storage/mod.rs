@@ -0,0 +1,110 @@
+use std::{fs, io, path::PathBuf};
+
+use actix_web::web::{self, Bytes, Data};
+use async_trait::async_trait;
+use log::error;
+
+use crate::errors::ServiceError;
+
+mod s3;
+
+pub use s3::S3Store;
+
+/// Stores the contents of uploaded files, addressed by opaque keys
+#[async_trait(?Send)]
+pub trait BlobStore: Send + Sync {
+    async fn put(&self, key: &str, content_type: &str, data: Bytes) -> Result<(), ServiceError>;
+
+    /// Returns `None` if there is no blob with the key
+    async fn get(&self, key: &str) -> Result<Option<Bytes>, ServiceError>;
+
+    /// Deleting a missing blob is not an error
+    async fn delete(&self, key: &str) -> Result<(), ServiceError>;
+}
+
+/// Deletes the blobs of removed attachments. Failures are only logged, the attachments
+/// are already gone and leftover blobs can't be reached anymore.
+pub async fn remove_blobs(store: &Data<dyn BlobStore>, keys: &[String]) {
+    for key in keys {
+        if let Err(e) = store.delete(key).await {
+            error!("Could not delete blob {}: {}", key, e);
+        }
+    }
+}
+
+/// Copies the blob at `from` to `to`, failing if there is no blob at `from`
+pub async fn copy_blob(
+    store: &Data<dyn BlobStore>,
+    from: &str,
+    to: &str,
+    content_type: &str,
+) -> Result<(), ServiceError> {
+    match store.get(from).await? {
+        Some(data) => store.put(to, content_type, data).await,
+        None => Err(ServiceError::InternalServerError),
+    }
+}
+
+/// Keeps blobs as files in a directory
+pub struct LocalStore {
+    root: PathBuf,
+}
+
+impl LocalStore {
+    pub fn new(root: impl Into<PathBuf>) -> Self {
+        let root = root.into();
+        fs::create_dir_all(&root).expect("Could not create the storage directory");
+
+        LocalStore { root }
+    }
+
+    /// Keys are generated by the server, anything that could escape the root is rejected
+    fn path(&self, key: &str) -> Result<PathBuf, ServiceError> {
+        if key.is_empty() || !key.chars().all(|c| c.is_ascii_alphanumeric() || c == '-') {
+            return Err(ServiceError::InternalServerError);
+        }
+
+        Ok(self.root.join(key))
+    }
+}
+
+/// Runs blocking file system calls on the thread pool
+async fn blocking<T, F>(f: F) -> Result<T, ServiceError>
+where
+    F: FnOnce() -> io::Result<T> + Send + 'static,
+    T: Send + 'static,
+{
+    web::block(f)
+        .await
+        .map_err(|_| ServiceError::InternalServerError)
+}
+
+#[async_trait(?Send)]
+impl BlobStore for LocalStore {
+    async fn put(&self, key: &str, _content_type: &str, data: Bytes) -> Result<(), ServiceError> {
+        let path = self.path(key)?;
+
+        blocking(move || fs::write(path, data)).await
+    }
+
+    async fn get(&self, key: &str) -> Result<Option<Bytes>, ServiceError> {
+        let path = self.path(key)?;
+
+        blocking(move || match fs::read(&path) {
+            Ok(data) => Ok(Some(Bytes::from(data))),
+            Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(None),
+            Err(e) => Err(e),
+        })
+        .await
+    }
+
+    async fn delete(&self, key: &str) -> Result<(), ServiceError> {
+        let path = self.path(key)?;
+
+        blocking(move || match fs::remove_file(&path) {
+            Err(e) if e.kind() != io::ErrorKind::NotFound => Err(e),
+            _ => Ok(()),
+        })
+        .await
+    }
+}
@@ -0,0 +1,160 @@
+use std::env;
+
+use actix_web::{http::StatusCode, web::Bytes};
+use async_trait::async_trait;
+use awc::Client;
+use chrono::Utc;
+use hmac::{Hmac, Mac};
+use sha2::{Digest, Sha256};
+
+use super::BlobStore;
+use crate::errors::ServiceError;
+
+/// Largest blob that is read back from the bucket
+const MAX_DOWNLOAD_SIZE: usize = 1024 * 1024 * 1024;
+
+/// Stores blobs in a bucket of an S3 compatible service, e.g. AWS S3 or MinIO.
+/// Buckets are addressed path-style, which every implementation supports.
+pub struct S3Store {
+    endpoint: String,
+    host: String,
+    bucket: String,
+    region: String,
+    access_key: String,
+    secret_key: String,
+}
+
+impl S3Store {
+    pub fn from_env() -> Self {
+        let endpoint = env::var("S3_ENDPOINT").expect("S3_ENDPOINT must be set");
+        let endpoint = endpoint.trim_end_matches('/').to_string();
+        let host = endpoint
+            .split("://")
+            .nth(1)
+            .expect("S3_ENDPOINT must be a URL")
+            .to_string();
+
+        S3Store {
+            endpoint,
+            host,
+            bucket: env::var("S3_BUCKET").expect("S3_BUCKET must be set"),
+            region: env::var("S3_REGION").unwrap_or_else(|_| "us-east-1".to_string()),
+            access_key: env::var("S3_ACCESS_KEY").expect("S3_ACCESS_KEY must be set"),
+            secret_key: env::var("S3_SECRET_KEY").expect("S3_SECRET_KEY must be set"),
+        }
+    }
+
+    fn path(&self, key: &str) -> String {
+        format!("/{}/{}", self.bucket, key)
+    }
+
+    /// Returns the headers authenticating a request with AWS Signature Version 4
+    fn sign(&self, method: &str, path: &str, payload: &[u8]) -> Vec<(&'static str, String)> {
+        let now = Utc::now();
+        let date = now.format("%Y%m%d").to_string();
+        let timestamp = now.format("%Y%m%dT%H%M%SZ").to_string();
+        let payload_hash = hex::encode(Sha256::digest(payload));
+
+        let signed_headers = "host;x-amz-content-sha256;x-amz-date";
+        let canonical_request = format!(
+            "{}\n{}\n\nhost:{}\nx-amz-content-sha256:{}\nx-amz-date:{}\n\n{}\n{}",
+            method, path, self.host, payload_hash, timestamp, signed_headers, payload_hash
+        );
+
+        let scope = format!("{}/{}/s3/aws4_request", date, self.region);
+        let string_to_sign = format!(
+            "AWS4-HMAC-SHA256\n{}\n{}\n{}",
+            timestamp,
+            scope,
+            hex::encode(Sha256::digest(canonical_request.as_bytes()))
+        );
+
+        let key = format!("AWS4{}", self.secret_key);
+        let key = hmac(key.as_bytes(), date.as_bytes());
+        let key = hmac(&key, self.region.as_bytes());
+        let key = hmac(&key, b"s3");
+        let key = hmac(&key, b"aws4_request");
+        let signature = hex::encode(hmac(&key, string_to_sign.as_bytes()));
+
+        vec![
+            ("x-amz-content-sha256", payload_hash),
+            ("x-amz-date", timestamp),
+            (
+                "authorization",
+                format!(
+                    "AWS4-HMAC-SHA256 Credential={}/{}, SignedHeaders={}, Signature={}",
+                    self.access_key, scope, signed_headers, signature
+                ),
+            ),
+        ]
+    }
+
+    /// Sends a signed request, returning the status and body of the response
+    async fn request(
+        &self,
+        method: &str,
+        key: &str,
+        content_type: Option<&str>,
+        payload: Bytes,
+    ) -> Result<(StatusCode, Bytes), ServiceError> {
+        let path = self.path(key);
+        let url = format!("{}{}", self.endpoint, path);
+
+        // Clients are bound to the current thread, so each request gets its own
+        let client = Client::default();
+        let mut request = match method {
+            "PUT" => client.put(url),
+            "DELETE" => client.delete(url),
+            _ => client.get(url),
+        };
+        for (header, value) in self.sign(method, &path, &payload) {
+            request = request.header(header, value);
+        }
+        if let Some(content_type) = content_type {
+            request = request.content_type(content_type);
+        }
+
+        let mut response = request
+            .send_body(payload)
+            .await
+            .map_err(|_| ServiceError::InternalServerError)?;
+        let body = response
+            .body()
+            .limit(MAX_DOWNLOAD_SIZE)
+            .await
+            .map_err(|_| ServiceError::InternalServerError)?;
+
+        Ok((response.status(), body))
+    }
+}
+
+fn hmac(key: &[u8], data: &[u8]) -> Vec<u8> {
+    let mut mac = Hmac::<Sha256>::new_from_slice(key).expect("HMAC accepts keys of any size");
+    mac.update(data);
+    mac.finalize().into_bytes().to_vec()
+}
+
+#[async_trait(?Send)]
+impl BlobStore for S3Store {
+    async fn put(&self, key: &str, content_type: &str, data: Bytes) -> Result<(), ServiceError> {
+        match self.request("PUT", key, Some(content_type), data).await? {
+            (status, _) if status.is_success() => Ok(()),
+            _ => Err(ServiceError::InternalServerError),
+        }
+    }
+
+    async fn get(&self, key: &str) -> Result<Option<Bytes>, ServiceError> {
+        match self.request("GET", key, None, Bytes::new()).await? {
+            (status, body) if status.is_success() => Ok(Some(body)),
+            (StatusCode::NOT_FOUND, _) => Ok(None),
+            _ => Err(ServiceError::InternalServerError),
+        }
+    }
+
+    async fn delete(&self, key: &str) -> Result<(), ServiceError> {
+        match self.request("DELETE", key, None, Bytes::new()).await? {
+            (status, _) if status.is_success() || status == StatusCode::NOT_FOUND => Ok(()),
+            _ => Err(ServiceError::InternalServerError),
+        }
+    }
+}
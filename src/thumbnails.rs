@@ -0,0 +1,97 @@
+//! Downscaled previews of image attachments
+
+use std::io::Cursor;
+
+use actix_web::{
+    rt,
+    web::{self, Bytes, Data},
+};
+use image::{
+    io::{Limits, Reader},
+    ImageOutputFormat,
+};
+use log::error;
+
+use crate::{
+    models::Attachment,
+    storage::{remove_blobs, BlobStore},
+    DbPool,
+};
+
+/// Longest edge of the generated thumbnails in pixels
+pub const SIZES: &[u32] = &[64, 256, 1024];
+
+/// Images larger than this in either dimension are not decoded
+const MAX_DIMENSION: u32 = 16384;
+
+pub const CONTENT_TYPE: &str = "image/png";
+
+pub fn supports(content_type: &str) -> bool {
+    matches!(
+        content_type.to_lowercase().as_str(),
+        "image/png" | "image/jpeg" | "image/gif" | "image/webp"
+    )
+}
+
+/// Encodes a PNG thumbnail for every size smaller than the image, images are
+/// never scaled up
+fn generate(data: &[u8]) -> image::ImageResult<Vec<(u32, Vec<u8>)>> {
+    let mut limits = Limits::default();
+    limits.max_image_width = Some(MAX_DIMENSION);
+    limits.max_image_height = Some(MAX_DIMENSION);
+
+    let mut reader = Reader::new(Cursor::new(data)).with_guessed_format()?;
+    reader.limits(limits);
+    let image = reader.decode()?;
+    let longest = image.width().max(image.height());
+
+    let mut thumbnails = Vec::new();
+    for &size in SIZES.iter().filter(|&&size| size < longest) {
+        let mut encoded = Vec::new();
+        image
+            .thumbnail(size, size)
+            .write_to(&mut Cursor::new(&mut encoded), ImageOutputFormat::Png)?;
+        thumbnails.push((size, encoded));
+    }
+
+    Ok(thumbnails)
+}
+
+/// Generates the thumbnails of a freshly uploaded image in the background. Failures are
+/// only logged, the attachment is still served without previews.
+pub fn spawn(pool: Data<DbPool>, store: Data<dyn BlobStore>, attachment: &Attachment, data: Bytes) {
+    let id = attachment.id;
+    let storage_key = attachment.storage_key.clone();
+
+    rt::spawn(async move {
+        let thumbnails = match web::block(move || generate(&data)).await {
+            Ok(thumbnails) => thumbnails,
+            Err(e) => {
+                error!("Could not generate thumbnails of attachment {}: {}", id, e);
+                return;
+            }
+        };
+
+        let mut keys = Vec::new();
+        let mut sizes = Vec::new();
+        for (size, encoded) in thumbnails {
+            let key = Attachment::thumbnail_key(&storage_key, size);
+            if let Err(e) = store.put(&key, CONTENT_TYPE, Bytes::from(encoded)).await {
+                error!("Could not store thumbnail {}: {}", key, e);
+                break;
+            }
+            keys.push(key);
+            sizes.push(size as i32);
+        }
+
+        match web::block(move || Attachment::set_thumbnails(&pool, id, sizes)).await {
+            Ok(1) => {}
+            // The attachment was deleted in the meantime
+            Ok(_) => remove_blobs(&store, &keys).await,
+            Err(e) => {
+                error!("Could not save thumbnails of attachment {}: {}", id, e);
+                remove_blobs(&store, &keys).await;
+            }
+        }
+    });
+}
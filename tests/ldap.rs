@@ -0,0 +1,45 @@
+//! Runs against the local directory described in the README:
+//! `cargo test --test ldap -- --ignored`
+
+use std::env;
+
+use backend::{auth::LdapBackend, errors::ServiceError};
+
+fn backend() -> LdapBackend {
+    let defaults = [
+        ("LDAP_URL", "ldap://localhost:389"),
+        ("LDAP_BASE_DN", "dc=kanban,dc=local"),
+        ("LDAP_BIND_DN", "cn=admin,dc=kanban,dc=local"),
+        ("LDAP_BIND_PASSWORD", "admin"),
+        ("LDAP_USER_FILTER", "(cn={username})"),
+    ];
+    for (name, value) in defaults {
+        if env::var(name).is_err() {
+            env::set_var(name, value);
+        }
+    }
+
+    LdapBackend::from_env()
+}
+
+#[test]
+#[ignore]
+fn lookup() {
+    let backend = backend();
+
+    let user = backend.lookup("admin", "admin").unwrap();
+    assert_eq!(user.dn, "cn=admin,dc=kanban,dc=local");
+
+    assert!(matches!(
+        backend.lookup("admin", "wrong"),
+        Err(ServiceError::InvalidCredentials)
+    ));
+    assert!(matches!(
+        backend.lookup("admin", ""),
+        Err(ServiceError::InvalidCredentials)
+    ));
+    assert!(matches!(
+        backend.lookup("nobody", "admin"),
+        Err(ServiceError::InvalidCredentials)
+    ));
+}